@@ -8,6 +8,7 @@ use gtk::{
 use tokio::runtime::{Builder as RuntimeBuilder, Runtime};
 
 use crate::components::WindowComponent;
+use crate::search_provider;
 
 pub struct Application {
     pub g_app: gtk::Application,
@@ -42,10 +43,23 @@ impl Application {
             .application_id("io.github.fin_ger.OvGUCanteen")
             .build();
 
+        g_app.connect_startup(|app| {
+            if let Err(err) = search_provider::register(app) {
+                log::warn!("search provider not available: {:#}", err);
+            }
+        });
+
         let build_rt = runtime.handle().clone();
-        g_app.connect_activate(move |app| match WindowComponent::new(&build_rt, app) {
-            Ok(()) => {}
-            Err(err) => {
+        g_app.connect_activate(move |app| {
+            // the application gets activated again when it is launched a second time
+            // or a search result gets activated, so reuse the existing window
+            if let Some(window) = app.get_active_window() {
+                log::debug!("presenting existing window");
+                window.present();
+                return;
+            }
+
+            if let Err(err) = WindowComponent::new(&build_rt, app) {
                 log::error!("error starting application: {:#}", err);
                 let dialog = MessageDialogBuilder::new()
                     .buttons(ButtonsType::Close)
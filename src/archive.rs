@@ -0,0 +1,307 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::favorites;
+use crate::model::{Canteen, CanteenDescription, Day};
+
+// the archive is an append-only file in the data directory with one JSON entry per line,
+// it is never pruned, in contrast to the cache
+const FILE_NAME: &str = "archive.jsonl";
+
+// the maximum number of results when searching for the last time a meal was served
+const MAX_LAST_SERVED: usize = 50;
+
+lazy_static! {
+    // the hash of the latest archived version of every day, the archive is only read
+    // for the first append, afterwards the hashes are kept up to date while appending
+    static ref ARCHIVED_HASHES: Mutex<Option<HashMap<(CanteenDescription, NaiveDate), u64>>> = Mutex::new(None);
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    canteen: CanteenDescription,
+    archived: DateTime<Utc>,
+    day: Day,
+}
+
+// the latest version of every archived day
+#[derive(Debug, Default)]
+pub struct Archive {
+    days: BTreeMap<(CanteenDescription, NaiveDate), Day>,
+}
+
+fn try_read() -> Result<Archive> {
+    let xdg = xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk")
+        .context("Could not find data directory")?;
+    let path = match xdg.find_data_file(FILE_NAME) {
+        Some(path) => path,
+        None => return Ok(Archive::default()),
+    };
+    log::debug!("reading archive from {:?}", path);
+
+    let mut archive = Archive::default();
+    let file = File::open(path).context("Could not open archive")?;
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.context("Could not read archive")?;
+        // a crash while appending may leave a broken last line, which is skipped
+        match serde_json::from_str::<Entry>(&line) {
+            // later entries are newer observations of the same day
+            Ok(entry) => {
+                archive.days.insert((entry.canteen, entry.day.date), entry.day);
+            },
+            Err(e) => log::warn!("skipping line {} of archive: {:#}", idx + 1, e),
+        }
+    }
+
+    Ok(archive)
+}
+
+// reads the archive, an unavailable archive results in an empty one
+pub fn read() -> Archive {
+    log::debug!("loading archive");
+
+    match try_read() {
+        Ok(archive) => archive,
+        Err(e) => {
+            log::warn!("failed to read archive: {:#}", e);
+            Archive::default()
+        },
+    }
+}
+
+fn day_hash(day: &Day) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(day)
+        .context("Failed to serialize archived day")?
+        .hash(&mut hasher);
+
+    Ok(hasher.finish())
+}
+
+fn try_append(canteens: &[Canteen]) -> Result<()> {
+    let mut archived_hashes = ARCHIVED_HASHES.lock().map_err(|_e| anyhow!("Archive got poisoned"))?;
+    if archived_hashes.is_none() {
+        let hashes = try_read()?
+            .days
+            .iter()
+            .map(|(key, day)| Ok((key.clone(), day_hash(day)?)))
+            .collect::<Result<_>>()?;
+        *archived_hashes = Some(hashes);
+    }
+    let hashes = archived_hashes.as_mut().context("Archive is not available")?;
+    let archived = Utc::now();
+
+    // only new days and days whose menu changed get appended
+    let mut lines = String::new();
+    let mut appended_hashes = Vec::new();
+    for canteen in canteens {
+        for day in &canteen.days {
+            let key = (canteen.description.clone(), day.date);
+            let hash = day_hash(day)?;
+            if hashes.get(&key) == Some(&hash) {
+                continue;
+            }
+            appended_hashes.push((key, hash));
+
+            let entry = Entry {
+                canteen: canteen.description.clone(),
+                archived,
+                day: day.clone(),
+            };
+            lines.push_str(&serde_json::to_string(&entry).context("Failed to serialize archive entry")?);
+            lines.push('\n');
+        }
+    }
+
+    if lines.is_empty() {
+        log::debug!("archive is up to date");
+        return Ok(());
+    }
+
+    let xdg = xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk")
+        .context("Could not find data directory")?;
+    let path = xdg.place_data_file(FILE_NAME).context("Could not place archive")?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context("Could not open archive")?;
+    file.write_all(lines.as_bytes()).context("Failed to append to archive")?;
+    hashes.extend(appended_hashes);
+
+    Ok(())
+}
+
+// appends all days of the canteens which are not archived yet
+pub fn append(canteens: &[Canteen]) {
+    log::debug!("appending loaded canteens to archive");
+
+    if let Err(e) = try_append(canteens) {
+        log::warn!("failed to append to archive: {:#}", e);
+    }
+
+    log::debug!("finish appending to archive");
+}
+
+impl Archive {
+    // an archive holding the days of the canteens without touching the archive file
+    #[cfg(test)]
+    pub fn from_canteens(canteens: &[Canteen]) -> Self {
+        let mut archive = Self::default();
+        for canteen in canteens {
+            for day in &canteen.days {
+                archive.days.insert((canteen.description.clone(), day.date), day.clone());
+            }
+        }
+        archive
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.days.is_empty()
+    }
+
+    // all archived days with their canteen, ordered by canteen and date
+    pub fn iter(&self) -> impl Iterator<Item = (&CanteenDescription, &Day)> {
+        self.days.iter().map(|((canteen_desc, _date), day)| (canteen_desc, day))
+    }
+
+    pub fn canteens(&self) -> BTreeSet<CanteenDescription> {
+        self.days.keys().map(|(canteen_desc, _date)| canteen_desc.clone()).collect()
+    }
+
+    pub fn day(&self, canteen_desc: &CanteenDescription, date: NaiveDate) -> Option<&Day> {
+        self.days.get(&(canteen_desc.clone(), date))
+    }
+
+    // all dates with an archived menu of the canteen
+    pub fn dates<'a>(&'a self, canteen_desc: &'a CanteenDescription) -> impl Iterator<Item = NaiveDate> + 'a {
+        self.days
+            .keys()
+            .filter(move |(desc, _date)| desc == canteen_desc)
+            .map(|(_desc, date)| *date)
+    }
+
+    // the last date until the given one on which each meal matching all terms got served,
+    // the most recently served meals come first
+    pub fn last_served(&self, query: &str, until: NaiveDate) -> Vec<(CanteenDescription, NaiveDate, String)> {
+        let terms = query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        // meals are identified by their normalized name in each canteen
+        let mut last_served = BTreeMap::new();
+        for ((canteen_desc, date), day) in &self.days {
+            if *date > until {
+                continue;
+            }
+
+            for meal in &day.meals {
+                let name = favorites::normalize(&meal.name);
+                if terms.iter().all(|term| name.contains(term)) {
+                    // the days of a canteen are ordered by date, so later days replace earlier ones
+                    last_served.insert((canteen_desc.clone(), name), (*date, meal.name.clone()));
+                }
+            }
+        }
+
+        let mut results = last_served
+            .into_iter()
+            .map(|((canteen_desc, _name), (date, meal_name))| (canteen_desc, date, meal_name))
+            .collect::<Vec<_>>();
+        results.sort_by(|(_, a, _), (_, b, _)| b.cmp(a));
+        results.truncate(MAX_LAST_SERVED);
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Meal, Price};
+
+    fn day(day: u32, meal_names: &[&str]) -> Day {
+        let meal = |name: &&str| Meal {
+            name: (*name).to_owned(),
+            price: Price {
+                student: 2.0,
+                staff: 3.0,
+                guest: 4.0,
+            },
+            symbols: Vec::new(),
+            additives: Vec::new(),
+            allergenics: Vec::new(),
+        };
+
+        Day {
+            date: NaiveDate::from_ymd(2020, 5, day),
+            meals: meal_names.iter().map(meal).collect(),
+            side_dishes: Vec::new(),
+        }
+    }
+
+    fn canteen(id: &str, days: Vec<Day>) -> Canteen {
+        Canteen {
+            description: CanteenDescription::new(id),
+            name: None,
+            source: None,
+            fetched: None,
+            days,
+        }
+    }
+
+    fn archive() -> Archive {
+        Archive::from_canteens(&[
+            canteen(
+                "UniCampusLowerHall",
+                vec![
+                    day(4, &["Pasta with tomato sauce", "Soup"]),
+                    day(5, &["pasta with  TOMATO sauce"]),
+                    day(11, &["Pasta with tomato sauce"]),
+                ],
+            ),
+            canteen("Herrenkrug", vec![day(6, &["Tomato soup"])]),
+        ])
+    }
+
+    #[test]
+    fn dates_belong_to_their_canteen() {
+        let archive = archive();
+        let lower_hall = CanteenDescription::new("UniCampusLowerHall");
+
+        assert_eq!(archive.canteens().len(), 2);
+        assert_eq!(archive.dates(&lower_hall).count(), 3);
+        assert!(archive.day(&lower_hall, NaiveDate::from_ymd(2020, 5, 6)).is_none());
+    }
+
+    #[test]
+    fn last_served_finds_the_latest_day_until_the_given_one() {
+        let results = archive().last_served("tomato", NaiveDate::from_ymd(2020, 5, 10));
+
+        assert_eq!(
+            results,
+            [
+                (CanteenDescription::new("Herrenkrug"), NaiveDate::from_ymd(2020, 5, 6), "Tomato soup".to_owned()),
+                (
+                    CanteenDescription::new("UniCampusLowerHall"),
+                    NaiveDate::from_ymd(2020, 5, 5),
+                    "pasta with  TOMATO sauce".to_owned()
+                ),
+            ]
+        );
+        assert!(archive().last_served("  ", NaiveDate::from_ymd(2020, 5, 10)).is_empty());
+    }
+}
@@ -0,0 +1,421 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Canteen, CanteenDescription};
+use crate::util;
+
+// the version of the cache files, increase it when the format changes in an incompatible way
+const VERSION: u32 = 1;
+
+// each canteen is cached in its own file inside this directory of the cache home
+const CANTEENS_DIR: &str = "canteens";
+
+// the cache of older versions, a single file containing all canteens
+const LEGACY_HISTORY: &str = "history.json";
+
+// a legacy history cache which could not be parsed is kept here instead of being removed
+const BROKEN_LEGACY_HISTORY: &str = "history.json.broken";
+
+// temporary files of this age are left over from an interrupted write,
+// younger ones might still be written by another instance of the app
+const STALE_TMP_FILE_AGE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    fetched: Option<DateTime<Utc>>,
+    source: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFile {
+    header: Header,
+    canteen: Canteen,
+}
+
+// the legacy history cache kept when a canteen was fetched next to its days
+#[derive(Debug, Deserialize)]
+struct LegacyCanteen {
+    #[serde(default)]
+    fetched: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    canteen: Canteen,
+}
+
+// the header is parsed on its own first, so files of other versions are never parsed as canteens
+#[derive(Debug, Deserialize)]
+struct HeaderOnly {
+    header: Header,
+}
+
+fn xdg() -> Result<xdg::BaseDirectories> {
+    xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk").context("Could not find cache directory")
+}
+
+fn canteens_dir() -> Result<PathBuf> {
+    xdg()?
+        .create_cache_directory(CANTEENS_DIR)
+        .context("Could not create cache directory for canteens")
+}
+
+// the file name of a canteen, its id is provided by the settings for some sources
+fn canteen_file_name(canteen_desc: &CanteenDescription) -> String {
+    let id = canteen_desc
+        .id()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect::<String>();
+    format!("{}.json", id)
+}
+
+fn is_cache_file(path: &Path) -> bool {
+    path.extension().map_or(false, |extension| extension == "json")
+}
+
+// temporary files are left behind when writing a cache file got interrupted
+fn is_tmp_file(path: &Path) -> bool {
+    path.extension().map_or(false, |extension| extension == "tmp")
+}
+
+fn is_stale(path: &Path, now: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| now.duration_since(modified).ok())
+        .map_or(false, |age| age >= STALE_TMP_FILE_AGE)
+}
+
+fn read_canteen(path: &Path) -> Result<Canteen> {
+    let content = fs::read(path).context("Could not read cache file")?;
+
+    let header = serde_json::from_slice::<HeaderOnly>(&content)
+        .context("Failed to parse header of cache file")?
+        .header;
+    if header.version != VERSION {
+        bail!("Cache file has unsupported version {}", header.version);
+    }
+
+    let cache_file = serde_json::from_slice::<CacheFile>(&content).context("Failed to parse cache file")?;
+    let mut canteen = cache_file.canteen;
+    canteen.fetched = cache_file.header.fetched;
+    canteen.source = cache_file.header.source;
+
+    Ok(canteen)
+}
+
+// a crash while writing never leaves a broken cache file behind, as the file is written atomically
+fn write_canteen(dir: &Path, canteen: &Canteen) -> Result<()> {
+    let path = dir.join(canteen_file_name(&canteen.description));
+    log::debug!("writing canteen {:?} to {:?}", canteen.description, path);
+
+    let cache_file = CacheFile {
+        header: Header {
+            version: VERSION,
+            fetched: canteen.fetched,
+            source: canteen.source.clone(),
+        },
+        canteen: canteen.clone(),
+    };
+    let content = serde_json::to_vec(&cache_file).context("Failed to serialize canteen")?;
+
+    util::write_atomically(&path, &content)
+}
+
+// moves the canteens of the legacy history cache into their own files
+fn try_migrate_history() -> Result<()> {
+    let history_path = match xdg()?.find_cache_file(LEGACY_HISTORY) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    log::info!("migrating legacy history cache {:?}", history_path);
+
+    let history_file = File::open(&history_path).context("Could not open legacy history cache")?;
+    let canteens: Vec<Canteen> = match serde_json::from_reader::<_, Vec<LegacyCanteen>>(history_file) {
+        // the fetched timestamp moved into the header of the cache file
+        Ok(canteens) => canteens
+            .into_iter()
+            .map(|legacy| Canteen {
+                fetched: legacy.fetched,
+                ..legacy.canteen
+            })
+            .collect(),
+        // a broken legacy cache cannot be migrated, it is kept for inspection instead
+        Err(e) => {
+            log::warn!("failed to parse legacy history cache: {:#}", e);
+            let broken_path = history_path.with_file_name(BROKEN_LEGACY_HISTORY);
+            fs::rename(&history_path, &broken_path).context("Could not move broken legacy history cache aside")?;
+            log::warn!("moved broken legacy history cache to {:?}", broken_path);
+            return Ok(());
+        },
+    };
+
+    // the legacy cache is only removed once all of its canteens got migrated
+    let dir = canteens_dir()?;
+    for canteen in &canteens {
+        write_canteen(&dir, canteen)?;
+    }
+    fs::remove_file(&history_path).context("Could not remove legacy history cache")?;
+
+    log::debug!("migrated {} canteens from legacy history cache", canteens.len());
+
+    Ok(())
+}
+
+fn try_read_history() -> Result<Vec<Canteen>> {
+    if let Err(e) = try_migrate_history() {
+        log::warn!("failed to migrate legacy history cache: {:#}", e);
+    }
+
+    let dir = canteens_dir()?;
+    log::debug!("found cache directory {:?}", dir);
+
+    let mut canteens = Vec::new();
+    for entry in fs::read_dir(&dir).context("Could not list cache directory")? {
+        let path = entry.context("Could not list cache directory")?.path();
+        if !is_cache_file(&path) {
+            continue;
+        }
+
+        // a broken cache file only drops its own canteen
+        match read_canteen(&path) {
+            Ok(canteen) => canteens.push(canteen),
+            Err(e) => log::warn!("skipping cache file {:?}: {:#}", path, e),
+        }
+    }
+
+    Ok(canteens)
+}
+
+// reads all canteens from the cache, unavailable or broken cache files result in no canteens
+pub fn read_history() -> HashMap<CanteenDescription, Canteen> {
+    log::debug!("loading canteens from cache");
+
+    let history = match try_read_history() {
+        Ok(history) => history,
+        // if reading the cache fails, just skip
+        Err(e) => {
+            log::warn!("failed to read cache: {:#}", e);
+            Vec::new()
+        },
+    };
+
+    log::debug!("finish loading cache");
+
+    history
+        .into_iter()
+        .map(|canteen| (canteen.description.clone(), canteen))
+        .collect()
+}
+
+fn try_write_history(canteens: &[Canteen]) -> Result<()> {
+    let dir = canteens_dir()?;
+    log::debug!("writing canteens into cache directory {:?}", dir);
+
+    for canteen in canteens {
+        if let Err(e) = write_canteen(&dir, canteen) {
+            log::warn!("failed to cache canteen {:?}: {:#}", canteen.description, e);
+        }
+    }
+
+    // canteens which are not given anymore are not cached anymore, e.g. hidden canteens
+    let file_names = canteens
+        .iter()
+        .map(|canteen| canteen_file_name(&canteen.description))
+        .collect::<Vec<_>>();
+    let now = SystemTime::now();
+    for entry in fs::read_dir(&dir).context("Could not list cache directory")? {
+        let path = entry.context("Could not list cache directory")?.path();
+        let cached = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .map_or(false, |file_name| file_names.iter().any(|name| name == file_name));
+        if is_cache_file(&path) && !cached {
+            log::debug!("removing cache file {:?}", path);
+            fs::remove_file(&path).with_context(|| format!("Could not remove cache file {:?}", path))?;
+        } else if is_tmp_file(&path) && is_stale(&path, now) {
+            log::debug!("removing leftover temporary cache file {:?}", path);
+            fs::remove_file(&path).with_context(|| format!("Could not remove temporary cache file {:?}", path))?;
+        }
+    }
+
+    Ok(())
+}
+
+// replaces the cache with the given canteens
+pub fn write_history(canteens: &[Canteen]) {
+    log::debug!("write loaded canteens into history cache");
+
+    if let Err(e) = try_write_history(canteens) {
+        log::warn!("{:#}", e);
+    }
+
+    log::debug!("finish writing history cache");
+}
+
+// when the cached canteens changed the last time, as every write renames a file into the cache directory
+pub fn modified() -> Option<SystemTime> {
+    let dir = xdg().ok()?.find_cache_file(CANTEENS_DIR)?;
+    fs::metadata(dir).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn file_size(path: &Path) -> Result<u64> {
+    Ok(fs::metadata(path).context("Could not read size of cache file")?.len())
+}
+
+// the size of all cache files in bytes
+pub fn size() -> Result<u64> {
+    let xdg = xdg()?;
+    let mut size = match xdg.find_cache_file(LEGACY_HISTORY) {
+        Some(path) => file_size(&path)?,
+        None => 0,
+    };
+
+    if let Some(dir) = xdg.find_cache_file(CANTEENS_DIR) {
+        for entry in fs::read_dir(&dir).context("Could not list cache directory")? {
+            size += file_size(&entry.context("Could not list cache directory")?.path())?;
+        }
+    }
+
+    Ok(size)
+}
+
+// removes all cached canteens, including a legacy history cache
+pub fn clear() -> Result<()> {
+    log::debug!("removing cache");
+
+    let xdg = xdg()?;
+    if let Some(path) = xdg.find_cache_file(LEGACY_HISTORY) {
+        fs::remove_file(&path).context("Could not remove legacy history cache")?;
+    }
+    if let Some(dir) = xdg.find_cache_file(CANTEENS_DIR) {
+        fs::remove_dir_all(&dir).context("Could not remove cache directory")?;
+    }
+
+    log::debug!("finish removing cache");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+
+    use super::*;
+    use crate::model::Day;
+
+    fn canteen(days: Vec<Day>) -> Canteen {
+        Canteen {
+            description: CanteenDescription::new("UniCampusLowerHall"),
+            name: None,
+            source: None,
+            fetched: None,
+            days,
+        }
+    }
+
+    fn day() -> Day {
+        Day {
+            date: NaiveDate::from_ymd(2020, 5, 4),
+            meals: Vec::new(),
+            side_dishes: vec!["Fries".to_owned()],
+        }
+    }
+
+    // an empty directory only used by a single test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ovgu-canteen-gtk-cache-{}-{}", name, std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn file_names_are_sanitized() {
+        assert_eq!(canteen_file_name(&CanteenDescription::new("UniCampusLowerHall")), "UniCampusLowerHall.json");
+        assert_eq!(canteen_file_name(&CanteenDescription::new("openmensa-42_b")), "openmensa-42_b.json");
+        assert_eq!(canteen_file_name(&CanteenDescription::new("../Mensa Süd")), "___Mensa_S_d.json");
+    }
+
+    #[test]
+    fn files_are_told_apart_by_extension() {
+        assert!(is_cache_file(Path::new("UniCampusLowerHall.json")));
+        assert!(!is_cache_file(Path::new(".UniCampusLowerHall.json.42.0.tmp")));
+        assert!(is_tmp_file(Path::new(".UniCampusLowerHall.json.42.0.tmp")));
+        assert!(!is_tmp_file(Path::new("UniCampusLowerHall.json")));
+    }
+
+    #[test]
+    fn fresh_tmp_files_are_not_stale() {
+        let dir = test_dir("stale");
+        let path = dir.join(".UniCampusLowerHall.json.42.0.tmp");
+        fs::write(&path, "").unwrap();
+
+        let now = SystemTime::now();
+        assert!(!is_stale(&path, now));
+        assert!(is_stale(&path, now + STALE_TMP_FILE_AGE));
+        // missing files are not removed either
+        assert!(!is_stale(&dir.join("missing.tmp"), now + STALE_TMP_FILE_AGE));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn written_canteen_is_read_again() {
+        let dir = test_dir("roundtrip");
+        let mut written = canteen(vec![day()]);
+        written.fetched = Some(Utc.ymd(2020, 5, 4).and_hms(9, 30, 0));
+        written.source = Some("ovgu".to_owned());
+
+        write_canteen(&dir, &written).unwrap();
+        let files = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        assert_eq!(files, ["UniCampusLowerHall.json"]);
+
+        let read = read_canteen(&dir.join("UniCampusLowerHall.json")).unwrap();
+        assert_eq!(read.description, written.description);
+        assert_eq!(read.fetched, written.fetched);
+        assert_eq!(read.source, written.source);
+        assert_eq!(read.days.len(), 1);
+        assert_eq!(read.days[0].side_dishes, ["Fries"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn other_versions_are_not_read() {
+        let dir = test_dir("version");
+        let path = dir.join("UniCampusLowerHall.json");
+        fs::write(&path, r#"{"header":{"version":2,"fetched":null,"source":null},"canteen":{}}"#).unwrap();
+
+        let error = read_canteen(&path).unwrap_err();
+        assert!(format!("{:#}", error).contains("unsupported version 2"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn legacy_canteen_keeps_fetched() {
+        let mut json = serde_json::to_value(canteen(vec![day()])).unwrap();
+        json["fetched"] = "2020-05-04T09:30:00Z".into();
+
+        let legacy = serde_json::from_value::<LegacyCanteen>(json).unwrap();
+        assert_eq!(legacy.fetched, Some(Utc.ymd(2020, 5, 4).and_hms(9, 30, 0)));
+        assert_eq!(legacy.canteen.description, CanteenDescription::new("UniCampusLowerHall"));
+        assert_eq!(legacy.canteen.days.len(), 1);
+
+        // caches of even older versions did not know when a canteen was fetched
+        let json = serde_json::to_value(canteen(Vec::new())).unwrap();
+        assert_eq!(serde_json::from_value::<LegacyCanteen>(json).unwrap().fetched, None);
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use gio::prelude::*;
+use gio::Settings;
+
+use crate::export::{self, Format, Selection};
+use crate::favorites::Favorites;
+use crate::filter::MealFilter;
+use crate::model::{Canteen, CanteenDescription};
+use crate::price::PriceGroup;
+
+// settings which require the calendar feed to be regenerated
+pub const FEED_KEYS: &[&str] = &[
+    "calendar-feed",
+    "calendar-canteen",
+    "calendar-favorites-only",
+    "calendar-apply-filters",
+    "price-group",
+];
+
+pub fn feed_path() -> Result<PathBuf> {
+    xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk")
+        .context("Could not find data directory")?
+        .place_data_file("menu.ics")
+        .context("Could not place calendar feed")
+}
+
+// builds the calendar feed from the loaded canteens,
+// returns None if the feed is disabled or its canteen is not loaded
+pub fn render_feed(settings: &Settings, favorites: &Favorites, canteens: &[Canteen]) -> Option<String> {
+    if !settings.get_boolean("calendar-feed") {
+        return None;
+    }
+
+    let canteen_desc = CanteenDescription::new(settings.get_string("calendar-canteen")?.as_str());
+    if !canteens.iter().any(|canteen| canteen.description == canteen_desc) {
+        log::debug!("calendar canteen {:?} is not loaded, keeping the old calendar feed", canteen_desc);
+        return None;
+    }
+
+    let selection = Selection {
+        canteens: Some(vec![canteen_desc]),
+        from: None,
+        until: None,
+        meal_filter: if settings.get_boolean("calendar-apply-filters") {
+            Some(MealFilter::from_settings(settings))
+        } else {
+            None
+        },
+        favorites: if settings.get_boolean("calendar-favorites-only") {
+            Some(favorites.get().clone())
+        } else {
+            None
+        },
+    };
+
+    log::debug!("rendering calendar feed with {:?}", selection);
+
+    export::export(&selection.apply(canteens), Format::ICalendar, PriceGroup::from_settings(Some(settings)))
+        .map_err(|e| log::warn!("failed to render calendar feed: {:#}", e))
+        .ok()
+}
+
+pub fn write_feed(feed: &str) {
+    log::debug!("write calendar feed");
+
+    let result = feed_path().and_then(|path| {
+        log::debug!("writing calendar feed to {:?}", path);
+        fs::write(&path, feed).with_context(|| format!("Could not write calendar feed to {}", path.display()))
+    });
+
+    if let Err(e) = result {
+        log::warn!("{:#}", e);
+    }
+
+    log::debug!("finish writing calendar feed");
+}
@@ -0,0 +1,358 @@
+use std::fs;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
+use futures::future;
+use getopts::Options;
+use gio::prelude::*;
+use gio::{Settings, SettingsSchemaSource};
+use gettextrs::gettext as t;
+
+use crate::archive;
+use crate::cache;
+use crate::canteen;
+use crate::components::day::translate_weekday;
+use crate::components::meal::translate_symbol;
+use crate::export::{self, Format, Selection, FORMATS};
+use crate::favorites;
+use crate::filter::MealFilter;
+use crate::fetch;
+use crate::model::{Canteen, CanteenDescription, Day};
+use crate::price::{PriceGroup, PRICE_GROUPS};
+use crate::source::{self, MenuSource};
+
+// the cli can be used without a running display server,
+// so gtk must never be initialized in here
+pub fn run(args: &[String]) -> Option<i32> {
+    let program = args.first().map_or("ovgu-canteen-gtk", |program| program.as_str());
+
+    match args.get(1).map(String::as_str) {
+        Some("menu") => {
+            log::debug!("running menu command");
+            Some(exit_code(menu(program, &args[2..])))
+        },
+        Some("export") => {
+            log::debug!("running export command");
+            Some(exit_code(export(program, &args[2..])))
+        },
+        // no cli command, start the graphical application
+        _ => None,
+    }
+}
+
+fn exit_code(result: Result<()>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}: {:#}", t("error"), e);
+            1
+        },
+    }
+}
+
+// the settings are optional for the cli, as the schema might not be installed
+fn settings() -> Option<Settings> {
+    let schema_source = SettingsSchemaSource::get_default()?;
+    schema_source.lookup("io.github.fin_ger.OvGUCanteen", true)?;
+
+    Some(Settings::new("io.github.fin_ger.OvGUCanteen"))
+}
+
+fn parse_canteen(sources: &[Box<dyn MenuSource>], name: &str) -> Result<CanteenDescription> {
+    let canteen_descs = source::canteens(sources);
+
+    canteen_descs
+        .iter()
+        .find(|canteen_desc| canteen_desc.id() == name)
+        .cloned()
+        .with_context(|| {
+            format!(
+                "Unknown canteen '{}', available canteens are: {}",
+                name,
+                canteen_descs
+                    .iter()
+                    .map(CanteenDescription::id)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        })
+}
+
+// accepts today, tomorrow, weekday names, YYYY-MM-DD and DD.MM.YYYY
+fn parse_date(date: &str, today: NaiveDate) -> Result<NaiveDate> {
+    match date.to_lowercase().as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return Ok(today.succ()),
+        _ => {},
+    }
+
+    // weekdays refer to the next occurrence of this weekday including today
+    if let Ok(weekday) = Weekday::from_str(date) {
+        let days_ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+        return Ok(today + Duration::days(i64::from(days_ahead)));
+    }
+
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(date, "%d.%m.%Y"))
+        .with_context(|| format!("Invalid date '{}'", date))
+}
+
+// the price group given on the command-line overrides the one of the settings
+fn parse_price_group(name: Option<String>, settings: Option<&Settings>) -> Result<PriceGroup> {
+    match name {
+        Some(name) => PriceGroup::from_name(&name).with_context(|| {
+            format!(
+                "Unknown price group '{}', available price groups are: {}",
+                name,
+                PRICE_GROUPS.iter().map(|(name, _group)| *name).collect::<Vec<_>>().join(", "),
+            )
+        }),
+        None => Ok(PriceGroup::from_settings(settings)),
+    }
+}
+
+fn history_oldest(settings: Option<&Settings>, today: NaiveDate) -> NaiveDate {
+    let menu_history_length = settings.map_or(7, |settings| settings.get_uint64("menu-history-length"));
+    today - Duration::days(menu_history_length as i64)
+}
+
+// loads the canteens like the graphical application does and updates the cache,
+// canteens which cannot be fetched are taken from the cache
+fn load_canteens(settings: Option<&Settings>, canteen_descs: &[CanteenDescription], offline: bool) -> Result<Vec<Canteen>> {
+    let sources = source::from_settings(settings);
+    let enabled_canteens = source::enabled_canteens(&sources, settings);
+    let history_oldest = history_oldest(settings, Local::today().naive_local());
+    let mut history = cache::read_history();
+
+    if offline {
+        log::debug!("using cached canteens");
+        return Ok(canteen_descs
+            .iter()
+            .filter_map(|canteen_desc| history.remove(canteen_desc))
+            .collect());
+    }
+
+    let mut rt = tokio::runtime::Runtime::new().context("Failed to create runtime")?;
+    let sources = &sources;
+    let canteens = rt.block_on(future::join_all(canteen_descs.iter().map(|canteen_desc| {
+        let cached_canteen = history.get(canteen_desc).cloned();
+        async move {
+            match fetch::load_canteen_or_cached(sources, cached_canteen, canteen_desc.clone()).await {
+                Ok(loaded) => Some(fetch::prune_history(loaded.canteen, history_oldest)),
+                Err(e) => {
+                    log::warn!("{:#}", e);
+                    None
+                },
+            }
+        }
+    })));
+    let canteens = canteens.into_iter().flatten().collect::<Vec<_>>();
+
+    // keep all other canteens in the cache untouched, but never cache disabled canteens
+    for canteen in &canteens {
+        history.insert(canteen.description.clone(), canteen.clone());
+    }
+    history.retain(|canteen_desc, _canteen| enabled_canteens.contains(canteen_desc));
+    cache::write_history(&history.values().cloned().collect::<Vec<_>>());
+    if settings.map_or(false, |settings| settings.get_boolean("archive")) {
+        archive::append(&canteens);
+    }
+
+    Ok(canteens)
+}
+
+fn print_day(canteen: &Canteen, day: &Day, price_group: PriceGroup) {
+    println!(
+        "{} – {}, {}",
+        canteen::name(canteen),
+        translate_weekday(day.date.weekday()),
+        day.date.format("%d.%m.%Y"),
+    );
+    println!();
+
+    // the meal name, the shown prices and the symbols
+    let mut header = vec![t("Meal")];
+    header.extend(price_group.shown().iter().map(|group| group.translate()));
+    header.push(t("Symbols"));
+    let rows = day
+        .meals
+        .iter()
+        .map(|meal| {
+            let mut row = vec![meal.name.clone()];
+            row.extend(
+                price_group
+                    .prices(&meal.price)
+                    .into_iter()
+                    .map(|(_group, price)| format!("{:.2} €", price)),
+            );
+            row.push(
+                meal.symbols
+                    .iter()
+                    .map(translate_symbol)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+            row
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = vec![0; header.len()];
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = std::cmp::max(*width, cell.chars().count());
+        }
+    }
+
+    // the name is aligned left, the prices right and the symbols are not padded
+    let last = header.len() - 1;
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let line = row
+            .iter()
+            .enumerate()
+            .map(|(idx, cell)| match idx {
+                0 => format!("{:<w$}", cell, w = widths[idx]),
+                idx if idx == last => cell.clone(),
+                _ => format!("{:>w$}", cell, w = widths[idx]),
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line);
+    }
+
+    if day.meals.is_empty() {
+        println!("{}", t("No meals available"));
+    }
+
+    println!();
+    if day.side_dishes.is_empty() {
+        println!("{}: {}", t("Side dishes"), t("not available"));
+    } else {
+        println!("{}: {}", t("Side dishes"), day.side_dishes.join(", "));
+    }
+}
+
+fn menu(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = Options::new();
+    opts.optopt("c", "canteen", "canteen to show, defaults to the default canteen", "CANTEEN");
+    opts.optopt("d", "date", "today, tomorrow, a weekday, YYYY-MM-DD or DD.MM.YYYY", "DATE");
+    opts.optflag("", "offline", "only show menus from the cache");
+    opts.optopt("p", "price-group", "all, student, staff or guest, defaults to the price group of the settings", "GROUP");
+    opts.optflag("h", "help", "print this help");
+
+    let matches = opts.parse(args).context("Invalid arguments")?;
+    if matches.opt_present("help") {
+        print!("{}", opts.usage(&format!("Usage: {} menu [options]", program)));
+        return Ok(());
+    }
+
+    let settings = settings();
+    let price_group = parse_price_group(matches.opt_str("price-group"), settings.as_ref())?;
+    let sources = source::from_settings(settings.as_ref());
+    let canteen_desc = match matches.opt_str("canteen") {
+        Some(name) => parse_canteen(&sources, &name)?,
+        None => settings
+            .as_ref()
+            .and_then(|settings| settings.get_string("default-canteen"))
+            .map_or_else(
+                || CanteenDescription::new("UniCampusLowerHall"),
+                |name| CanteenDescription::new(name.as_str()),
+            ),
+    };
+
+    let today = Local::today().naive_local();
+    let date = match matches.opt_str("date") {
+        Some(date) => parse_date(&date, today)?,
+        None => today,
+    };
+
+    log::debug!("showing menu of {:?} on {}", canteen_desc, date);
+
+    let canteen = load_canteens(settings.as_ref(), &[canteen_desc.clone()], matches.opt_present("offline"))?
+        .pop()
+        .context("Canteen is neither available online nor in the cache")?;
+
+    match canteen.days.iter().find(|day| day.date == date) {
+        Some(day) => print_day(&canteen, day, price_group),
+        None => bail!(
+            "No menu available for {} on {}",
+            canteen::translate(&canteen_desc),
+            date.format("%d.%m.%Y"),
+        ),
+    }
+
+    Ok(())
+}
+
+fn export(program: &str, args: &[String]) -> Result<()> {
+    let format_names = FORMATS.iter().map(|(name, _format)| *name).collect::<Vec<_>>().join(", ");
+
+    let mut opts = Options::new();
+    opts.optopt("f", "format", &format!("one of {}, defaults to markdown", format_names), "FORMAT");
+    opts.optmulti("c", "canteen", "canteen to export, can be given multiple times, defaults to all shown canteens", "CANTEEN");
+    opts.optopt("", "from", "first day to export", "DATE");
+    opts.optopt("", "until", "last day to export", "DATE");
+    opts.optopt("o", "output", "file to write to, defaults to the standard output", "FILE");
+    opts.optflag("", "apply-filters", "leave out meals excluded by the diet profile or avoided allergenics");
+    opts.optflag("", "favorites-only", "only export favorite meals");
+    opts.optflag("", "offline", "only export menus from the cache");
+    opts.optopt("p", "price-group", "all, student, staff or guest, defaults to the price group of the settings", "GROUP");
+    opts.optflag("h", "help", "print this help");
+
+    let matches = opts.parse(args).context("Invalid arguments")?;
+    if matches.opt_present("help") {
+        print!("{}", opts.usage(&format!("Usage: {} export [options]", program)));
+        return Ok(());
+    }
+
+    let format = match matches.opt_str("format") {
+        Some(name) => Format::from_name(&name)
+            .with_context(|| format!("Unknown format '{}', available formats are: {}", name, format_names))?,
+        None => Format::Markdown,
+    };
+
+    let settings = settings();
+    let price_group = parse_price_group(matches.opt_str("price-group"), settings.as_ref())?;
+    let sources = source::from_settings(settings.as_ref());
+    let meal_filter = if matches.opt_present("apply-filters") {
+        let settings = settings.as_ref().context("Filters are not available without installed settings")?;
+        Some(MealFilter::from_settings(settings))
+    } else {
+        None
+    };
+
+    let today = Local::today().naive_local();
+    let canteen_descs = matches
+        .opt_strs("canteen")
+        .iter()
+        .map(|name| parse_canteen(&sources, name))
+        .collect::<Result<Vec<_>>>()?;
+    let selection = Selection {
+        canteens: if canteen_descs.is_empty() { None } else { Some(canteen_descs.clone()) },
+        from: matches.opt_str("from").map(|date| parse_date(&date, today)).transpose()?,
+        until: matches.opt_str("until").map(|date| parse_date(&date, today)).transpose()?,
+        meal_filter,
+        favorites: if matches.opt_present("favorites-only") {
+            Some(favorites::load().get().clone())
+        } else {
+            None
+        },
+    };
+
+    log::debug!("exporting canteens as {:?} with {:?}", format, selection);
+
+    let canteen_descs = if canteen_descs.is_empty() {
+        source::enabled_canteens(&sources, settings.as_ref())
+    } else {
+        canteen_descs
+    };
+    let canteens = load_canteens(settings.as_ref(), &canteen_descs, matches.opt_present("offline"))?;
+    let exported = export::export(&selection.apply(&canteens), format, price_group)?;
+
+    match matches.opt_str("output") {
+        Some(path) => fs::write(&path, exported).with_context(|| format!("Could not write to {}", path))?,
+        None => print!("{}", exported),
+    }
+
+    Ok(())
+}
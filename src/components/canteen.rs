@@ -1,34 +1,60 @@
 use anyhow::{Error, Result};
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use gtk::prelude::*;
-use gtk::{Box, Builder, Label, Spinner, Stack, ScrolledWindow};
+use gtk::{Box, Builder, InfoBar, Label, Spinner, Stack, ScrolledWindow};
 use gettextrs::gettext as t;
-use ovgu_canteen::{Canteen, CanteenDescription};
 
 use crate::components::{get, glib_yield, DayComponent, WindowComponent, GLADE};
+use crate::fetch::Loaded;
+use crate::model::{Canteen, CanteenDescription};
+use crate::source::openmensa;
 use crate::util::{enclose, AdjustingVec};
 
 pub struct CanteenComponent {
     description: CanteenDescription,
     canteen_stack: Stack,
+    canteen_scrolled_window: ScrolledWindow,
     canteen_error_label: Label,
+    canteen_offline_info_bar: InfoBar,
+    canteen_offline_label: Label,
+    canteen_updated_label: Label,
     canteen_spinner: Spinner,
     days: AdjustingVec<DayComponent, Error>,
+    // a loaded canteen whose days are not rendered yet
+    pending: Option<Canteen>,
 }
 
 pub fn translate(description: &CanteenDescription) -> String {
     log::debug!("translating canteen {:?}", description);
 
-    match description {
-        CanteenDescription::UniCampusLowerHall => t("UniCampus Magdeburg Lower Hall"),
-        CanteenDescription::UniCampusUpperHall => t("UniCampus Magdeburg Upper Hall"),
-        CanteenDescription::Kellercafe => t("Kellercafé Magdeburg"),
-        CanteenDescription::Herrenkrug => t("Herrenkrug Magdeburg"),
-        CanteenDescription::Stendal => t("Stendal"),
-        CanteenDescription::Wernigerode => t("Wernigerode"),
-        CanteenDescription::DomCafeteHalberstadt => t("DomCafete Halberstadt"),
+    match description.id() {
+        "UniCampusLowerHall" => t("UniCampus Magdeburg Lower Hall"),
+        "UniCampusUpperHall" => t("UniCampus Magdeburg Upper Hall"),
+        "Kellercafe" => t("Kellercafé Magdeburg"),
+        "Herrenkrug" => t("Herrenkrug Magdeburg"),
+        "Stendal" => t("Stendal"),
+        "Wernigerode" => t("Wernigerode"),
+        "DomCafeteHalberstadt" => t("DomCafete Halberstadt"),
+        // canteens of other sources are named by their source after fetching
+        id => match openmensa::canteen_id(description) {
+            Some(openmensa_id) => format!("{} {}", t("OpenMensa Canteen"), openmensa_id),
+            None => id.to_owned(),
+        },
     }
 }
 
+fn format_fetched(fetched: DateTime<Utc>) -> String {
+    fetched.with_timezone(&Local).format("%d.%m.%Y %H:%M").to_string()
+}
+
+// the name of a loaded canteen, which is either provided by its source or translated
+pub fn name(canteen: &Canteen) -> String {
+    canteen
+        .name
+        .clone()
+        .unwrap_or_else(|| translate(&canteen.description))
+}
+
 impl CanteenComponent {
     pub fn new(description: &CanteenDescription, window: &WindowComponent) -> Result<Self> {
         log::debug!("creating new CanteenComponent for canteen {:?}", description);
@@ -37,19 +63,27 @@ impl CanteenComponent {
         let canteen_stack: Stack = get!(&builder, "canteen-stack")?;
         let canteen_scrolled_window: ScrolledWindow = get!(&builder, "canteen-scrolled-window")?;
         let canteen_error_label: Label = get!(&builder, "canteen-error-label")?;
+        let canteen_offline_info_bar: InfoBar = get!(&builder, "canteen-offline-info-bar")?;
+        let canteen_offline_label: Label = get!(&builder, "canteen-offline-label")?;
+        let canteen_updated_label: Label = get!(&builder, "canteen-updated-label")?;
         let canteen_spinner: Spinner = get!(&builder, "canteen-spinner")?;
         let days_box: Box = get!(&builder, "days-box")?;
         let canteen_name = translate(description);
+        let settings = window.settings().clone();
+        let favorites = window.favorites().clone();
+        let ledger = window.ledger().clone();
+        let ratings = window.ratings().clone();
 
         log::debug!("adding CanteenComponent {:?} to window", description);
-        window.add_canteen(&canteen_stack, serde_plain::to_string(description).unwrap(), canteen_name)?;
+        window.add_canteen(&canteen_stack, description.id().to_owned(), &canteen_name)?;
 
-        // create a new adjusting vector which adjusts its size according to an input iterator
-        let days = AdjustingVec::new(
+        // create a new adjusting vector which adjusts its size according to an input iterator,
+        // the days are matched by their date, so a new day does not reload all following days
+        let days = AdjustingVec::new_keyed(
             // define how to create a new DayComponent
-            enclose! { (canteen_scrolled_window, description, days_box) move || {
-                enclose! { (canteen_scrolled_window, description, days_box) async move {
-                    let comp = DayComponent::new(move |y| {
+            enclose! { (canteen_scrolled_window, description, days_box, settings, favorites, ledger, ratings) move || {
+                enclose! { (canteen_scrolled_window, description, days_box, settings, favorites, ledger, ratings) async move {
+                    let comp = DayComponent::new(&settings, &favorites, &ledger, &ratings, move |y| {
                         Self::scroll_to(&canteen_scrolled_window, &description, y);
                     }).await?;
                     days_box.pack_start(comp.root_widget(), false, true, 0);
@@ -64,6 +98,10 @@ impl CanteenComponent {
                 glib_yield!(); // give gtk a chance to update the UI
                 Ok(())
             },
+            // define how to move a DayComponent
+            enclose! { (days_box) move |day: &DayComponent, idx| {
+                days_box.reorder_child(day.root_widget(), idx as i32);
+            }},
         );
 
         log::debug!("finish creating new CanteenComponent for canteen {:?}", description);
@@ -71,20 +109,70 @@ impl CanteenComponent {
         Ok(Self {
             description: description.clone(),
             canteen_stack,
+            canteen_scrolled_window,
             canteen_error_label,
+            canteen_offline_info_bar,
+            canteen_offline_label,
+            canteen_updated_label,
             canteen_spinner,
             days,
+            pending: None,
         })
     }
 
+    pub const fn root_widget(&self) -> &Stack {
+        &self.canteen_stack
+    }
+
     fn scroll_to(canteen_scrolled_window: &ScrolledWindow, description: &CanteenDescription, y: i32) {
         if let Some(position) = canteen_scrolled_window.get_vadjustment() {
             log::debug!("scrolling to todays canteen in CanteenComponent {:?}", description);
-            position.set_value(y as f64 - 42.0);
+            position.set_value(f64::from(y) - 42.0);
+        }
+    }
+
+    // returns false if no menu for this date is loaded
+    pub fn scroll_to_day(&self, date: NaiveDate) -> bool {
+        match self.days.iter().find(|day| day.date() == Some(date)) {
+            Some(day) => {
+                log::debug!("scrolling to day {} in CanteenComponent {:?}", date, self.description);
+                Self::scroll_to(&self.canteen_scrolled_window, &self.description, day.root_widget().get_allocation().y);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // shows when the menus were fetched and whether they are taken from the cache
+    fn update_staleness(&self, canteen: &Canteen, offline: Option<&Error>) {
+        match canteen.fetched {
+            Some(fetched) => {
+                self.canteen_updated_label
+                    .set_text(&t("Last updated {time}").replace("{time}", &format_fetched(fetched)));
+                self.canteen_updated_label.show();
+            },
+            None => self.canteen_updated_label.hide(),
+        }
+
+        match offline {
+            Some(e) => {
+                log::info!("showing cached menus for CanteenComponent {:?}", self.description);
+                let message = match canteen.fetched {
+                    Some(fetched) => t("Offline – showing data from {time}").replace("{time}", &format_fetched(fetched)),
+                    None => t("Offline – showing cached data"),
+                };
+                self.canteen_offline_label.set_text(&message);
+                // the reason is available for the curious
+                self.canteen_offline_label.set_tooltip_text(Some(&format!("{:#}", e)));
+                self.canteen_offline_info_bar.show();
+            },
+            None => self.canteen_offline_info_bar.hide(),
         }
     }
 
-    pub async fn load(&mut self, load_result: Result<Canteen>) -> Option<Canteen> {
+    // the days of a canteen are only rendered if render is set, otherwise
+    // they are rendered by render_pending when the canteen is needed
+    pub async fn load(&mut self, load_result: Result<Loaded>, render: bool) -> Option<Canteen> {
         log::debug!("loading content into CanteenComponent {:?}", self.description);
 
         // start and show loading spinner
@@ -92,8 +180,9 @@ impl CanteenComponent {
         self.canteen_spinner.show();
 
         let canteen = match load_result {
-            Ok(canteen) => {
+            Ok(Loaded { canteen, offline }) => {
                 log::debug!("unpacking content for CanteenComponent {:?}", self.description);
+                self.update_staleness(&canteen, offline.as_ref());
 
                 // makes the menu items visible in this canteen-component,
                 // e.g. not the error page if previously shown
@@ -112,14 +201,45 @@ impl CanteenComponent {
             }
         };
 
+        if render {
+            self.load_days(&canteen).await;
+        } else {
+            log::debug!("deferring rendering of days in CanteenComponent {:?}", self.description);
+            self.pending = Some(canteen.clone());
+        }
+
+        log::debug!("finish loading content into CanteenComponent {:?}", self.description);
+
+        Some(canteen)
+    }
+
+    #[allow(clippy::missing_const_for_fn)] // Option::is_some is not stable as a const fn yet
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // renders the days of the canteen if they got deferred while loading
+    pub async fn render_pending(&mut self) {
+        if let Some(canteen) = self.pending.take() {
+            log::debug!("rendering deferred days in CanteenComponent {:?}", self.description);
+            self.load_days(&canteen).await;
+        }
+    }
+
+    // loads the days of an already loaded canteen again, e.g. to sort its meals differently
+    pub async fn load_days(&mut self, canteen: &Canteen) {
         log::debug!("loading days into CanteenComponent {:?}", self.description);
 
+        // the days get rendered now, deferred days would be outdated
+        self.pending = None;
+
         // adjust DayComponents to match canteen.days
+        let description = &self.description;
         let days_result = self
             .days
-            .adjust(&canteen.days, |mut comp, day| async move {
+            .adjust_keyed(&canteen.days, |day| day.date, |mut comp, day| async move {
                 // how to update a DayComponent
-                comp.load(day).await;
+                comp.load(description, day).await;
                 glib_yield!(); // give gtk a chance to update the UI
                 Ok(comp)
             })
@@ -142,9 +262,5 @@ impl CanteenComponent {
         // stop and hide loading spinner
         self.canteen_spinner.stop();
         self.canteen_spinner.hide();
-
-        log::debug!("finish loading content into CanteenComponent {:?}", self.description);
-
-        Some(canteen)
     }
 }
@@ -0,0 +1,337 @@
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use gio::prelude::*;
+use gio::Settings;
+use gtk::prelude::*;
+use gtk::{Box, Builder, CheckButton, ComboBoxText, FlowBox, Frame, Label, ListBox, Orientation, SelectionMode};
+use gettextrs::gettext as t;
+
+use crate::components::day::format_date;
+use crate::components::{get, MealComponent, GLADE};
+use crate::favorites::Favorites;
+use crate::filter::MealFilter;
+use crate::ledger::Ledger;
+use crate::model::{Canteen, CanteenDescription, Meal};
+use crate::price::PriceGroup;
+use crate::ratings::Ratings;
+use crate::sorting::{self, MealOrder};
+use crate::util::enclose;
+
+// shows the meals of several canteens on one day side by side, see the compare-canteens settings key
+#[derive(Clone)]
+pub struct CompareComponent {
+    page: Box,
+    date_combo_box: ComboBoxText,
+    canteens_flow_box: FlowBox,
+    empty_label: Label,
+    columns_box: Box,
+    settings: Settings,
+    favorites: Favorites,
+    ledger: Ledger,
+    ratings: Ratings,
+    canteens: Rc<RefCell<Vec<Canteen>>>,
+    check_buttons: Rc<RefCell<Vec<(CanteenDescription, CheckButton)>>>,
+    meals: Rc<RefCell<Vec<MealComponent>>>,
+    // increased on every render, so columns which are still loading can tell they are outdated
+    generation: Rc<Cell<usize>>,
+}
+
+// the cheapest meal which passes the filters of the user
+fn cheapest_meal<'a>(meals: &[&'a Meal], filter: &MealFilter, price_group: PriceGroup) -> Option<&'a Meal> {
+    meals
+        .iter()
+        .copied()
+        .filter(|meal| filter.check(meal).is_empty())
+        .min_by(|a, b| {
+            price_group
+                .relevant_price(&a.price)
+                .partial_cmp(&price_group.relevant_price(&b.price))
+                .unwrap_or(Ordering::Equal)
+        })
+}
+
+// adds or removes the canteen from the compared canteens
+fn set_compared(settings: &Settings, canteen_desc: &CanteenDescription, compared: bool) {
+    let current = settings.get_strv("compare-canteens");
+    // the check buttons are toggled as well when the settings changed
+    if current.iter().any(|id| id.as_str() == canteen_desc.id()) == compared {
+        return;
+    }
+
+    let mut ids = current
+        .iter()
+        .map(ToString::to_string)
+        .filter(|id| id != canteen_desc.id())
+        .collect::<Vec<_>>();
+    if compared {
+        ids.push(canteen_desc.id().to_owned());
+    }
+
+    settings.set_strv("compare-canteens", &ids.iter().map(String::as_str).collect::<Vec<_>>()).unwrap();
+}
+
+fn create_column(title: &str) -> (Box, Label, ListBox) {
+    let column = Box::new(Orientation::Vertical, 8);
+
+    let title_label = Label::new(Some(title));
+    title_label.set_xalign(0.0);
+    title_label.set_line_wrap(true);
+    title_label.get_style_context().add_class("compare-column-title");
+    column.pack_start(&title_label, false, true, 0);
+
+    let cheapest_label = Label::new(None);
+    cheapest_label.set_xalign(0.0);
+    cheapest_label.set_line_wrap(true);
+    cheapest_label.get_style_context().add_class("dim-label");
+    cheapest_label.get_style_context().add_class("small");
+    column.pack_start(&cheapest_label, false, true, 0);
+
+    let frame = Frame::new(None);
+    let list_box = ListBox::new();
+    list_box.set_selection_mode(SelectionMode::None);
+    frame.add(&list_box);
+    column.pack_start(&frame, false, true, 0);
+
+    column.show_all();
+    (column, cheapest_label, list_box)
+}
+
+impl CompareComponent {
+    pub fn new(settings: &Settings, favorites: &Favorites, ledger: &Ledger, ratings: &Ratings) -> Result<Self> {
+        log::debug!("creating new CompareComponent");
+
+        let builder = Builder::new_from_string(GLADE);
+        let page: Box = get!(&builder, "compare-page")?;
+        let date_combo_box: ComboBoxText = get!(&builder, "compare-date-combo-box")?;
+        let canteens_flow_box: FlowBox = get!(&builder, "compare-canteens-flow-box")?;
+        let empty_label: Label = get!(&builder, "compare-empty-label")?;
+        let columns_box: Box = get!(&builder, "compare-columns-box")?;
+
+        let comp = Self {
+            page,
+            date_combo_box,
+            canteens_flow_box,
+            empty_label,
+            columns_box,
+            settings: settings.clone(),
+            favorites: favorites.clone(),
+            ledger: ledger.clone(),
+            ratings: ratings.clone(),
+            canteens: Rc::new(RefCell::new(Vec::new())),
+            check_buttons: Rc::new(RefCell::new(Vec::new())),
+            meals: Rc::new(RefCell::new(Vec::new())),
+            generation: Rc::new(Cell::new(0)),
+        };
+
+        comp.date_combo_box.connect_changed(enclose! { (comp) move |_combo_box| {
+            comp.render();
+        }});
+
+        // the component lives as long as the window, so this is never disconnected
+        comp.settings.connect_changed(enclose! { (comp) move |settings, key| {
+            if key == "compare-canteens" {
+                let compared = settings.get_strv(key);
+                for (desc, check_button) in comp.check_buttons.borrow().iter() {
+                    check_button.set_active(compared.iter().any(|id| id.as_str() == desc.id()));
+                }
+                comp.render();
+            } else if sorting::SORTING_KEYS.contains(&key) {
+                // the cheapest meal depends on the price group
+                comp.render();
+            }
+        }});
+
+        log::debug!("finish creating CompareComponent");
+
+        Ok(comp)
+    }
+
+    pub const fn root_widget(&self) -> &Box {
+        &self.page
+    }
+
+    // shows the given canteens for comparison, the visible canteen is compared if none got chosen yet
+    pub fn load(
+        &self,
+        canteens: &[Canteen],
+        titles: &[(CanteenDescription, String)],
+        visible_canteen: Option<CanteenDescription>,
+    ) {
+        log::debug!("loading canteens into CompareComponent");
+
+        self.canteens.replace(canteens.to_vec());
+
+        // compare the visible canteen with the next one of the canteen menu by default
+        if self.settings.get_strv("compare-canteens").is_empty() {
+            let visible_idx = visible_canteen
+                .and_then(|visible_canteen| titles.iter().position(|(desc, _title)| *desc == visible_canteen))
+                .unwrap_or(0);
+            let defaults = titles
+                .iter()
+                .cycle()
+                .skip(visible_idx)
+                .take(titles.len().min(2))
+                .map(|(desc, _title)| desc.id())
+                .collect::<Vec<_>>();
+            log::debug!("comparing {:?} by default", defaults);
+            self.settings.set_strv("compare-canteens", &defaults).unwrap();
+        }
+
+        for child in self.canteens_flow_box.get_children() {
+            child.destroy();
+        }
+        let compared = self.settings.get_strv("compare-canteens");
+        let settings = &self.settings;
+        let check_buttons = titles
+            .iter()
+            .map(|(desc, title)| {
+                let check_button = CheckButton::new_with_label(title);
+                check_button.set_active(compared.iter().any(|id| id.as_str() == desc.id()));
+                check_button.connect_toggled(enclose! { (settings, desc) move |check_button| {
+                    set_compared(&settings, &desc, check_button.get_active());
+                }});
+                check_button.show();
+                self.canteens_flow_box.insert(&check_button, -1);
+                (desc.clone(), check_button)
+            })
+            .collect();
+        self.check_buttons.replace(check_buttons);
+
+        // every day which is loaded in at least one canteen can be compared
+        let mut dates = canteens
+            .iter()
+            .flat_map(|canteen| canteen.days.iter().map(|day| day.date))
+            .collect::<Vec<_>>();
+        dates.sort();
+        dates.dedup();
+
+        let today = Local::today().naive_local();
+        let active_date = dates
+            .iter()
+            .find(|date| **date >= today)
+            .or_else(|| dates.last())
+            .copied();
+
+        self.date_combo_box.remove_all();
+        for date in &dates {
+            self.date_combo_box.append(Some(&date.to_string()), &format_date(*date));
+        }
+        match active_date {
+            // renders the comparison as well
+            Some(date) => {
+                self.date_combo_box.set_active_id(Some(&date.to_string()));
+            },
+            None => self.render(),
+        }
+
+        log::debug!("finish loading canteens into CompareComponent");
+    }
+
+    fn selected_date(&self) -> Option<NaiveDate> {
+        self.date_combo_box
+            .get_active_id()
+            .and_then(|id| id.as_str().parse().ok())
+    }
+
+    fn render(&self) {
+        let generation = self.generation.get() + 1;
+        self.generation.set(generation);
+
+        for child in self.columns_box.get_children() {
+            child.destroy();
+        }
+        self.meals.borrow_mut().clear();
+
+        let compared = self.settings.get_strv("compare-canteens");
+        let columns = self
+            .check_buttons
+            .borrow()
+            .iter()
+            .filter(|(desc, _check_button)| compared.iter().any(|id| id.as_str() == desc.id()))
+            .map(|(desc, check_button)| {
+                let title = check_button.get_label().map_or_else(|| desc.id().to_owned(), |label| label.to_string());
+                let canteen = self.canteens.borrow().iter().find(|canteen| canteen.description == *desc).cloned();
+                (desc.clone(), title, canteen)
+            })
+            .collect::<Vec<_>>();
+
+        let date = match self.selected_date() {
+            Some(date) => date,
+            None => {
+                self.empty_label.set_text(&t("No menus loaded yet"));
+                self.empty_label.show();
+                return;
+            },
+        };
+        if columns.is_empty() {
+            self.empty_label.set_text(&t("Select the canteens you want to compare"));
+            self.empty_label.show();
+            return;
+        }
+
+        log::debug!("comparing {} canteens on {} in CompareComponent", columns.len(), date);
+        self.empty_label.hide();
+
+        let c = glib::MainContext::default();
+        let comp = self.clone();
+        c.spawn_local(async move {
+            let order = MealOrder::from_settings(&comp.settings);
+            let filter = MealFilter::from_settings(&comp.settings);
+            let price_group = PriceGroup::from_settings(Some(&comp.settings));
+
+            for (canteen_desc, title, canteen) in columns {
+                let (column, cheapest_label, list_box) = create_column(&title);
+                comp.columns_box.pack_start(&column, true, true, 0);
+
+                let day = canteen.as_ref().and_then(|canteen| canteen.days.iter().find(|day| day.date == date));
+                let day = match day {
+                    Some(day) => day,
+                    None => {
+                        cheapest_label.set_text(&t("No menu for this day"));
+                        continue;
+                    },
+                };
+
+                let meals = order.sort(&canteen_desc, &day.meals, &comp.favorites, &comp.ratings.get());
+                let cheapest = cheapest_meal(&meals, &filter, price_group);
+                match cheapest {
+                    Some(meal) => cheapest_label.set_text(
+                        &t("Cheapest meal: {price}")
+                            .replace("{price}", &format!("{:.2} €", price_group.relevant_price(&meal.price))),
+                    ),
+                    None => cheapest_label.set_text(&t("No meal matches your filters")),
+                }
+
+                for meal in meals {
+                    let mut meal_comp = match MealComponent::new(&comp.settings, &comp.favorites, &comp.ledger, &comp.ratings).await {
+                        Ok(meal_comp) => meal_comp,
+                        Err(e) => {
+                            log::error!("failed to create compared meal: {:#}", e);
+                            return;
+                        },
+                    };
+                    list_box.insert(meal_comp.root_widget(), -1);
+                    if let Err(e) = meal_comp.load(&canteen_desc, date, meal).await {
+                        log::error!("failed to load compared meal {}: {:#}", meal.name, e);
+                    }
+                    if cheapest.map_or(false, |cheapest| std::ptr::eq(cheapest, meal)) {
+                        meal_comp.root_widget().get_style_context().add_class("meal-cheapest");
+                    }
+
+                    // another comparison got rendered while this one was loading
+                    if comp.generation.get() != generation {
+                        column.destroy();
+                        return;
+                    }
+                    comp.meals.borrow_mut().push(meal_comp);
+                }
+            }
+
+            log::debug!("finish comparing canteens on {} in CompareComponent", date);
+        });
+    }
+}
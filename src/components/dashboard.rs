@@ -0,0 +1,177 @@
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use gio::Settings;
+use gtk::prelude::*;
+use gtk::{Builder, Button, FlowBox, Frame, Label, Orientation, ScrolledWindow};
+use gettextrs::gettext as t;
+
+use crate::components::day::format_date;
+use crate::components::{get, GLADE};
+use crate::favorites::{self, Favorites};
+use crate::filter::{ExclusionMode, MealFilter};
+use crate::model::{Canteen, CanteenDescription};
+use crate::price::PriceGroup;
+use crate::ratings::Ratings;
+use crate::sorting::MealOrder;
+
+// shows the meals of today, or the next day with a menu, of all enabled canteens at a glance
+#[derive(Clone)]
+pub struct DashboardComponent {
+    page: ScrolledWindow,
+    date_label: Label,
+    empty_label: Label,
+    flow_box: FlowBox,
+    settings: Settings,
+    favorites: Favorites,
+    ratings: Ratings,
+}
+
+// today if any canteen serves meals today, otherwise the next day any canteen serves meals
+fn dashboard_date(canteens: &[Canteen]) -> Option<NaiveDate> {
+    let today = Local::today().naive_local();
+
+    canteens
+        .iter()
+        .flat_map(|canteen| canteen.days.iter())
+        .filter(|day| day.date >= today && !day.meals.is_empty())
+        .map(|day| day.date)
+        .min()
+}
+
+fn create_label(text: &str, class: Option<&str>) -> Label {
+    let label = Label::new(Some(text));
+    label.set_xalign(0.0);
+    label.set_line_wrap(true);
+    if let Some(class) = class {
+        label.get_style_context().add_class(class);
+    }
+    label
+}
+
+impl DashboardComponent {
+    pub fn new(settings: &Settings, favorites: &Favorites, ratings: &Ratings) -> Result<Self> {
+        log::debug!("creating new DashboardComponent");
+
+        let builder = Builder::new_from_string(GLADE);
+        let page: ScrolledWindow = get!(&builder, "dashboard-page")?;
+        let date_label: Label = get!(&builder, "dashboard-date-label")?;
+        let empty_label: Label = get!(&builder, "dashboard-empty-label")?;
+        let flow_box: FlowBox = get!(&builder, "dashboard-flow-box")?;
+
+        log::debug!("finish creating DashboardComponent");
+
+        Ok(Self {
+            page,
+            date_label,
+            empty_label,
+            flow_box,
+            settings: settings.clone(),
+            favorites: favorites.clone(),
+            ratings: ratings.clone(),
+        })
+    }
+
+    pub const fn root_widget(&self) -> &ScrolledWindow {
+        &self.page
+    }
+
+    pub fn load(&self, canteens: &[Canteen], titles: &[(CanteenDescription, String)]) {
+        log::debug!("loading canteens into DashboardComponent");
+
+        for child in self.flow_box.get_children() {
+            child.destroy();
+        }
+
+        let date = match dashboard_date(canteens) {
+            Some(date) => date,
+            None => {
+                self.date_label.set_text("");
+                self.empty_label.show();
+                return;
+            },
+        };
+        self.date_label.set_text(&format_date(date));
+        self.empty_label.hide();
+
+        // the canteens are shown in the order of the canteen menu
+        for (canteen_desc, title) in titles {
+            let canteen = canteens.iter().find(|canteen| canteen.description == *canteen_desc);
+            let card = self.create_card(canteen_desc, title, canteen, date);
+            self.flow_box.insert(&card, -1);
+        }
+
+        log::debug!("finish loading canteens into DashboardComponent");
+    }
+
+    fn create_card(
+        &self,
+        canteen_desc: &CanteenDescription,
+        title: &str,
+        canteen: Option<&Canteen>,
+        date: NaiveDate,
+    ) -> Frame {
+        let card_box = gtk::Box::new(Orientation::Vertical, 6);
+        card_box.set_margin_start(12);
+        card_box.set_margin_end(12);
+        card_box.set_margin_top(12);
+        card_box.set_margin_bottom(12);
+
+        let header_box = gtk::Box::new(Orientation::Horizontal, 8);
+        let title_label = create_label(title, Some("dashboard-card-title"));
+        header_box.pack_start(&title_label, true, true, 0);
+
+        // the day-selected action shows the full menu of the canteen and scrolls to the day
+        let full_menu_button = Button::new_with_label(&t("Full menu"));
+        full_menu_button.set_relief(gtk::ReliefStyle::None);
+        full_menu_button.set_valign(gtk::Align::Center);
+        full_menu_button.set_action_name(Some("app.day-selected"));
+        full_menu_button.set_action_target_value(Some(
+            &format!("{}:{}", canteen_desc.id(), date.format("%Y-%m-%d")).to_variant(),
+        ));
+        header_box.pack_start(&full_menu_button, false, true, 0);
+        card_box.pack_start(&header_box, false, true, 0);
+
+        let meals = canteen
+            .and_then(|canteen| canteen.days.iter().find(|day| day.date == date))
+            .map(|day| {
+                MealOrder::from_settings(&self.settings).sort(canteen_desc, &day.meals, &self.favorites, &self.ratings.get())
+            })
+            .unwrap_or_default();
+
+        if meals.is_empty() {
+            card_box.pack_start(&create_label(&t("No menu for this day"), Some("dim-label")), false, true, 0);
+        }
+
+        let filter = MealFilter::from_settings(&self.settings);
+        let price_group = PriceGroup::from_settings(Some(&self.settings));
+        for meal in meals {
+            let excluded = !filter.check(meal).is_empty();
+            if excluded && filter.mode() == ExclusionMode::Hide {
+                continue;
+            }
+
+            let meal_box = gtk::Box::new(Orientation::Horizontal, 8);
+            let name = if favorites::is_favorite(&self.favorites, &meal.name) {
+                format!("★ {}", meal.name)
+            } else {
+                meal.name.clone()
+            };
+            meal_box.pack_start(&create_label(&name, None), true, true, 0);
+
+            let price_label = Label::new(Some(&format!("{:.2} €", price_group.relevant_price(&meal.price))));
+            price_label.set_valign(gtk::Align::Start);
+            meal_box.pack_start(&price_label, false, true, 0);
+
+            if excluded {
+                meal_box.get_style_context().add_class("dashboard-meal-excluded");
+            }
+            card_box.pack_start(&meal_box, false, true, 0);
+        }
+
+        let card = Frame::new(None);
+        card.get_style_context().add_class("dashboard-card");
+        card.add(&card_box);
+        card.show_all();
+        card
+    }
+}
@@ -1,33 +1,133 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
 
 use anyhow::{Error, Result};
-use chrono::{Datelike, TimeZone, Utc, Weekday};
+use chrono::{Datelike, Local, NaiveDate, TimeZone, Utc, Weekday};
+use gio::prelude::*;
+use gio::Settings;
+use glib::SignalHandlerId;
 use gtk::prelude::*;
-use gtk::{Builder, FlowBox, Frame, InfoBar, Label, ListBox};
+use gtk::{Builder, FlowBox, Frame, InfoBar, Label, ListBox, ListBoxRow};
 use gettextrs::gettext as t;
-use ovgu_canteen::Day;
 
 use crate::components::{
     get, glib_yield, BadgeComponent, LiteBadgeComponent, MealComponent, GLADE,
 };
+use crate::favorites::{self, Favorites};
+use crate::filter;
+use crate::ledger::Ledger;
+use crate::ratings::Ratings;
+use crate::model::{CanteenDescription, Day};
+use crate::sorting::{MealOrder, Protein};
 use crate::util::{enclose, AdjustingVec};
 
 pub struct DayComponent {
+    date: Option<NaiveDate>,
     frame: Frame,
     label: Label,
     date_label: Label,
     error: InfoBar,
     error_label: Label,
     side_dish_badges: FlowBox,
+    meals_list_box: ListBox,
+    // the protein group of each meal row, empty if the meals are not grouped
+    groups: Rc<RefCell<Vec<Protein>>>,
+    settings: Settings,
+    favorites: Favorites,
+    ratings: Ratings,
     empty_side_dishes_label: Option<LiteBadgeComponent>,
     meals: AdjustingVec<MealComponent, Error>,
     side_dishes: AdjustingVec<BadgeComponent, Error>,
     scroll_to: Box<dyn Fn(i32) + 'static>,
 }
 
+pub fn translate_weekday(weekday: Weekday) -> String {
+    log::debug!("translating weekday {:?}", weekday);
+
+    match weekday {
+        Weekday::Mon => t("Monday"),
+        Weekday::Tue => t("Tuesday"),
+        Weekday::Wed => t("Wednesday"),
+        Weekday::Thu => t("Thursday"),
+        Weekday::Fri => t("Friday"),
+        Weekday::Sat => t("Saturday"),
+        Weekday::Sun => t("Sunday"),
+    }
+}
+
+// shows a heading above the first visible meal of each protein group
+fn update_group_header(row: &ListBoxRow, groups: &[Protein]) {
+    let group_of = |row: &ListBoxRow| usize::try_from(row.get_index()).ok().and_then(|idx| groups.get(idx).copied());
+    let list_box = row.get_parent().and_then(|parent| parent.downcast::<ListBox>().ok());
+
+    // the meals of a group are next to each other, so the rows before the row are checked until another group starts
+    let is_first_visible = |group| {
+        list_box.as_ref().map_or(true, |list_box| {
+            (0..row.get_index())
+                .rev()
+                .filter_map(|idx| list_box.get_row_at_index(idx))
+                .take_while(|previous| group_of(previous) == Some(group))
+                .all(|previous| !previous.get_visible())
+        })
+    };
+
+    let group = match group_of(row) {
+        Some(group) if row.get_visible() && is_first_visible(group) => group,
+        _ => {
+            row.set_header(None::<&Label>);
+            return;
+        },
+    };
+
+    match row.get_header().and_then(|header| header.downcast::<Label>().ok()) {
+        Some(header) => header.set_text(&group.translate()),
+        None => {
+            let header = Label::new(Some(&group.translate()));
+            header.set_xalign(0.0);
+            header.get_style_context().add_class("meal-group-header");
+            header.show();
+            row.set_header(Some(&header));
+        },
+    }
+}
+
+// gtk only updates the headers of visible rows, so the headers of hidden rows are removed here
+fn update_group_headers(list_box: &ListBox) {
+    for row in list_box.get_children() {
+        if let Ok(row) = row.downcast::<ListBoxRow>() {
+            if !row.get_visible() {
+                row.set_header(None::<&Label>);
+            }
+        }
+    }
+    list_box.invalidate_headers();
+}
+
+// names today and tomorrow, e.g. for choosing a day
+pub fn format_date(date: NaiveDate) -> String {
+    let today = Local::today().naive_local();
+    let day_name = if date == today {
+        t("Today")
+    } else if date == today.succ() {
+        t("Tomorrow")
+    } else {
+        translate_weekday(date.weekday())
+    };
+
+    format!("{}, {}", day_name, date.format("%d.%m.%Y"))
+}
+
 impl DayComponent {
-    pub async fn new<F: Fn(i32) + 'static>(scroll_to: F) -> Result<Self> {
+    pub async fn new<F: Fn(i32) + 'static>(
+        settings: &Settings,
+        favorites: &Favorites,
+        ledger: &Ledger,
+        ratings: &Ratings,
+        scroll_to: F,
+    ) -> Result<Self> {
         log::debug!("creating new DayComponent");
 
         let builder = Builder::new_from_string(GLADE);
@@ -39,18 +139,39 @@ impl DayComponent {
         let meals_list_box: ListBox = get!(&builder, "day-meals-list-box")?;
         let side_dish_badges: FlowBox = get!(&builder, "side-dish-badges")?;
 
+        let groups: Rc<RefCell<Vec<Protein>>> = Rc::new(RefCell::new(Vec::new()));
+        meals_list_box.set_header_func(Some(Box::new(enclose! { (groups) move |row, _before| {
+            update_group_header(row, &groups.borrow());
+        }})));
+
+        // in hide mode, all meals of a group might get hidden by the filters
+        let signal_handler = settings.connect_changed(enclose! { (meals_list_box) move |_settings, key| {
+            if filter::FILTER_KEYS.contains(&key) {
+                let c = glib::MainContext::default();
+                c.spawn_local(enclose! { (meals_list_box) async move {
+                    glib_yield!(); // the meals apply the filters in their own handlers
+                    update_group_headers(&meals_list_box);
+                }});
+            }
+        }});
+        frame.connect_destroy(enclose! { (settings) move |_frame| {
+            use glib::translate::{FromGlib, ToGlib};
+            settings.disconnect(SignalHandlerId::from_glib(signal_handler.to_glib()));
+        }});
+
         log::debug!("setting up AdjustingVec in DayComponent");
 
         // these counters track the current insertion index in meals_list and side_dish_badges
         let meal_offset = Arc::new(AtomicI32::new(0));
         let side_dish_offset = Arc::new(AtomicI32::new(0));
 
-        // create a new adjusting vector which adjusts its size according to an input iterator
-        let meals = AdjustingVec::new(
+        // create a new adjusting vector which adjusts its size according to an input iterator,
+        // the meals are matched by their name so their rows keep their state when they get reordered
+        let meals = AdjustingVec::new_keyed(
             // define how to create a new MealComponent
-            enclose! { (meals_list_box, meal_offset) move || {
-                enclose! { (meals_list_box, meal_offset) async move {
-                    let comp = MealComponent::new().await?;
+            enclose! { (meals_list_box, meal_offset, settings, favorites, ledger, ratings) move || {
+                enclose! { (meals_list_box, meal_offset, settings, favorites, ledger, ratings) async move {
+                    let comp = MealComponent::new(&settings, &favorites, &ledger, &ratings).await?;
                     meals_list_box.insert(comp.root_widget(), meal_offset.load(Ordering::SeqCst));
                     meal_offset.fetch_add(1, Ordering::SeqCst);
 
@@ -68,6 +189,15 @@ impl DayComponent {
                     Ok(())
                 }}
             }},
+            // define how to move a MealComponent, the number of rows and therefore meal_offset stays the same
+            enclose! { (meals_list_box) move |meal: &MealComponent, idx| {
+                let row = meal.root_widget();
+                let idx = idx as i32;
+                if row.get_index() != idx {
+                    meals_list_box.remove(row);
+                    meals_list_box.insert(row, idx);
+                }
+            }},
         );
 
         // create a new adjusting vector which adjusts its size according to an input iterator
@@ -99,8 +229,14 @@ impl DayComponent {
         log::debug!("finish creating DayComponent");
 
         Ok(Self {
+            date: None,
             empty_side_dishes_label: None,
             side_dish_badges,
+            meals_list_box,
+            groups,
+            settings: settings.clone(),
+            favorites: favorites.clone(),
+            ratings: ratings.clone(),
             frame,
             label,
             date_label,
@@ -116,18 +252,16 @@ impl DayComponent {
         &self.frame
     }
 
-    pub async fn load(&mut self, day: &Day) {
+    pub const fn date(&self) -> Option<NaiveDate> {
+        self.date
+    }
+
+    pub async fn load(&mut self, canteen_desc: &CanteenDescription, day: &Day) {
         log::debug!("loading content into DayComponent {}", day.date);
 
-        let mut day_name = match day.date.weekday() {
-            Weekday::Mon => t("Monday"),
-            Weekday::Tue => t("Tuesday"),
-            Weekday::Wed => t("Wednesday"),
-            Weekday::Thu => t("Thursday"),
-            Weekday::Fri => t("Friday"),
-            Weekday::Sat => t("Saturday"),
-            Weekday::Sun => t("Sunday"),
-        };
+        self.date = Some(day.date);
+
+        let mut day_name = translate_weekday(day.date.weekday());
         let today = Utc::today();
         let date = chrono_tz::Europe::Berlin.ymd(day.date.year(), day.date.month(), day.date.day());
         // add special display for today and tomorrow
@@ -141,19 +275,32 @@ impl DayComponent {
         self.label.set_text(&day_name);
         self.date_label.set_text(&format!("{}", day.date.format("%d.%m.%Y")));
 
+        log::debug!("sorting meals of DayComponent {}", day.date);
+
+        let order = MealOrder::from_settings(&self.settings);
+        let meals = order.sort(canteen_desc, &day.meals, &self.favorites, &self.ratings.get());
+        self.groups.replace(if order.group_by_protein() {
+            meals.iter().map(|meal| Protein::of(meal)).collect()
+        } else {
+            Vec::new()
+        });
+
         log::debug!("loading meals into DayComponent {}", day.date);
 
-        // adjust MealComponents to match day.meals
+        // adjust MealComponents to match the sorted meals
         let meal_result = self
             .meals
-            .adjust(&day.meals, |mut comp, meal| async move {
+            .adjust_keyed(meals, |meal| favorites::normalize(&meal.name), |mut comp, meal| async move {
                 // define how to update a MealComponent
-                comp.load(meal).await?;
+                comp.load(canteen_desc, day.date, meal).await?;
                 glib_yield!(); // give gtk a chance to update the UI
                 Ok(comp)
             })
             .await;
 
+        // the groups of the rows might have changed without rows being added or removed
+        update_group_headers(&self.meals_list_box);
+
         log::debug!("loading side dishes into DayComponent {}", day.date);
 
         // adjust BadgeComponents for side dishes to match day.side_dishes
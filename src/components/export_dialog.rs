@@ -0,0 +1,182 @@
+use std::fs;
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate};
+use gtk::prelude::*;
+use gtk::{
+    Builder, Button, ButtonsType, CheckButton, ComboBoxText, DialogFlags, FileChooserAction,
+    FileChooserNative, FlowBox, MessageDialog, MessageType, ResponseType, Window,
+};
+use gettextrs::gettext as t;
+use itertools::Itertools;
+
+use crate::components::day::translate_weekday;
+use crate::components::{get, WindowComponent, GLADE};
+use crate::export::{self, Format, Selection, FORMATS};
+use crate::filter::MealFilter;
+use crate::model::{Canteen, CanteenDescription};
+use crate::price::PriceGroup;
+use crate::util::enclose;
+use crate::canteen;
+
+fn selection(
+    window: &WindowComponent,
+    canteen_buttons: &[(CanteenDescription, CheckButton)],
+    from_combo_box: &ComboBoxText,
+    until_combo_box: &ComboBoxText,
+    apply_filters_check_button: &CheckButton,
+    favorites_only_check_button: &CheckButton,
+) -> Selection {
+    let parse_date = |combo_box: &ComboBoxText| {
+        combo_box
+            .get_active_id()
+            .and_then(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok())
+    };
+
+    Selection {
+        canteens: Some(
+            canteen_buttons
+                .iter()
+                .filter(|(_desc, check_button)| check_button.get_active())
+                .map(|(desc, _check_button)| desc.clone())
+                .collect(),
+        ),
+        from: parse_date(from_combo_box),
+        until: parse_date(until_combo_box),
+        meal_filter: if apply_filters_check_button.get_active() {
+            Some(MealFilter::from_settings(window.settings()))
+        } else {
+            None
+        },
+        favorites: if favorites_only_check_button.get_active() {
+            Some(window.favorites().get().clone())
+        } else {
+            None
+        },
+    }
+}
+
+// returns whether the export got saved or the user cancelled
+fn save(dialog: &Window, format: Format, exported: &str) -> Result<bool> {
+    let file_chooser = FileChooserNative::new(
+        Some(&t("Export Menus")),
+        Some(dialog),
+        FileChooserAction::Save,
+        Some(&t("_Export")),
+        Some(&t("_Cancel")),
+    );
+    file_chooser.set_do_overwrite_confirmation(true);
+    file_chooser.set_current_name(&format!("{}.{}", t("menu"), format.extension()));
+
+    if file_chooser.run() != ResponseType::Accept {
+        log::debug!("export got cancelled");
+        return Ok(false);
+    }
+
+    let path = file_chooser.get_filename().context("No file selected")?;
+    log::debug!("writing exported menus to {:?}", path);
+    fs::write(&path, exported).with_context(|| format!("Could not write to {}", path.display()))?;
+
+    Ok(true)
+}
+
+pub fn open(window: &WindowComponent, canteens: &[Canteen]) -> Result<()> {
+    log::debug!("opening up export dialog");
+
+    let builder = Builder::new_from_string(GLADE);
+    let dialog: Window = get!(&builder, "export-dialog")?;
+    let save_button: Button = get!(&builder, "export-save-button")?;
+    let format_combo_box: ComboBoxText = get!(&builder, "export-format-combo-box")?;
+    let canteens_flow_box: FlowBox = get!(&builder, "export-canteens-flow-box")?;
+    let from_combo_box: ComboBoxText = get!(&builder, "export-from-combo-box")?;
+    let until_combo_box: ComboBoxText = get!(&builder, "export-until-combo-box")?;
+    let apply_filters_check_button: CheckButton = get!(&builder, "export-apply-filters-check-button")?;
+    let favorites_only_check_button: CheckButton = get!(&builder, "export-favorites-only-check-button")?;
+
+    let parent_window = window.window();
+    if let Some(application) = parent_window.get_application() {
+        dialog.set_application(Some(&application));
+    }
+    dialog.set_transient_for(Some(parent_window));
+    dialog.set_attached_to(Some(parent_window));
+
+    for &(name, format) in FORMATS.iter() {
+        format_combo_box.append(Some(name), &format.translate());
+    }
+    format_combo_box.set_active_id(Some("markdown"));
+
+    // only the loaded canteens can be exported
+    let canteen_buttons = canteens
+        .iter()
+        .map(|canteen| {
+            let check_button = CheckButton::new_with_label(&canteen::name(canteen));
+            check_button.set_active(true);
+            check_button.set_visible(true);
+            canteens_flow_box.insert(&check_button, -1);
+            (canteen.description.clone(), check_button)
+        })
+        .collect::<Vec<_>>();
+
+    let dates = canteens
+        .iter()
+        .flat_map(|canteen| canteen.days.iter().map(|day| day.date))
+        .sorted()
+        .dedup()
+        .collect::<Vec<_>>();
+    for date in &dates {
+        let id = format!("{}", date.format("%Y-%m-%d"));
+        let label = format!("{}, {}", translate_weekday(date.weekday()), date.format("%d.%m.%Y"));
+        from_combo_box.append(Some(&id), &label);
+        until_combo_box.append(Some(&id), &label);
+    }
+    if !dates.is_empty() {
+        from_combo_box.set_active(Some(0));
+        until_combo_box.set_active(Some(dates.len() as u32 - 1));
+    }
+
+    let canteens = canteens.to_vec();
+    save_button.connect_clicked(enclose! { (window, dialog) move |_btn| {
+        let format = format_combo_box
+            .get_active_id()
+            .and_then(|name| Format::from_name(&name))
+            .unwrap_or(Format::Markdown);
+        let selection = selection(
+            &window,
+            &canteen_buttons,
+            &from_combo_box,
+            &until_combo_box,
+            &apply_filters_check_button,
+            &favorites_only_check_button,
+        );
+        log::debug!("exporting canteens as {:?} with {:?}", format, selection);
+
+        let price_group = PriceGroup::from_settings(Some(window.settings()));
+        let result = export::export(&selection.apply(&canteens), format, price_group)
+            .and_then(|exported| save(&dialog, format, &exported));
+
+        match result {
+            Ok(true) => dialog.close(),
+            Ok(false) => {},
+            Err(e) => {
+                log::error!("{:#}", e);
+                let message = MessageDialog::new(
+                    Some(&dialog),
+                    DialogFlags::MODAL,
+                    MessageType::Error,
+                    ButtonsType::Close,
+                    &format!("{}: {:#}", t("Failed to export menus"), e),
+                );
+                message.run();
+                message.destroy();
+            },
+        }
+    }});
+
+    log::debug!("showing export dialog");
+
+    dialog.show_all();
+
+    log::debug!("finish opening up export dialog");
+
+    Ok(())
+}
@@ -0,0 +1,303 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDate};
+use gio::Settings;
+use gtk::prelude::*;
+use gtk::{Box, Builder, Calendar, ComboBoxText, Label, ListBox, ListBoxRow, Orientation, SearchEntry};
+use gettextrs::gettext as t;
+
+use crate::archive::{self, Archive};
+use crate::canteen;
+use crate::components::day::translate_weekday;
+use crate::components::{get, DayComponent, GLADE};
+use crate::favorites::Favorites;
+use crate::ledger::Ledger;
+use crate::ratings::Ratings;
+use crate::model::CanteenDescription;
+use crate::util::enclose;
+
+// browses the archive of all menus, see the archive settings key
+#[derive(Clone)]
+pub struct HistoryComponent {
+    page: Box,
+    canteen_combo_box: ComboBoxText,
+    calendar: Calendar,
+    search_entry: SearchEntry,
+    results_list_box: ListBox,
+    day_box: Box,
+    empty_label: Label,
+    settings: Settings,
+    favorites: Favorites,
+    ledger: Ledger,
+    ratings: Ratings,
+    archive: Rc<RefCell<Archive>>,
+    titles: Rc<RefCell<Vec<(CanteenDescription, String)>>>,
+    // the canteen and date of each row in the results list box
+    results: Rc<RefCell<Vec<(CanteenDescription, NaiveDate)>>>,
+    day: Rc<RefCell<Option<DayComponent>>>,
+    // increased on every shown day, so a day which is still loading can tell it is outdated
+    day_generation: Rc<Cell<usize>>,
+}
+
+fn create_result_row(meal_name: &str, detail: &str) -> ListBoxRow {
+    let row_box = Box::new(Orientation::Vertical, 2);
+    row_box.set_margin_start(8);
+    row_box.set_margin_end(8);
+    row_box.set_margin_top(8);
+    row_box.set_margin_bottom(8);
+
+    let name_label = Label::new(Some(meal_name));
+    name_label.set_xalign(0.0);
+    name_label.set_line_wrap(true);
+    row_box.pack_start(&name_label, false, true, 0);
+
+    let detail_label = Label::new(Some(detail));
+    detail_label.set_xalign(0.0);
+    detail_label.set_line_wrap(true);
+    detail_label.get_style_context().add_class("dim-label");
+    detail_label.get_style_context().add_class("small");
+    row_box.pack_start(&detail_label, false, true, 0);
+
+    let row = ListBoxRow::new();
+    row.add(&row_box);
+    row.show_all();
+    row
+}
+
+impl HistoryComponent {
+    pub fn new(settings: &Settings, favorites: &Favorites, ledger: &Ledger, ratings: &Ratings) -> Result<Self> {
+        log::debug!("creating new HistoryComponent");
+
+        let builder = Builder::new_from_string(GLADE);
+        let page: Box = get!(&builder, "history-page")?;
+        let canteen_combo_box: ComboBoxText = get!(&builder, "history-canteen-combo-box")?;
+        let calendar: Calendar = get!(&builder, "history-calendar")?;
+        let search_entry: SearchEntry = get!(&builder, "history-search-entry")?;
+        let results_list_box: ListBox = get!(&builder, "history-results-list-box")?;
+        let day_box: Box = get!(&builder, "history-day-box")?;
+        let empty_label: Label = get!(&builder, "history-empty-label")?;
+
+        let comp = Self {
+            page,
+            canteen_combo_box,
+            calendar,
+            search_entry,
+            results_list_box,
+            day_box,
+            empty_label,
+            settings: settings.clone(),
+            favorites: favorites.clone(),
+            ledger: ledger.clone(),
+            ratings: ratings.clone(),
+            archive: Rc::new(RefCell::new(Archive::default())),
+            titles: Rc::new(RefCell::new(Vec::new())),
+            results: Rc::new(RefCell::new(Vec::new())),
+            day: Rc::new(RefCell::new(None)),
+            day_generation: Rc::new(Cell::new(0)),
+        };
+
+        comp.canteen_combo_box.connect_changed(enclose! { (comp) move |_combo_box| {
+            comp.mark_archived_days();
+            comp.show_selected_day();
+        }});
+
+        comp.calendar.connect_month_changed(enclose! { (comp) move |_calendar| {
+            comp.mark_archived_days();
+        }});
+
+        comp.calendar.connect_day_selected(enclose! { (comp) move |_calendar| {
+            comp.show_selected_day();
+        }});
+
+        comp.search_entry.connect_search_changed(enclose! { (comp) move |_entry| {
+            comp.update_results();
+        }});
+
+        comp.results_list_box.connect_row_activated(enclose! { (comp) move |_list_box, row| {
+            let result = comp.results.borrow().get(row.get_index() as usize).cloned();
+            if let Some((canteen_desc, date)) = result {
+                comp.select(&canteen_desc, date);
+            }
+        }});
+
+        log::debug!("finish creating HistoryComponent");
+
+        Ok(comp)
+    }
+
+    pub const fn root_widget(&self) -> &Box {
+        &self.page
+    }
+
+    // reads the archive again and shows the given canteen on the current date
+    pub fn load(&self, canteens: &[(CanteenDescription, String)], visible_canteen: Option<CanteenDescription>) {
+        log::debug!("loading archive into HistoryComponent");
+
+        self.archive.replace(archive::read());
+
+        // hidden canteens and canteens of removed sources may still be archived
+        let mut titles = canteens.to_vec();
+        for canteen_desc in self.archive.borrow().canteens() {
+            if !titles.iter().any(|(desc, _title)| desc == &canteen_desc) {
+                let title = canteen::translate(&canteen_desc);
+                titles.push((canteen_desc, title));
+            }
+        }
+
+        self.canteen_combo_box.remove_all();
+        for (canteen_desc, title) in &titles {
+            self.canteen_combo_box.append(Some(canteen_desc.id()), title);
+        }
+
+        let today = Local::today().naive_local();
+        self.calendar.select_month(today.month0(), today.year() as u32);
+        self.calendar.select_day(today.day());
+
+        self.titles.replace(titles.clone());
+
+        let active_canteen = visible_canteen
+            .filter(|visible_canteen| titles.iter().any(|(desc, _title)| desc == visible_canteen))
+            .or_else(|| titles.first().map(|(desc, _title)| desc.clone()));
+        match active_canteen {
+            // shows the selected day as well
+            Some(canteen_desc) => {
+                self.canteen_combo_box.set_active_id(Some(canteen_desc.id()));
+            },
+            None => self.show_selected_day(),
+        }
+
+        self.update_results();
+
+        log::debug!("finish loading archive into HistoryComponent");
+    }
+
+    fn select(&self, canteen_desc: &CanteenDescription, date: NaiveDate) {
+        log::debug!("selecting {} of canteen {:?} in HistoryComponent", date, canteen_desc);
+
+        self.canteen_combo_box.set_active_id(Some(canteen_desc.id()));
+        self.calendar.select_month(date.month0(), date.year() as u32);
+        self.calendar.select_day(date.day());
+    }
+
+    fn title(&self, canteen_desc: &CanteenDescription) -> String {
+        self.titles
+            .borrow()
+            .iter()
+            .find(|(desc, _title)| desc == canteen_desc)
+            .map_or_else(|| canteen::translate(canteen_desc), |(_desc, title)| title.clone())
+    }
+
+    fn selected_canteen(&self) -> Option<CanteenDescription> {
+        self.canteen_combo_box
+            .get_active_id()
+            .map(|id| CanteenDescription::new(id.as_str()))
+    }
+
+    fn selected_date(&self) -> Option<NaiveDate> {
+        let (year, month, day) = self.calendar.get_date();
+        NaiveDate::from_ymd_opt(year as i32, month + 1, day)
+    }
+
+    // marks all days of the shown month on which a menu is archived
+    fn mark_archived_days(&self) {
+        self.calendar.clear_marks();
+
+        let canteen_desc = match self.selected_canteen() {
+            Some(canteen_desc) => canteen_desc,
+            None => return,
+        };
+        let (year, month, _day) = self.calendar.get_date();
+        for date in self.archive.borrow().dates(&canteen_desc) {
+            if date.year() == year as i32 && date.month0() == month {
+                self.calendar.mark_day(date.day());
+            }
+        }
+    }
+
+    fn show_selected_day(&self) {
+        let day_generation = self.day_generation.get() + 1;
+        self.day_generation.set(day_generation);
+
+        if let Some(day_comp) = self.day.borrow_mut().take() {
+            day_comp.root_widget().destroy();
+        }
+
+        let day = match (self.selected_canteen(), self.selected_date()) {
+            (Some(canteen_desc), Some(date)) => self
+                .archive
+                .borrow()
+                .day(&canteen_desc, date)
+                .cloned()
+                .map(|day| (canteen_desc, day)),
+            _ => None,
+        };
+        let (canteen_desc, day) = match day {
+            Some(day) => day,
+            None => {
+                self.empty_label.set_text(&if self.archive.borrow().is_empty() {
+                    t("No menus archived yet. Enable the archive in the preferences to keep all menus.")
+                } else {
+                    t("No menu archived for this day")
+                });
+                self.empty_label.show();
+                return;
+            },
+        };
+
+        log::debug!("showing archived day {} in HistoryComponent", day.date);
+        self.empty_label.hide();
+
+        let c = glib::MainContext::default();
+        let comp = self.clone();
+        c.spawn_local(async move {
+            let mut day_comp = match DayComponent::new(&comp.settings, &comp.favorites, &comp.ledger, &comp.ratings, |_y| {}).await {
+                Ok(day_comp) => day_comp,
+                Err(e) => {
+                    log::error!("failed to create archived day: {:#}", e);
+                    return;
+                },
+            };
+            comp.day_box.pack_start(day_comp.root_widget(), false, true, 0);
+            day_comp.load(&canteen_desc, &day).await;
+
+            // another day got selected while this one was loading
+            if comp.day_generation.get() != day_generation {
+                day_comp.root_widget().destroy();
+                return;
+            }
+            comp.day.replace(Some(day_comp));
+        });
+    }
+
+    fn update_results(&self) {
+        for child in self.results_list_box.get_children() {
+            child.destroy();
+        }
+
+        let query = self.search_entry.get_text().map(|text| text.to_string()).unwrap_or_default();
+        let results = self
+            .archive
+            .borrow()
+            .last_served(&query, Local::today().naive_local());
+        log::debug!("meals matching {} were served {} times in archive", query, results.len());
+
+        for (canteen_desc, date, meal_name) in &results {
+            let detail = format!(
+                "{}, {} – {}",
+                translate_weekday(date.weekday()),
+                date.format("%d.%m.%Y"),
+                self.title(canteen_desc),
+            );
+            self.results_list_box.insert(&create_result_row(meal_name, &detail), -1);
+        }
+
+        self.results.replace(
+            results
+                .into_iter()
+                .map(|(canteen_desc, date, _meal_name)| (canteen_desc, date))
+                .collect(),
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use std::fs;
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Local};
+use gio::prelude::*;
+use gio::Settings;
+use gtk::prelude::*;
+use gtk::{
+    Builder, Button, ButtonsType, DialogFlags, FileChooserAction, FileChooserNative, IconSize, InfoBar, Label,
+    ListBox, ListBoxRow, MessageDialog, MessageType, Orientation, ResponseType, ScrolledWindow, Window,
+};
+use gettextrs::gettext as t;
+
+use crate::canteen;
+use crate::components::day::translate_weekday;
+use crate::components::{get, GLADE};
+use crate::ledger::{self, Entry, Ledger};
+use crate::util::enclose;
+
+// shows the meals the user ate and what they cost, see the monthly-budget settings key
+#[derive(Clone)]
+pub struct LedgerComponent {
+    page: ScrolledWindow,
+    budget_info_bar: InfoBar,
+    budget_label: Label,
+    week_label: Label,
+    month_label: Label,
+    budget_status_label: Label,
+    empty_label: Label,
+    list_box: ListBox,
+    settings: Settings,
+    ledger: Ledger,
+}
+
+fn format_price(price: f32) -> String {
+    format!("{:.2} €", price)
+}
+
+fn create_entry_row(entry: &Entry, ledger: &Ledger) -> ListBoxRow {
+    let row_box = gtk::Box::new(Orientation::Horizontal, 16);
+    row_box.set_margin_start(8);
+    row_box.set_margin_end(8);
+    row_box.set_margin_top(8);
+    row_box.set_margin_bottom(8);
+
+    let text_box = gtk::Box::new(Orientation::Vertical, 2);
+    let name_label = Label::new(Some(&entry.meal));
+    name_label.set_xalign(0.0);
+    name_label.set_line_wrap(true);
+    text_box.pack_start(&name_label, false, true, 0);
+
+    let detail_label = Label::new(Some(&format!(
+        "{}, {} – {}",
+        translate_weekday(entry.date.weekday()),
+        entry.date.format("%d.%m.%Y"),
+        canteen::translate(&entry.canteen),
+    )));
+    detail_label.set_xalign(0.0);
+    detail_label.set_line_wrap(true);
+    detail_label.get_style_context().add_class("dim-label");
+    detail_label.get_style_context().add_class("small");
+    text_box.pack_start(&detail_label, false, true, 0);
+    row_box.pack_start(&text_box, true, true, 0);
+
+    let price_label = Label::new(Some(&format_price(entry.price)));
+    row_box.pack_start(&price_label, false, true, 0);
+
+    let remove_button = Button::new_from_icon_name(Some("user-trash-symbolic"), IconSize::Button);
+    remove_button.set_relief(gtk::ReliefStyle::None);
+    remove_button.set_valign(gtk::Align::Center);
+    remove_button.set_tooltip_text(Some(&t("Remove")));
+    remove_button.connect_clicked(enclose! { (entry, ledger) move |_btn| {
+        ledger::set_eaten(&ledger, entry.clone(), false);
+    }});
+    row_box.pack_start(&remove_button, false, true, 0);
+
+    let row = ListBoxRow::new();
+    row.set_selectable(false);
+    row.set_activatable(false);
+    row.add(&row_box);
+    row.show_all();
+    row
+}
+
+impl LedgerComponent {
+    pub fn new(settings: &Settings, ledger: &Ledger) -> Result<Self> {
+        log::debug!("creating new LedgerComponent");
+
+        let builder = Builder::new_from_string(GLADE);
+        let page: ScrolledWindow = get!(&builder, "ledger-page")?;
+        let export_button: Button = get!(&builder, "ledger-export-button")?;
+        let budget_info_bar: InfoBar = get!(&builder, "ledger-budget-info-bar")?;
+        let budget_label: Label = get!(&builder, "ledger-budget-label")?;
+        let week_label: Label = get!(&builder, "ledger-week-label")?;
+        let month_label: Label = get!(&builder, "ledger-month-label")?;
+        let budget_status_label: Label = get!(&builder, "ledger-budget-status-label")?;
+        let empty_label: Label = get!(&builder, "ledger-empty-label")?;
+        let list_box: ListBox = get!(&builder, "ledger-list-box")?;
+
+        let comp = Self {
+            page,
+            budget_info_bar,
+            budget_label,
+            week_label,
+            month_label,
+            budget_status_label,
+            empty_label,
+            list_box,
+            settings: settings.clone(),
+            ledger: ledger.clone(),
+        };
+
+        // the component lives as long as the window, so these are never disconnected
+        comp.ledger.connect_changed(enclose! { (comp) move |_entries| {
+            comp.load();
+        }});
+
+        comp.settings.connect_changed(enclose! { (comp) move |_settings, key| {
+            if key == "monthly-budget" {
+                comp.load();
+            }
+        }});
+
+        export_button.connect_clicked(enclose! { (comp) move |_btn| {
+            if let Err(e) = comp.export() {
+                log::error!("{:#}", e);
+                let parent = comp.parent_window();
+                let message = MessageDialog::new(
+                    parent.as_ref(),
+                    DialogFlags::MODAL,
+                    MessageType::Error,
+                    ButtonsType::Close,
+                    &format!("{}: {:#}", t("Failed to export spending"), e),
+                );
+                message.run();
+                message.destroy();
+            }
+        }});
+
+        comp.load();
+
+        log::debug!("finish creating LedgerComponent");
+
+        Ok(comp)
+    }
+
+    pub const fn root_widget(&self) -> &ScrolledWindow {
+        &self.page
+    }
+
+    fn parent_window(&self) -> Option<Window> {
+        self.page
+            .get_toplevel()
+            .and_then(|toplevel| toplevel.downcast::<Window>().ok())
+    }
+
+    pub fn load(&self) {
+        log::debug!("loading ledger into LedgerComponent");
+
+        for child in self.list_box.get_children() {
+            child.destroy();
+        }
+
+        let entries = self.ledger.get();
+        let today = Local::today().naive_local();
+        let month_total = ledger::month_total(&entries, today);
+        self.week_label.set_text(&format_price(ledger::week_total(&entries, today)));
+        self.month_label.set_text(&format_price(month_total));
+
+        match ledger::budget(&self.settings) {
+            Some(budget) if month_total > budget => {
+                self.budget_label.set_text(
+                    &t("You exceeded your monthly budget of {budget} by {amount}")
+                        .replace("{budget}", &format_price(budget))
+                        .replace("{amount}", &format_price(month_total - budget)),
+                );
+                self.budget_info_bar.show();
+                self.budget_status_label.hide();
+            },
+            Some(budget) => {
+                self.budget_status_label.set_text(
+                    &t("{amount} left of your monthly budget of {budget}")
+                        .replace("{amount}", &format_price(budget - month_total))
+                        .replace("{budget}", &format_price(budget)),
+                );
+                self.budget_status_label.show();
+                self.budget_info_bar.hide();
+            },
+            None => {
+                self.budget_status_label.set_text(&t("Set a monthly budget in the preferences to get warned"));
+                self.budget_status_label.show();
+                self.budget_info_bar.hide();
+            },
+        }
+
+        // the latest meals come first
+        for entry in entries.iter().rev() {
+            self.list_box.insert(&create_entry_row(entry, &self.ledger), -1);
+        }
+        self.empty_label.set_visible(entries.is_empty());
+    }
+
+    fn export(&self) -> Result<()> {
+        let file_chooser = FileChooserNative::new(
+            Some(&t("Export Spending")),
+            self.parent_window().as_ref(),
+            FileChooserAction::Save,
+            Some(&t("_Export")),
+            Some(&t("_Cancel")),
+        );
+        file_chooser.set_do_overwrite_confirmation(true);
+        file_chooser.set_current_name(&format!("{}.csv", t("spending")));
+
+        if file_chooser.run() != ResponseType::Accept {
+            log::debug!("export of ledger got cancelled");
+            return Ok(());
+        }
+
+        let path = file_chooser.get_filename().context("No file selected")?;
+        log::debug!("writing exported ledger to {:?}", path);
+        let csv = ledger::export_csv(&self.ledger.get());
+        fs::write(&path, csv).with_context(|| format!("Could not write to {}", path.display()))?;
+
+        Ok(())
+    }
+}
@@ -1,27 +1,106 @@
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
 
-use anyhow::{Error, Result};
+use anyhow::{Context, Error, Result};
+use chrono::NaiveDate;
+use gio::prelude::*;
+use gio::Settings;
+use glib::SignalHandlerId;
 use gtk::prelude::*;
-use gtk::{Builder, FlowBox, Label, ListBoxRow};
+use gtk::{
+    Builder, Button, FlowBox, GestureMultiPress, Grid, Image, IconSize, InfoBar, Label, ListBoxRow, MenuButton, Popover,
+    Separator, TextBuffer, TextView, ToggleButton,
+};
 use gettextrs::gettext as t;
-use ovgu_canteen::{Meal, Additive, Allergenic, Symbol};
 
 use crate::components::{get, glib_yield, SymbolComponent, LiteBadgeComponent, GLADE};
+use crate::favorites::{self, Favorites};
+use crate::filter::{self, ExclusionMode, MealFilter};
+use crate::ledger::{self, Entry, Ledger};
+use crate::legend::{self, EuAllergen};
+use crate::model::{CanteenDescription, Meal, Additive, Allergenic, Symbol};
+use crate::price::PriceGroup;
+use crate::ratings::{self, Ratings};
 use crate::util::{enclose, AdjustingVec};
 
+// the widgets showing the ratings and the note of a meal
+#[derive(Clone)]
+struct RatingWidgets {
+    button: MenuButton,
+    label: Label,
+    popover: Popover,
+    average_label: Label,
+    star_buttons: Vec<Button>,
+    note_buffer: TextBuffer,
+}
+
+// the widgets of the popover listing the allergenics, additives and symbols of a meal
+#[derive(Clone)]
+struct DetailWidgets {
+    popover: Popover,
+    name_label: Label,
+    allergenics_heading: Label,
+    allergenics_grid: Grid,
+    additives_heading: Label,
+    additives_grid: Grid,
+    symbols_heading: Label,
+    symbols_grid: Grid,
+    empty_label: Label,
+}
+
 pub struct MealComponent {
     name: Label,
     meal: ListBoxRow,
-    price_student: Label,
-    price_staff: Label,
-    price_guest: Label,
+    warning: InfoBar,
+    warning_label: Label,
+    favorite_button: ToggleButton,
+    favorite_image: Image,
+    eaten_button: ToggleButton,
+    rating: RatingWidgets,
+    details: DetailWidgets,
+    // opens the details popover, it has to live as long as the meal row
+    _details_gesture: GestureMultiPress,
+    current_meal: Rc<RefCell<Option<Meal>>>,
+    // the canteen and date the current meal is served on
+    current_day: Rc<RefCell<Option<(CanteenDescription, NaiveDate)>>>,
+    settings: Settings,
+    favorites: Favorites,
+    ledger: Ledger,
+    ratings: Ratings,
+    // the price labels in the order of PriceGroup::All.shown()
+    prices: Vec<(PriceGroup, Label)>,
+    price_separators: Vec<Separator>,
     additives: AdjustingVec<LiteBadgeComponent, Error>,
     allergenics: AdjustingVec<LiteBadgeComponent, Error>,
     symbols: AdjustingVec<SymbolComponent, Error>,
 }
 
-fn translate_additive(additive: &Additive) -> String {
+// defines the list of all variants of a marking together with its translation and its settings name,
+// so every variant which gets translated is also offered in the preferences
+macro_rules! variants {
+    ($all:ident, $translate:ident, $name:ident, $enum:ident { $($variant:ident => $label:expr,)* }) => {
+        pub const $all: &[$enum] = &[$($enum::$variant,)*];
+
+        pub fn $translate(value: &$enum) -> String {
+            log::debug!("translating {} {:?}", stringify!($enum), value);
+
+            match value {
+                $($enum::$variant => $label,)*
+            }
+        }
+
+        // the name used in the settings, which is the serde name of the variant
+        pub const fn $name(value: &$enum) -> &'static str {
+            match value {
+                $($enum::$variant => stringify!($variant),)*
+            }
+        }
+    };
+}
+
+pub fn translate_additive(additive: &Additive) -> String {
     log::debug!("translating additive {:?}", additive);
 
     match additive {
@@ -38,62 +117,56 @@ fn translate_additive(additive: &Additive) -> String {
     }
 }
 
-fn translate_allergenic(allergenic: &Allergenic) -> String {
-    log::debug!("translating allergenic {:?}", allergenic);
-
-    match allergenic {
-        Allergenic::Wheat => t("Wheat"),
-        Allergenic::Rye => t("Rye"),
-        Allergenic::Barley => t("Barley"),
-        Allergenic::Oat => t("Oat"),
-        Allergenic::Spelt => t("Spelt"),
-        Allergenic::Kamut => t("Kamut"),
-        Allergenic::Crustacean => t("Crustacean"),
-        Allergenic::Egg => t("Egg"),
-        Allergenic::Fish => t("Fish"),
-        Allergenic::Peanut => t("Peanut"),
-        Allergenic::Soya => t("Soya"),
-        Allergenic::Lactose => t("Lactose"),
-        Allergenic::Almond => t("Almond"),
-        Allergenic::Hazelnut => t("Hazelnut"),
-        Allergenic::Walnut => t("Walnut"),
-        Allergenic::Cashew => t("Cashew"),
-        Allergenic::PecanNut => t("Pecan Nut"),
-        Allergenic::BrazilNut => t("Brazil Nut"),
-        Allergenic::Pistachio => t("Pistachio"),
-        Allergenic::MacadamiaNut => t("Macadamia Nut"),
-        Allergenic::QueenslandNut => t("Queensland Nut"),
-        Allergenic::Celery => t("Celery"),
-        Allergenic::Mustard => t("Mustard"),
-        Allergenic::Sesame => t("Sesame"),
-        Allergenic::Sulphite => t("Sulphite"),
-        Allergenic::Lupin => t("Lupin"),
-        Allergenic::Mollusc => t("Mollusc"),
-    }
-}
-
-fn translate_symbol(symbol: &Symbol) -> String {
-    log::debug!("translating symbol {:?}", symbol);
-
-    match symbol {
-        Symbol::Pig => t("Pig"),
-        Symbol::Cattle => t("Cattle"),
-        Symbol::Poultry => t("Poultry"),
-        Symbol::Fish => t("Fish"),
-        Symbol::Game => t("Game"),
-        Symbol::Lamb => t("Lamb"),
-        Symbol::Vegan => t("Vegan"),
-        Symbol::Organic => t("Organic"),
-        Symbol::Vegetarian => t("Vegetarian"),
-        Symbol::Alcohol => t("Alcohol"),
-        Symbol::SoupOfTheDay => t("Soup of the Day"),
-        Symbol::MensaVital => t("MensaVital"),
-        Symbol::Garlic => t("Garlic"),
-        Symbol::AnimalWelfare => t("Animal Welfare"),
-    }
-}
-
-fn icon_name_from_symbol(symbol: &Symbol) -> &'static str {
+// all allergenics, which can be avoided by the user, with their translation
+variants! { ALLERGENICS, translate_allergenic, allergenic_name, Allergenic {
+    Wheat => t("Wheat"),
+    Rye => t("Rye"),
+    Barley => t("Barley"),
+    Oat => t("Oat"),
+    Spelt => t("Spelt"),
+    Kamut => t("Kamut"),
+    Crustacean => t("Crustacean"),
+    Egg => t("Egg"),
+    Fish => t("Fish"),
+    Peanut => t("Peanut"),
+    Soya => t("Soya"),
+    Lactose => t("Lactose"),
+    Almond => t("Almond"),
+    Hazelnut => t("Hazelnut"),
+    Walnut => t("Walnut"),
+    Cashew => t("Cashew"),
+    PecanNut => t("Pecan Nut"),
+    BrazilNut => t("Brazil Nut"),
+    Pistachio => t("Pistachio"),
+    MacadamiaNut => t("Macadamia Nut"),
+    QueenslandNut => t("Queensland Nut"),
+    Celery => t("Celery"),
+    Mustard => t("Mustard"),
+    Sesame => t("Sesame"),
+    Sulphite => t("Sulphite"),
+    Lupin => t("Lupin"),
+    Mollusc => t("Mollusc"),
+}}
+
+// all symbols, which a custom diet profile can exclude, with their translation
+variants! { SYMBOLS, translate_symbol, symbol_name, Symbol {
+    Pig => t("Pig"),
+    Cattle => t("Cattle"),
+    Poultry => t("Poultry"),
+    Fish => t("Fish"),
+    Game => t("Game"),
+    Lamb => t("Lamb"),
+    Vegan => t("Vegan"),
+    Organic => t("Organic"),
+    Vegetarian => t("Vegetarian"),
+    Alcohol => t("Alcohol"),
+    SoupOfTheDay => t("Soup of the Day"),
+    MensaVital => t("MensaVital"),
+    Garlic => t("Garlic"),
+    AnimalWelfare => t("Animal Welfare"),
+}}
+
+pub fn icon_name_from_symbol(symbol: &Symbol) -> &'static str {
     log::debug!("getting icon-name for symbol {:?}", symbol);
 
     match symbol {
@@ -114,18 +187,271 @@ fn icon_name_from_symbol(symbol: &Symbol) -> &'static str {
     }
 }
 
+// hides or greys out the meal row if the meal does not pass the user's filters
+fn apply_filter(meal: &Meal, meal_box: &ListBoxRow, warning: &InfoBar, warning_label: &Label, settings: &Settings) {
+    let filter = MealFilter::from_settings(settings);
+    let reasons = filter.check(meal);
+    let context = meal_box.get_style_context();
+
+    if reasons.is_empty() {
+        log::debug!("meal {} passes all filters", meal.name);
+        meal_box.show();
+        warning.hide();
+        context.remove_class("meal-excluded");
+        return;
+    }
+
+    log::debug!("meal {} is excluded by filters: {:?}", meal.name, reasons);
+    match filter.mode() {
+        ExclusionMode::Hide => {
+            meal_box.hide();
+        },
+        ExclusionMode::Dim => {
+            meal_box.show();
+            warning_label.set_text(&reasons.join("\n"));
+            warning.show_all();
+            // css class 'meal-excluded' greys out the meal details
+            context.add_class("meal-excluded");
+        },
+    }
+}
+
+// shows only the price of the user's price group prominently,
+// the other prices are collapsed into its tooltip
+fn apply_price_group(meal: &Meal, prices: &[(PriceGroup, Label)], price_separators: &[Separator], settings: &Settings) {
+    let price_group = PriceGroup::from_settings(Some(settings));
+    let all_prices = PriceGroup::All
+        .prices(&meal.price)
+        .into_iter()
+        .map(|(group, price)| format!("{}: {:.2} €", group.translate(), price))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    for (group, label) in prices {
+        let context = label.get_style_context();
+        if price_group == PriceGroup::All {
+            label.show();
+            label.set_tooltip_text(None);
+            context.add_class("dim-label");
+            context.remove_class("meal-price-group");
+        } else if price_group == *group {
+            label.show();
+            label.set_tooltip_text(Some(&all_prices));
+            // css class 'meal-price-group' emphasizes the relevant price
+            context.remove_class("dim-label");
+            context.add_class("meal-price-group");
+        } else {
+            label.hide();
+        }
+    }
+
+    for separator in price_separators {
+        separator.set_visible(price_group == PriceGroup::All);
+    }
+}
+
+// highlights the meal row if the meal is one of the user's favorites
+fn apply_favorite(meal: &Meal, meal_box: &ListBoxRow, favorite_button: &ToggleButton, favorite_image: &Image, favorites: &Favorites) {
+    let favorite = favorites::is_favorite(favorites, &meal.name);
+    let context = meal_box.get_style_context();
+
+    favorite_button.set_active(favorite);
+    if favorite {
+        favorite_image.set_from_icon_name(Some("starred-symbolic"), IconSize::Button);
+        // css class 'meal-favorite' highlights the meal
+        context.add_class("meal-favorite");
+    } else {
+        favorite_image.set_from_icon_name(Some("non-starred-symbolic"), IconSize::Button);
+        context.remove_class("meal-favorite");
+    }
+}
+
+// checks the eaten button if the meal is in the user's ledger
+fn apply_eaten(
+    meal: &Meal,
+    current_day: Option<&(CanteenDescription, NaiveDate)>,
+    eaten_button: &ToggleButton,
+    ledger: &Ledger,
+) {
+    let eaten = current_day.map_or(false, |(canteen_desc, date)| ledger::has_eaten(ledger, canteen_desc, *date, &meal.name));
+
+    eaten_button.set_active(eaten);
+}
+
+fn note_from_buffer(buffer: &TextBuffer) -> String {
+    buffer
+        .get_text(&buffer.get_start_iter(), &buffer.get_end_iter(), false)
+        .map(|text| text.trim().to_owned())
+        .unwrap_or_default()
+}
+
+// shows the average rating of the dish inline, and the rating of the current day and the note in the popover
+fn apply_rating(
+    meal: &Meal,
+    current_day: Option<&(CanteenDescription, NaiveDate)>,
+    rating: &RatingWidgets,
+    ratings: &Ratings,
+) {
+    let (canteen_desc, date) = match current_day {
+        Some(day) => day,
+        None => return,
+    };
+    let dishes = ratings.get();
+    let stars = dishes.stars(canteen_desc, *date, &meal.name).unwrap_or(0);
+    let note = dishes.note(canteen_desc, &meal.name).unwrap_or_default();
+
+    let context = rating.label.get_style_context();
+    match dishes.average(canteen_desc, &meal.name) {
+        Some((average, count)) => {
+            rating.label.set_text(&format!("★ {:.1}", average));
+            rating.average_label.set_text(
+                &t("Average of {count} ratings: {average}")
+                    .replace("{count}", &count.to_string())
+                    .replace("{average}", &format!("{:.1}", average)),
+            );
+            context.remove_class("dim-label");
+        },
+        None => {
+            rating.label.set_text("☆");
+            rating.average_label.set_text(&t("Not rated yet"));
+            context.add_class("dim-label");
+        },
+    }
+
+    // the note can be read without opening the popover
+    if note.is_empty() {
+        rating.button.set_tooltip_text(Some(&t("Rate this meal")));
+    } else {
+        rating.button.set_tooltip_text(Some(note));
+    }
+
+    for (idx, star_button) in rating.star_buttons.iter().enumerate() {
+        star_button.set_label(if idx < usize::from(stars) { "★" } else { "☆" });
+    }
+
+    // only replace the text if it differs, otherwise the cursor would jump while typing
+    if note_from_buffer(&rating.note_buffer) != note {
+        rating.note_buffer.set_text(note);
+    }
+}
+
+fn create_legend_label(text: &str, dim: bool) -> Label {
+    let label = Label::new(Some(text));
+    label.set_xalign(0.0);
+    label.set_line_wrap(true);
+    if dim {
+        label.get_style_context().add_class("dim-label");
+    }
+    label
+}
+
+// replaces the rows of the grid with one label per text, the codes and categories are dimmed
+fn fill_legend_grid(grid: &Grid, heading: &Label, rows: &[Vec<String>]) {
+    for child in grid.get_children() {
+        child.destroy();
+    }
+
+    for (row_idx, row) in rows.iter().enumerate() {
+        for (column_idx, text) in row.iter().enumerate() {
+            let label = create_legend_label(text, column_idx == 0 || column_idx == 2);
+            grid.attach(&label, column_idx as i32, row_idx as i32, 1, 1);
+        }
+    }
+
+    grid.show_all();
+    heading.set_visible(!rows.is_empty());
+    grid.set_visible(!rows.is_empty());
+}
+
+// lists the allergenics with their EU category and the additives with the codes of the
+// OvGU menus, as the badges and symbols of the meal row are hard to read on touch screens
+fn apply_details(meal: &Meal, details: &DetailWidgets) {
+    details.name_label.set_text(&meal.name);
+
+    let allergenics = meal
+        .allergenics
+        .iter()
+        .map(|allergenic| {
+            vec![
+                legend::allergenic_code(allergenic).to_owned(),
+                translate_allergenic(allergenic),
+                EuAllergen::of(allergenic).translate(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    fill_legend_grid(&details.allergenics_grid, &details.allergenics_heading, &allergenics);
+
+    let additives = meal
+        .additives
+        .iter()
+        .map(|additive| vec![legend::additive_code(additive).to_owned(), legend::describe_additive(additive)])
+        .collect::<Vec<_>>();
+    fill_legend_grid(&details.additives_grid, &details.additives_heading, &additives);
+
+    for child in details.symbols_grid.get_children() {
+        child.destroy();
+    }
+    for (row_idx, symbol) in meal.symbols.iter().enumerate() {
+        let image = Image::new_from_icon_name(Some(icon_name_from_symbol(symbol)), IconSize::LargeToolbar);
+        details.symbols_grid.attach(&image, 0, row_idx as i32, 1, 1);
+        details.symbols_grid.attach(&create_legend_label(&translate_symbol(symbol), false), 1, row_idx as i32, 1, 1);
+    }
+    details.symbols_grid.show_all();
+    details.symbols_heading.set_visible(!meal.symbols.is_empty());
+    details.symbols_grid.set_visible(!meal.symbols.is_empty());
+
+    details
+        .empty_label
+        .set_visible(meal.allergenics.is_empty() && meal.additives.is_empty() && meal.symbols.is_empty());
+}
+
 impl MealComponent {
-    pub async fn new() -> Result<Self> {
+    pub async fn new(settings: &Settings, favorites: &Favorites, ledger: &Ledger, ratings: &Ratings) -> Result<Self> {
         log::debug!("creating new MealComponent");
 
         let builder = Builder::new_from_string(GLADE);
         let meal_box: ListBoxRow = get!(&builder, "meal")?;
         let name: Label = get!(&builder, "meal-name")?;
+        let warning: InfoBar = get!(&builder, "meal-warning")?;
+        let warning_label: Label = get!(&builder, "meal-warning-label")?;
+        let favorite_button: ToggleButton = get!(&builder, "meal-favorite-button")?;
+        let favorite_image: Image = get!(&builder, "meal-favorite-image")?;
+        let eaten_button: ToggleButton = get!(&builder, "meal-eaten-button")?;
+        let note_text_view: TextView = get!(&builder, "meal-note-text-view")?;
+        let rating = RatingWidgets {
+            button: get!(&builder, "meal-rating-button")?,
+            label: get!(&builder, "meal-rating-label")?,
+            popover: get!(&builder, "meal-rating-popover")?,
+            average_label: get!(&builder, "meal-rating-average-label")?,
+            star_buttons: (1..=ratings::MAX_STARS)
+                .map(|stars| get!(&builder, &format!("meal-rating-star-{}", stars)))
+                .collect::<Result<_>>()?,
+            note_buffer: note_text_view
+                .get_buffer()
+                .context("meal-note-text-view has no buffer")?,
+        };
+        let details = DetailWidgets {
+            popover: get!(&builder, "meal-details-popover")?,
+            name_label: get!(&builder, "meal-details-name-label")?,
+            allergenics_heading: get!(&builder, "meal-details-allergenics-heading")?,
+            allergenics_grid: get!(&builder, "meal-details-allergenics-grid")?,
+            additives_heading: get!(&builder, "meal-details-additives-heading")?,
+            additives_grid: get!(&builder, "meal-details-additives-grid")?,
+            symbols_heading: get!(&builder, "meal-details-symbols-heading")?,
+            symbols_grid: get!(&builder, "meal-details-symbols-grid")?,
+            empty_label: get!(&builder, "meal-details-empty-label")?,
+        };
         let badges: FlowBox = get!(&builder, "badges")?;
         let symbols: FlowBox = get!(&builder, "symbols")?;
-        let price_student: Label = get!(&builder, "meal-price-student")?;
-        let price_staff: Label = get!(&builder, "meal-price-staff")?;
-        let price_guest: Label = get!(&builder, "meal-price-guest")?;
+        let prices: Vec<(PriceGroup, Label)> = vec![
+            (PriceGroup::Student, get!(&builder, "meal-price-student")?),
+            (PriceGroup::Staff, get!(&builder, "meal-price-staff")?),
+            (PriceGroup::Guest, get!(&builder, "meal-price-guest")?),
+        ];
+        let price_separators: Vec<Separator> = vec![
+            get!(&builder, "meal-price-staff-separator")?,
+            get!(&builder, "meal-price-guest-separator")?,
+        ];
 
         log::debug!("setting up AdjustingVec's in MealComponent");
 
@@ -214,14 +540,148 @@ impl MealComponent {
             }},
         );
 
+        log::debug!("connecting settings-changed handler in MealComponent");
+
+        // the meal currently shown, needed to re-apply the filters when the settings change
+        let current_meal: Rc<RefCell<Option<Meal>>> = Rc::new(RefCell::new(None));
+        let current_day: Rc<RefCell<Option<(CanteenDescription, NaiveDate)>>> = Rc::new(RefCell::new(None));
+
+        let signal_handler = settings.connect_changed(enclose! {
+            (meal_box, warning, warning_label, prices, price_separators, current_meal) move |settings, key| {
+                if let Some(meal) = current_meal.borrow().as_ref() {
+                    if filter::FILTER_KEYS.contains(&key) {
+                        apply_filter(meal, &meal_box, &warning, &warning_label, settings);
+                    } else if key == "price-group" {
+                        apply_price_group(meal, &prices, &price_separators, settings);
+                    }
+                }
+            }
+        });
+
+        log::debug!("connecting favorites-changed handler in MealComponent");
+
+        let favorites_listener = favorites.connect_changed(enclose! {
+            (meal_box, favorite_button, favorite_image, current_meal, favorites) move |_favorites| {
+                if let Some(meal) = current_meal.borrow().as_ref() {
+                    apply_favorite(meal, &meal_box, &favorite_button, &favorite_image, &favorites);
+                }
+            }
+        });
+
+        favorite_button.connect_toggled(enclose! { (current_meal, favorites) move |favorite_button| {
+            if let Some(meal) = current_meal.borrow().as_ref() {
+                // the button also gets toggled when the favorites changed
+                let favorite = favorite_button.get_active();
+                if favorite == favorites::is_favorite(&favorites, &meal.name) {
+                    return;
+                }
+
+                favorites::set_favorite(&favorites, &meal.name, favorite);
+            }
+        }});
+
+        log::debug!("connecting ledger-changed handler in MealComponent");
+
+        let ledger_listener = ledger.connect_changed(enclose! {
+            (eaten_button, current_meal, current_day, ledger) move |_entries| {
+                if let Some(meal) = current_meal.borrow().as_ref() {
+                    apply_eaten(meal, current_day.borrow().as_ref(), &eaten_button, &ledger);
+                }
+            }
+        });
+
+        eaten_button.connect_toggled(enclose! { (current_meal, current_day, settings, ledger) move |eaten_button| {
+            let current_meal = current_meal.borrow();
+            let current_day = current_day.borrow();
+            let (meal, (canteen_desc, date)) = match (current_meal.as_ref(), current_day.as_ref()) {
+                (Some(meal), Some(day)) => (meal, day),
+                _ => return,
+            };
+
+            // the button also gets toggled when the ledger changed
+            let eaten = eaten_button.get_active();
+            if eaten == ledger::has_eaten(&ledger, canteen_desc, *date, &meal.name) {
+                return;
+            }
+
+            // without a selected price group the student price is recorded
+            let price = PriceGroup::from_settings(Some(&settings)).relevant_price(&meal.price);
+            let entry = Entry {
+                canteen: canteen_desc.clone(),
+                date: *date,
+                meal: meal.name.clone(),
+                price,
+            };
+            ledger::set_eaten(&ledger, entry, eaten);
+        }});
+
+        log::debug!("connecting ratings-changed handler in MealComponent");
+
+        let ratings_listener = ratings.connect_changed(enclose! {
+            (rating, current_meal, current_day, ratings) move |_dishes| {
+                if let Some(meal) = current_meal.borrow().as_ref() {
+                    apply_rating(meal, current_day.borrow().as_ref(), &rating, &ratings);
+                }
+            }
+        });
+
+        for (idx, star_button) in rating.star_buttons.iter().enumerate() {
+            star_button.connect_clicked(enclose! { (current_meal, current_day, ratings) move |_star_button| {
+                if let (Some(meal), Some((canteen_desc, date))) = (current_meal.borrow().as_ref(), current_day.borrow().as_ref()) {
+                    // clicking the current rating again removes it
+                    let stars = idx as u8 + 1;
+                    let current_stars = ratings.get().stars(canteen_desc, *date, &meal.name);
+                    let stars = if current_stars == Some(stars) { None } else { Some(stars) };
+                    ratings::set_stars(&ratings, canteen_desc, *date, &meal.name, stars);
+                }
+            }});
+        }
+
+        // the note is saved when the popover gets closed, not on every keystroke
+        rating.popover.connect_closed(enclose! { (rating, current_meal, current_day, ratings) move |_popover| {
+            if let (Some(meal), Some((canteen_desc, _date))) = (current_meal.borrow().as_ref(), current_day.borrow().as_ref()) {
+                ratings::set_note(&ratings, canteen_desc, &meal.name, &note_from_buffer(&rating.note_buffer));
+            }
+        }});
+
+        // the buttons of the row handle their clicks themselves, any other click on the row opens the details
+        let details_gesture = GestureMultiPress::new(&meal_box);
+        details_gesture.connect_released(enclose! { (details, current_meal) move |_gesture, _n_press, _x, _y| {
+            if current_meal.borrow().is_some() {
+                details.popover.popup();
+            }
+        }});
+
+        // stop listening for settings, favorites, ledger and ratings changes when this meal gets destroyed
+        meal_box.connect_destroy(enclose! { (settings, favorites, ledger, ratings) move |_meal_box| {
+            use glib::translate::{FromGlib, ToGlib}; // clone or copy would be boring...
+            settings.disconnect(SignalHandlerId::from_glib(signal_handler.to_glib()));
+            favorites.disconnect(favorites_listener);
+            ledger.disconnect(ledger_listener);
+            ratings.disconnect(ratings_listener);
+        }});
+
         log::debug!("finish creating MealComponent");
 
         Ok(Self {
             meal: meal_box,
             name,
-            price_student,
-            price_staff,
-            price_guest,
+            warning,
+            warning_label,
+            favorite_button,
+            favorite_image,
+            eaten_button,
+            rating,
+            details,
+            _details_gesture: details_gesture,
+            current_meal,
+            current_day,
+            settings: settings.clone(),
+            favorites: favorites.clone(),
+            ledger: ledger.clone(),
+            ratings: ratings.clone(),
+            prices,
+            price_separators,
             additives,
             allergenics,
             symbols,
@@ -232,22 +692,31 @@ impl MealComponent {
         &self.meal
     }
 
-    pub async fn load(&mut self, meal: &Meal) -> Result<()> {
+    pub async fn load(&mut self, canteen_desc: &CanteenDescription, date: NaiveDate, meal: &Meal) -> Result<()> {
+        // the listeners keep an unchanged meal up to date, so it is not rebuilt on every refresh
+        let same_day = self
+            .current_day
+            .borrow()
+            .as_ref()
+            .map_or(false, |(current_desc, current_date)| current_desc == canteen_desc && *current_date == date);
+        let same_meal = self.current_meal.borrow().as_ref().map_or(false, |current| current == meal);
+        if same_day && same_meal {
+            log::debug!("MealComponent {} is unchanged", meal.name);
+            return Ok(());
+        }
+
         log::debug!("loading content into MealComponent {}", meal.name);
 
         self.name.set_text(&meal.name);
-        self.price_student
-            .set_text(format!("{:.2} €", meal.price.student).as_str());
-        self.price_staff
-            .set_text(format!("{:.2} €", meal.price.staff).as_str());
-        self.price_guest
-            .set_text(format!("{:.2} €", meal.price.guest).as_str());
+        for ((_group, label), (_group_of_price, price)) in self.prices.iter().zip(PriceGroup::All.prices(&meal.price)) {
+            label.set_text(format!("{:.2} €", price).as_str());
+        }
 
         log::debug!("loading additives into MealComponent {}", meal.name);
         self.additives
             .adjust(&meal.additives, |badge, additive| async move {
                 // define how to update an additive
-                badge.load(&translate_additive(&additive)).await;
+                badge.load(&translate_additive(additive)).await;
                 glib_yield!(); // give gtk a chance to update the UI
                 Ok(badge)
             })
@@ -257,7 +726,7 @@ impl MealComponent {
         self.allergenics
             .adjust(&meal.allergenics, |badge, allergenic| async move {
                 // define how to update an allergenic
-                badge.load(&translate_allergenic(&allergenic)).await;
+                badge.load(&translate_allergenic(allergenic)).await;
                 glib_yield!(); // give gtk a chance to update the UI
                 Ok(badge)
             })
@@ -267,12 +736,34 @@ impl MealComponent {
         self.symbols
             .adjust(&meal.symbols, |badge, symbol| async move {
                 // define how to update a symbol
-                badge.load(icon_name_from_symbol(&symbol), &translate_symbol(&symbol)).await;
+                badge.load(icon_name_from_symbol(symbol), &translate_symbol(symbol)).await;
                 glib_yield!(); // give gtk a chance to update the UI
                 Ok(badge)
             })
             .await?;
 
+        // the current meal has to be replaced first, as the favorite and eaten buttons read it when toggled
+        self.current_day.replace(Some((canteen_desc.clone(), date)));
+        self.current_meal.replace(Some(meal.clone()));
+
+        log::debug!("applying filters to MealComponent {}", meal.name);
+        apply_filter(meal, &self.meal, &self.warning, &self.warning_label, &self.settings);
+
+        log::debug!("applying price group to MealComponent {}", meal.name);
+        apply_price_group(meal, &self.prices, &self.price_separators, &self.settings);
+
+        log::debug!("applying favorite state to MealComponent {}", meal.name);
+        apply_favorite(meal, &self.meal, &self.favorite_button, &self.favorite_image, &self.favorites);
+
+        log::debug!("applying eaten state to MealComponent {}", meal.name);
+        apply_eaten(meal, self.current_day.borrow().as_ref(), &self.eaten_button, &self.ledger);
+
+        log::debug!("applying ratings to MealComponent {}", meal.name);
+        apply_rating(meal, self.current_day.borrow().as_ref(), &self.rating, &self.ratings);
+
+        log::debug!("applying details to MealComponent {}", meal.name);
+        apply_details(meal, &self.details);
+
         log::debug!("finish loading MealComponent {}", meal.name);
 
         Ok(())
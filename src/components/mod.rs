@@ -1,14 +1,29 @@
 mod badge;
 pub mod canteen;
-mod day;
-mod meal;
+mod compare;
+mod dashboard;
+pub mod day;
+pub mod export_dialog;
+mod history;
+mod ledger;
+pub mod meal;
+mod upcoming_favorites;
 mod window;
 pub mod preferences;
+mod search;
+mod statistics;
 
 pub use badge::{BadgeComponent, LiteBadgeComponent, SymbolComponent};
 pub use canteen::CanteenComponent;
+pub use compare::CompareComponent;
+pub use dashboard::DashboardComponent;
 pub use day::DayComponent;
+pub use history::HistoryComponent;
+pub use ledger::LedgerComponent;
 pub use meal::MealComponent;
+pub use search::SearchComponent;
+pub use statistics::StatisticsComponent;
+pub use upcoming_favorites::UpcomingFavoritesComponent;
 pub use window::WindowComponent;
 
 // the content of the glade file
@@ -1,13 +1,18 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::Arc;
-use std::fs::File;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use glib::SignalHandlerId;
 use gio::prelude::*;
 use gtk::prelude::*;
-use gtk::{Builder, Button, Label, Window, Switch, ComboBox, SpinButton, ListStore};
+use gtk::{
+    Builder, Button, Label, Window, Switch, ComboBox, ComboBoxText, SpinButton, ListStore,
+    CheckButton, FlowBox, TextBuffer, TextView, ListBox, ListBoxRow, Orientation, IconSize,
+    ButtonsType, DialogFlags, FileChooserAction, FileChooserNative, MessageDialog, MessageType, ResponseType,
+};
 use humansize::{FileSize, file_size_opts};
-use ovgu_canteen::CanteenDescription;
 use gettextrs::gettext as t;
 use tokio::runtime::Handle;
 use tokio::sync::Notify;
@@ -15,35 +20,295 @@ use tokio::sync::mpsc::channel;
 use notify::{RecursiveMode, watcher, Watcher};
 use futures::future::{self, Either, FutureExt};
 
-use crate::components::{get, WindowComponent, GLADE};
+use crate::cache;
+use crate::calendar;
+use crate::components::{get, meal, WindowComponent, GLADE};
+use crate::filter;
+use crate::model::CanteenDescription;
+use crate::price;
+use crate::ratings::{self, Ratings};
+use crate::sorting;
 use crate::util::enclose;
-use crate::canteen;
 
 fn update_cache_size_label(cache_size_label: &Label) {
     log::debug!("updating cache-size label in preferences");
 
-    let file = xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk").ok()
-        .and_then(|xdg| xdg.find_cache_file("history.json"))
-        .map(|path| {
-            // this cannot fail, as xdg.find_cache_file makes sure the file exists
-            File::open(path).unwrap()
-        });
-    let size = match file {
-        Some(file) => file.metadata().ok().map(|meta| meta.len()),
-        None => Some(0),
-    };
+    let size = cache::size()
+        .map_err(|e| log::warn!("failed to determine cache size: {:#}", e))
+        .ok();
     let humansize = size
         .and_then(|size| {
             size.file_size(file_size_opts::BINARY).ok()
         })
-        .unwrap_or(t("Unknown"));
+        .unwrap_or_else(|| t("Unknown"));
 
     log::debug!("new cache-size shown in preferences is {}", humansize);
 
     cache_size_label.set_text(&humansize);
 }
 
-pub fn open<'a, I: IntoIterator<Item = &'a CanteenDescription>>(rt: &Handle, window: &WindowComponent, canteens: I) -> Result<()> {
+// creates a checklist in the flow box, one check button for each (value, label) pair
+fn create_check_buttons<I: IntoIterator<Item = (String, String)>>(flow_box: &FlowBox, items: I) -> Vec<(String, CheckButton)> {
+    items
+        .into_iter()
+        .map(|(value, label)| {
+            let check_button = CheckButton::new_with_label(&label);
+            check_button.set_visible(true);
+            flow_box.insert(&check_button, -1);
+            (value, check_button)
+        })
+        .collect()
+}
+
+// activates all check buttons whose value is contained in the string array at the settings key
+fn update_check_buttons(check_buttons: &[(String, CheckButton)], settings: &gio::Settings, key: &str) {
+    log::debug!("updating check buttons for {} in preferences", key);
+
+    let values = settings.get_strv(key);
+    for (value, check_button) in check_buttons {
+        check_button.set_active(values.iter().any(|v| v.as_str() == value));
+    }
+}
+
+// adds or removes the value of a check button to the string array at the settings key when toggled
+fn connect_check_buttons(check_buttons: &[(String, CheckButton)], settings: &gio::Settings, key: &'static str) {
+    for (value, check_button) in check_buttons {
+        check_button.connect_toggled(enclose! { (settings, value) move |check_button| {
+            let mut values = settings
+                .get_strv(key)
+                .iter()
+                .map(ToString::to_string)
+                .filter(|v| v != &value)
+                .collect::<Vec<_>>();
+            if check_button.get_active() {
+                values.push(value.clone());
+            }
+
+            settings.set_strv(key, &values.iter().map(String::as_str).collect::<Vec<_>>()).unwrap();
+        }});
+    }
+}
+
+// a row in the list of canteens, which can be toggled and moved
+#[derive(Clone)]
+struct CanteenRow {
+    canteen: String,
+    row: ListBoxRow,
+    check_button: CheckButton,
+    up_button: Button,
+    down_button: Button,
+}
+
+type CanteenRows = Rc<RefCell<Vec<CanteenRow>>>;
+
+fn create_canteen_rows(list_box: &ListBox, canteens: &[(CanteenDescription, String)]) -> CanteenRows {
+    let rows = canteens
+        .iter()
+        .map(|(canteen, title)| {
+            let check_button = CheckButton::new_with_label(title);
+            let up_button = Button::new_from_icon_name(Some("go-up-symbolic"), IconSize::Button);
+            up_button.set_tooltip_text(Some(&t("Move up")));
+            up_button.set_relief(gtk::ReliefStyle::None);
+            let down_button = Button::new_from_icon_name(Some("go-down-symbolic"), IconSize::Button);
+            down_button.set_tooltip_text(Some(&t("Move down")));
+            down_button.set_relief(gtk::ReliefStyle::None);
+
+            let row_box = gtk::Box::new(Orientation::Horizontal, 4);
+            row_box.set_margin_start(8);
+            row_box.set_margin_end(8);
+            row_box.pack_start(&check_button, true, true, 0);
+            row_box.pack_start(&up_button, false, true, 0);
+            row_box.pack_start(&down_button, false, true, 0);
+
+            let row = ListBoxRow::new();
+            row.set_activatable(false);
+            row.add(&row_box);
+            row.show_all();
+            list_box.insert(&row, -1);
+
+            CanteenRow {
+                canteen: canteen.id().to_owned(),
+                row,
+                check_button,
+                up_button,
+                down_button,
+            }
+        })
+        .collect();
+
+    Rc::new(RefCell::new(rows))
+}
+
+// shows the rows in the order of the vector
+fn layout_canteen_rows(list_box: &ListBox, rows: &[CanteenRow]) {
+    for (idx, canteen_row) in rows.iter().enumerate() {
+        // a list box cannot reorder its rows, so they are inserted again,
+        // the rows stay alive as they are referenced by the vector
+        list_box.remove(&canteen_row.row);
+        list_box.insert(&canteen_row.row, idx as i32);
+        canteen_row.up_button.set_sensitive(idx > 0);
+        canteen_row.down_button.set_sensitive(idx + 1 < rows.len());
+    }
+}
+
+// sorts the rows like the canteens settings key, hidden canteens are moved to the end
+fn update_canteen_rows(list_box: &ListBox, rows: &CanteenRows, settings: &gio::Settings) {
+    log::debug!("updating canteen rows in preferences");
+
+    let enabled = settings.get_strv("canteens");
+    let position = |canteen_row: &CanteenRow| {
+        enabled
+            .iter()
+            .position(|canteen| canteen.as_str() == canteen_row.canteen)
+    };
+
+    let mut rows = rows.borrow_mut();
+    rows.sort_by_key(|canteen_row| position(canteen_row).unwrap_or(enabled.len()));
+    for canteen_row in rows.iter() {
+        canteen_row.check_button.set_active(position(canteen_row).is_some());
+    }
+
+    layout_canteen_rows(list_box, &rows);
+}
+
+// writes the checked canteens in the order of the rows into the canteens settings key
+fn save_canteen_rows(rows: &[CanteenRow], settings: &gio::Settings) {
+    let enabled = rows
+        .iter()
+        .filter(|canteen_row| canteen_row.check_button.get_active())
+        .map(|canteen_row| canteen_row.canteen.as_str())
+        .collect::<Vec<_>>();
+
+    settings.set_strv("canteens", &enabled).unwrap();
+}
+
+fn connect_canteen_rows(list_box: &ListBox, rows: &CanteenRows, settings: &gio::Settings) {
+    for canteen_row in rows.borrow().iter() {
+        let canteen = canteen_row.canteen.clone();
+        // moves the row by the offset, e.g. -1 moves it one row up
+        let move_row = enclose! { (list_box, rows, settings, canteen) move |offset: isize| {
+            let mut rows_borrow = rows.borrow_mut();
+            let idx = match rows_borrow.iter().position(|canteen_row| canteen_row.canteen == canteen) {
+                Some(idx) => idx,
+                None => return,
+            };
+            let new_idx = idx as isize + offset;
+            if new_idx < 0 || new_idx as usize >= rows_borrow.len() {
+                return;
+            }
+
+            log::debug!("moving canteen {} to position {} in preferences", canteen, new_idx);
+            rows_borrow.swap(idx, new_idx as usize);
+            layout_canteen_rows(&list_box, &rows_borrow);
+
+            let rows_copy = rows_borrow.clone();
+            // the settings-changed handler borrows the rows again
+            drop(rows_borrow);
+            save_canteen_rows(&rows_copy, &settings);
+        }};
+
+        canteen_row.up_button.connect_clicked(enclose! { (move_row) move |_btn| move_row(-1) });
+        canteen_row.down_button.connect_clicked(move |_btn| move_row(1));
+
+        canteen_row.check_button.connect_toggled(enclose! { (rows, settings) move |_check_button| {
+            // the rows are currently updated from the settings, nothing to save
+            let rows_copy = match rows.try_borrow() {
+                Ok(rows) => rows.clone(),
+                Err(_) => return,
+            };
+            // the settings-changed handler borrows the rows again
+            save_canteen_rows(&rows_copy, &settings);
+        }});
+    }
+}
+
+// each non-empty line of the buffer is a watched meal pattern
+fn watched_meals_from_buffer(buffer: &TextBuffer) -> Vec<String> {
+    buffer
+        .get_text(&buffer.get_start_iter(), &buffer.get_end_iter(), false)
+        .map(|text| {
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn update_watched_meals_buffer(buffer: &TextBuffer, settings: &gio::Settings) {
+    let watched_meals = settings
+        .get_strv("watched-meals")
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+    // only replace the text if it differs, otherwise the cursor would jump while typing
+    if watched_meals_from_buffer(buffer) != watched_meals {
+        log::debug!("updating watched meals in preferences");
+        buffer.set_text(&watched_meals.join("\n"));
+    }
+}
+
+// the canteens are given with their title as shown in the canteen menu
+fn show_message(parent: &Window, message_type: MessageType, text: &str) {
+    let message = MessageDialog::new(Some(parent), DialogFlags::MODAL, message_type, ButtonsType::Close, text);
+    message.run();
+    message.destroy();
+}
+
+// returns None if the user cancelled the file chooser
+fn choose_ratings_file(parent: &Window, action: FileChooserAction) -> Option<PathBuf> {
+    let (title, accept) = match action {
+        FileChooserAction::Save => (t("Export Ratings"), t("_Export")),
+        _ => (t("Import Ratings"), t("_Import")),
+    };
+    let file_chooser = FileChooserNative::new(Some(&title), Some(parent), action, Some(&accept), Some(&t("_Cancel")));
+    if action == FileChooserAction::Save {
+        file_chooser.set_do_overwrite_confirmation(true);
+        file_chooser.set_current_name(&format!("{}.json", t("ratings")));
+    }
+
+    if file_chooser.run() == ResponseType::Accept {
+        file_chooser.get_filename()
+    } else {
+        log::debug!("choosing a ratings file got cancelled");
+        None
+    }
+}
+
+fn import_ratings(parent: &Window, ratings: &Ratings) {
+    let path = match choose_ratings_file(parent, FileChooserAction::Open) {
+        Some(path) => path,
+        None => return,
+    };
+
+    match ratings::import(ratings, &path) {
+        Ok(added) => show_message(
+            parent,
+            MessageType::Info,
+            &t("Imported {count} new ratings and notes").replace("{count}", &added.to_string()),
+        ),
+        Err(e) => {
+            log::error!("{:#}", e);
+            show_message(parent, MessageType::Error, &format!("{}: {:#}", t("Failed to import ratings"), e));
+        },
+    }
+}
+
+fn export_ratings(parent: &Window, ratings: &Ratings) {
+    let path = match choose_ratings_file(parent, FileChooserAction::Save) {
+        Some(path) => path,
+        None => return,
+    };
+
+    if let Err(e) = ratings::export(ratings, &path) {
+        log::error!("{:#}", e);
+        show_message(parent, MessageType::Error, &format!("{}: {:#}", t("Failed to export ratings"), e));
+    }
+}
+
+pub fn open(rt: &Handle, window: &WindowComponent, canteens: &[(CanteenDescription, String)]) -> Result<()> {
     log::debug!("opening up preferences");
 
     let builder = Builder::new_from_string(GLADE);
@@ -52,18 +317,73 @@ pub fn open<'a, I: IntoIterator<Item = &'a CanteenDescription>>(rt: &Handle, win
     let default_canteen_combo_box: ComboBox = get!(&builder, "default-canteen-combo-box")?;
     let canteen_list_store: ListStore = get!(&builder, "canteen-liststore")?;
     let menu_history_length_spin_button: SpinButton = get!(&builder, "menu-history-length-spin-button")?;
+    let refresh_interval_spin_button: SpinButton = get!(&builder, "refresh-interval-spin-button")?;
+    let archive_switch: Switch = get!(&builder, "archive-switch")?;
+    let price_group_combo_box: ComboBoxText = get!(&builder, "price-group-combo-box")?;
+    let monthly_budget_spin_button: SpinButton = get!(&builder, "monthly-budget-spin-button")?;
+    let ratings_import_button: Button = get!(&builder, "ratings-import-button")?;
+    let ratings_export_button: Button = get!(&builder, "ratings-export-button")?;
+    let meal_sort_mode_combo_box: ComboBoxText = get!(&builder, "meal-sort-mode-combo-box")?;
+    let group_meals_by_protein_switch: Switch = get!(&builder, "group-meals-by-protein-switch")?;
     let clear_cache_button: Button = get!(&builder, "clear-cache-button")?;
     let cache_size_label: Label = get!(&builder, "cache-size-label")?;
+    let excluded_meals_mode_combo_box: ComboBoxText = get!(&builder, "excluded-meals-mode-combo-box")?;
+    let avoided_allergenics_flow_box: FlowBox = get!(&builder, "avoided-allergenics-flow-box")?;
+    let diet_profile_combo_box: ComboBoxText = get!(&builder, "diet-profile-combo-box")?;
+    let custom_diet_symbols_flow_box: FlowBox = get!(&builder, "custom-diet-symbols-flow-box")?;
+    let notify_favorites_switch: Switch = get!(&builder, "notify-favorites-switch")?;
+    let watched_meals_text_view: TextView = get!(&builder, "watched-meals-text-view")?;
+    let watched_meals_buffer = watched_meals_text_view
+        .get_buffer()
+        .context("watched-meals-text-view has no buffer")?;
+    let calendar_feed_switch: Switch = get!(&builder, "calendar-feed-switch")?;
+    let calendar_canteen_combo_box: ComboBox = get!(&builder, "calendar-canteen-combo-box")?;
+    let calendar_favorites_only_switch: Switch = get!(&builder, "calendar-favorites-only-switch")?;
+    let calendar_apply_filters_switch: Switch = get!(&builder, "calendar-apply-filters-switch")?;
+    let calendar_feed_path_label: Label = get!(&builder, "calendar-feed-path-label")?;
+    let canteens_list_box: ListBox = get!(&builder, "canteens-list-box")?;
 
     log::debug!("inserting available canteens into preferences combo-box");
-    for (idx, canteen) in canteens.into_iter().enumerate() {
+    for (idx, (canteen, title)) in canteens.iter().enumerate() {
         canteen_list_store.insert_with_values(
             Some(idx as u32),
             &[0, 1],
-            &[&canteen::translate(&canteen), &serde_plain::to_string(&canteen).unwrap()],
+            &[title, &canteen.id()],
         );
     }
 
+    log::debug!("inserting available canteens into preferences list");
+    let canteen_rows = create_canteen_rows(&canteens_list_box, canteens);
+
+    log::debug!("inserting available allergenics into preferences checklist");
+    let allergenic_check_buttons = create_check_buttons(
+        &avoided_allergenics_flow_box,
+        meal::ALLERGENICS.iter().map(|allergenic| {
+            (meal::allergenic_name(allergenic).to_owned(), meal::translate_allergenic(allergenic))
+        }),
+    );
+
+    log::debug!("inserting available price groups into preferences combo-box");
+    for &(name, group) in price::PRICE_GROUPS.iter() {
+        price_group_combo_box.append(Some(name), &group.translate());
+    }
+
+    log::debug!("inserting available sort modes into preferences combo-box");
+    for &(name, mode) in sorting::SORT_MODES.iter() {
+        meal_sort_mode_combo_box.append(Some(name), &mode.translate());
+    }
+
+    log::debug!("inserting available diet profiles into preferences combo-box");
+    for &(name, profile) in filter::DIET_PROFILES.iter() {
+        diet_profile_combo_box.append(Some(name), &profile.translate());
+    }
+
+    log::debug!("inserting available symbols into preferences checklist");
+    let symbol_check_buttons = create_check_buttons(
+        &custom_diet_symbols_flow_box,
+        meal::SYMBOLS.iter().map(|symbol| (meal::symbol_name(symbol).to_owned(), meal::translate_symbol(symbol))),
+    );
+
     // watch for changes on canteen cache and notify UI thread over tx/rx channel of the changes
     let (mut tx, mut rx) = channel(32); // 32 filesystem change events can be buffered
     // notification that preferences window has been closed
@@ -72,7 +392,7 @@ pub fn open<'a, I: IntoIterator<Item = &'a CanteenDescription>>(rt: &Handle, win
         let (std_tx, std_rx) = std::sync::mpsc::channel();
         // install filesystem watcher
         let mut watcher = watcher(std_tx, std::time::Duration::from_millis(100)).unwrap();
-        for xdg in xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk") {
+        if let Ok(xdg) = xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk") {
             watcher.watch(xdg.get_cache_home(), RecursiveMode::NonRecursive).ok();
 
             loop {
@@ -98,7 +418,7 @@ pub fn open<'a, I: IntoIterator<Item = &'a CanteenDescription>>(rt: &Handle, win
                     },
                 }
 
-                if let Some(_) = quit_send.notified().now_or_never() {
+                if quit_send.notified().now_or_never().is_some() {
                     // quit if notified
                     break;
                 }
@@ -141,6 +461,41 @@ pub fn open<'a, I: IntoIterator<Item = &'a CanteenDescription>>(rt: &Handle, win
         default_canteen_combo_box.set_active_id(Some(&canteen));
     }
     menu_history_length_spin_button.set_value(settings.get_uint64("menu-history-length") as f64);
+    refresh_interval_spin_button.set_value(f64::from(settings.get_uint("refresh-interval")));
+    archive_switch.set_state(settings.get_boolean("archive"));
+    if let Some(group) = settings.get_string("price-group") {
+        price_group_combo_box.set_active_id(Some(&group));
+    }
+    monthly_budget_spin_button.set_value(settings.get_double("monthly-budget"));
+    if let Some(mode) = settings.get_string("meal-sort-mode") {
+        meal_sort_mode_combo_box.set_active_id(Some(&mode));
+    }
+    group_meals_by_protein_switch.set_state(settings.get_boolean("group-meals-by-protein"));
+    if let Some(mode) = settings.get_string("excluded-meals-mode") {
+        excluded_meals_mode_combo_box.set_active_id(Some(&mode));
+    }
+    if let Some(profile) = settings.get_string("diet-profile") {
+        diet_profile_combo_box.set_active_id(Some(&profile));
+        custom_diet_symbols_flow_box.set_sensitive(profile.as_str() == "custom");
+    }
+    update_check_buttons(&allergenic_check_buttons, settings, "avoided-allergenics");
+    update_check_buttons(&symbol_check_buttons, settings, "custom-diet-excluded-symbols");
+    notify_favorites_switch.set_state(settings.get_boolean("notify-favorites"));
+    update_watched_meals_buffer(&watched_meals_buffer, settings);
+    calendar_feed_switch.set_state(settings.get_boolean("calendar-feed"));
+    if let Some(canteen) = settings.get_string("calendar-canteen") {
+        calendar_canteen_combo_box.set_active_id(Some(&canteen));
+    }
+    calendar_favorites_only_switch.set_state(settings.get_boolean("calendar-favorites-only"));
+    calendar_apply_filters_switch.set_state(settings.get_boolean("calendar-apply-filters"));
+    update_canteen_rows(&canteens_list_box, &canteen_rows, settings);
+    match calendar::feed_path() {
+        Ok(path) => calendar_feed_path_label.set_text(&path.to_string_lossy()),
+        Err(e) => {
+            log::warn!("{:#}", e);
+            calendar_feed_path_label.set_text(&t("Unknown"));
+        },
+    }
 
     log::debug!("connecting settings-changed handlers");
     let signal_handler = settings.connect_changed(enclose! {
@@ -148,6 +503,25 @@ pub fn open<'a, I: IntoIterator<Item = &'a CanteenDescription>>(rt: &Handle, win
             dark_theme_switch,
             default_canteen_combo_box,
             menu_history_length_spin_button,
+            refresh_interval_spin_button,
+            archive_switch,
+            price_group_combo_box,
+            monthly_budget_spin_button,
+            meal_sort_mode_combo_box,
+            group_meals_by_protein_switch,
+            excluded_meals_mode_combo_box,
+            allergenic_check_buttons,
+            diet_profile_combo_box,
+            custom_diet_symbols_flow_box,
+            symbol_check_buttons,
+            notify_favorites_switch,
+            watched_meals_buffer,
+            calendar_feed_switch,
+            calendar_canteen_combo_box,
+            calendar_favorites_only_switch,
+            calendar_apply_filters_switch,
+            canteens_list_box,
+            canteen_rows,
         ) move |settings, key| {
             match key {
                 "dark-theme-variant" => {
@@ -164,6 +538,85 @@ pub fn open<'a, I: IntoIterator<Item = &'a CanteenDescription>>(rt: &Handle, win
                     log::debug!("menu-history-length changed to {}", settings.get_uint64(key));
                     menu_history_length_spin_button.set_value(settings.get_uint64(key) as f64);
                 },
+                "refresh-interval" => {
+                    log::debug!("refresh-interval changed to {}", settings.get_uint(key));
+                    refresh_interval_spin_button.set_value(f64::from(settings.get_uint(key)));
+                },
+                "archive" => {
+                    log::debug!("archive changed to {}", settings.get_boolean(key));
+                    archive_switch.set_state(settings.get_boolean(key));
+                },
+                "price-group" => {
+                    if let Some(group) = settings.get_string(key) {
+                        log::debug!("price-group changed to {}", group);
+                        price_group_combo_box.set_active_id(Some(&group));
+                    }
+                },
+                "monthly-budget" => {
+                    log::debug!("monthly-budget changed to {}", settings.get_double(key));
+                    monthly_budget_spin_button.set_value(settings.get_double(key));
+                },
+                "meal-sort-mode" => {
+                    if let Some(mode) = settings.get_string(key) {
+                        log::debug!("meal-sort-mode changed to {}", mode);
+                        meal_sort_mode_combo_box.set_active_id(Some(&mode));
+                    }
+                },
+                "group-meals-by-protein" => {
+                    log::debug!("group-meals-by-protein changed to {}", settings.get_boolean(key));
+                    group_meals_by_protein_switch.set_state(settings.get_boolean(key));
+                },
+                "excluded-meals-mode" => {
+                    if let Some(mode) = settings.get_string(key) {
+                        log::debug!("excluded-meals-mode changed to {}", mode);
+                        excluded_meals_mode_combo_box.set_active_id(Some(&mode));
+                    }
+                },
+                "avoided-allergenics" => {
+                    log::debug!("avoided-allergenics changed to {:?}", settings.get_strv(key));
+                    update_check_buttons(&allergenic_check_buttons, settings, key);
+                },
+                "diet-profile" => {
+                    if let Some(profile) = settings.get_string(key) {
+                        log::debug!("diet-profile changed to {}", profile);
+                        diet_profile_combo_box.set_active_id(Some(&profile));
+                        custom_diet_symbols_flow_box.set_sensitive(profile.as_str() == "custom");
+                    }
+                },
+                "custom-diet-excluded-symbols" => {
+                    log::debug!("custom-diet-excluded-symbols changed to {:?}", settings.get_strv(key));
+                    update_check_buttons(&symbol_check_buttons, settings, key);
+                },
+                "notify-favorites" => {
+                    log::debug!("notify-favorites changed to {}", settings.get_boolean(key));
+                    notify_favorites_switch.set_state(settings.get_boolean(key));
+                },
+                "watched-meals" => {
+                    log::debug!("watched-meals changed to {:?}", settings.get_strv(key));
+                    update_watched_meals_buffer(&watched_meals_buffer, settings);
+                },
+                "calendar-feed" => {
+                    log::debug!("calendar-feed changed to {}", settings.get_boolean(key));
+                    calendar_feed_switch.set_state(settings.get_boolean(key));
+                },
+                "calendar-canteen" => {
+                    if let Some(canteen) = settings.get_string(key) {
+                        log::debug!("calendar-canteen changed to {}", canteen);
+                        calendar_canteen_combo_box.set_active_id(Some(&canteen));
+                    }
+                },
+                "calendar-favorites-only" => {
+                    log::debug!("calendar-favorites-only changed to {}", settings.get_boolean(key));
+                    calendar_favorites_only_switch.set_state(settings.get_boolean(key));
+                },
+                "calendar-apply-filters" => {
+                    log::debug!("calendar-apply-filters changed to {}", settings.get_boolean(key));
+                    calendar_apply_filters_switch.set_state(settings.get_boolean(key));
+                },
+                "canteens" => {
+                    log::debug!("canteens changed to {:?}", settings.get_strv(key));
+                    update_canteen_rows(&canteens_list_box, &canteen_rows, settings);
+                },
                 _ => {},
             }
         }
@@ -173,11 +626,12 @@ pub fn open<'a, I: IntoIterator<Item = &'a CanteenDescription>>(rt: &Handle, win
 
     // when preferences get closed, cleanup
     preferences.connect_destroy(enclose! { (settings) move |_window| {
+        use glib::translate::{FromGlib, ToGlib}; // clone or copy would be boring...
+
         // quit the file watcher and label updater futures
         quit_recv.notify();
         quit_send.notify();
 
-        use glib::translate::{FromGlib, ToGlib}; // clone or copy would be boring...
         settings.disconnect(SignalHandlerId::from_glib(signal_handler.to_glib()));
     }});
 
@@ -196,33 +650,110 @@ pub fn open<'a, I: IntoIterator<Item = &'a CanteenDescription>>(rt: &Handle, win
         settings.set_uint64("menu-history-length", spin_button.get_value() as u64).unwrap();
     }});
 
+    refresh_interval_spin_button.connect_changed(enclose! { (settings) move |spin_button| {
+        settings.set_uint("refresh-interval", spin_button.get_value() as u32).unwrap();
+    }});
+
+    archive_switch.connect_state_set(enclose! { (settings) move |_switch, state| {
+        settings.set_boolean("archive", state).unwrap();
+        Inhibit(false)
+    }});
+
+    price_group_combo_box.connect_changed(enclose! { (settings) move |combo_box| {
+        if let Some(group) = combo_box.get_active_id() {
+            settings.set_string("price-group", &group).unwrap();
+        }
+    }});
+
+    monthly_budget_spin_button.connect_changed(enclose! { (settings) move |spin_button| {
+        settings.set_double("monthly-budget", spin_button.get_value()).unwrap();
+    }});
+
+    meal_sort_mode_combo_box.connect_changed(enclose! { (settings) move |combo_box| {
+        if let Some(mode) = combo_box.get_active_id() {
+            settings.set_string("meal-sort-mode", &mode).unwrap();
+        }
+    }});
+
+    group_meals_by_protein_switch.connect_state_set(enclose! { (settings) move |_switch, state| {
+        settings.set_boolean("group-meals-by-protein", state).unwrap();
+        Inhibit(false)
+    }});
+
+    let ratings = window.ratings().clone();
+
+    ratings_import_button.connect_clicked(enclose! { (preferences, ratings) move |_btn| {
+        import_ratings(&preferences, &ratings);
+    }});
+
+    ratings_export_button.connect_clicked(enclose! { (preferences, ratings) move |_btn| {
+        export_ratings(&preferences, &ratings);
+    }});
+
+    excluded_meals_mode_combo_box.connect_changed(enclose! { (settings) move |combo_box| {
+        if let Some(mode) = combo_box.get_active_id() {
+            settings.set_string("excluded-meals-mode", &mode).unwrap();
+        }
+    }});
+
+    connect_check_buttons(&allergenic_check_buttons, settings, "avoided-allergenics");
+
+    diet_profile_combo_box.connect_changed(enclose! { (settings) move |combo_box| {
+        if let Some(profile) = combo_box.get_active_id() {
+            settings.set_string("diet-profile", &profile).unwrap();
+        }
+    }});
+
+    connect_check_buttons(&symbol_check_buttons, settings, "custom-diet-excluded-symbols");
+
+    notify_favorites_switch.connect_state_set(enclose! { (settings) move |_switch, state| {
+        settings.set_boolean("notify-favorites", state).unwrap();
+        Inhibit(false)
+    }});
+
+    watched_meals_buffer.connect_changed(enclose! { (settings) move |buffer| {
+        let watched_meals = watched_meals_from_buffer(buffer);
+        settings.set_strv(
+            "watched-meals",
+            &watched_meals.iter().map(String::as_str).collect::<Vec<_>>(),
+        ).unwrap();
+    }});
+
+    calendar_feed_switch.connect_state_set(enclose! { (settings) move |_switch, state| {
+        settings.set_boolean("calendar-feed", state).unwrap();
+        Inhibit(false)
+    }});
+
+    calendar_canteen_combo_box.connect_changed(enclose! { (settings) move |combo_box| {
+        if let Some(canteen) = combo_box.get_active_id() {
+            settings.set_string("calendar-canteen", &canteen).unwrap();
+        }
+    }});
+
+    calendar_favorites_only_switch.connect_state_set(enclose! { (settings) move |_switch, state| {
+        settings.set_boolean("calendar-favorites-only", state).unwrap();
+        Inhibit(false)
+    }});
+
+    calendar_apply_filters_switch.connect_state_set(enclose! { (settings) move |_switch, state| {
+        settings.set_boolean("calendar-apply-filters", state).unwrap();
+        Inhibit(false)
+    }});
+
+    connect_canteen_rows(&canteens_list_box, &canteen_rows, settings);
+
     clear_cache_button.connect_clicked(enclose! { (window, rt) move |btn| {
         // start removing the cache in a tokio future
         let removed = Arc::new(Notify::new());
         rt.spawn(enclose! { (removed) async move {
             log::debug!("try removing cache");
-            // loop will only run once, used to abort early with break as ? is not available in scopes
-            for xdg in xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk") {
-                log::debug!("found cache directory");
-                let history_path = match xdg.find_cache_file("history.json") {
-                    Some(path) => path,
-                    // if no cache is available, just skip
-                    None => {
-                        log::info!("no cache available in cache directory");
-                        break;
-                    },
-                };
-                log::debug!("found cache history file in {:?}", history_path);
-
-                // this cannot fail, as xdg.find_cache_file makes sure the file exists
-                if let Err(err) = tokio::fs::remove_file(history_path).await {
-                    log::error!("failed removing cache: {:#}", err);
-                    break;
-                }
-
-                log::debug!("notifying preferences window that cache got removed");
-                removed.notify();
+            if let Err(err) = cache::clear() {
+                log::error!("failed removing cache: {:#}", err);
+                return;
             }
+
+            log::debug!("notifying preferences window that cache got removed");
+            removed.notify();
         }});
 
         let c = glib::MainContext::default();
@@ -0,0 +1,171 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use gtk::prelude::*;
+use gtk::{Box, Builder, Frame, Label, ListBox, ListBoxRow, Orientation, ScrolledWindow, SelectionMode, ShadowType};
+use gettextrs::gettext as t;
+
+use crate::canteen;
+use crate::components::day::translate_weekday;
+use crate::components::meal::{translate_allergenic, translate_symbol};
+use crate::components::{get, GLADE};
+use crate::model::{Canteen, CanteenDescription};
+
+type SelectedHandler = Rc<dyn Fn(CanteenDescription, NaiveDate)>;
+
+#[derive(Clone)]
+pub struct SearchComponent {
+    page: ScrolledWindow,
+    results_box: Box,
+    empty_label: Label,
+    selected_handler: Rc<RefCell<Option<SelectedHandler>>>,
+}
+
+// every term of the query has to be part of one of the texts
+fn matches(texts: &[String], terms: &[String]) -> bool {
+    let texts = texts.iter().map(|text| text.to_lowercase()).collect::<Vec<_>>();
+    terms.iter().all(|term| texts.iter().any(|text| text.contains(term)))
+}
+
+fn create_row(name: &str, detail: &str) -> ListBoxRow {
+    let row_box = Box::new(Orientation::Vertical, 2);
+    row_box.set_margin_start(8);
+    row_box.set_margin_end(8);
+    row_box.set_margin_top(8);
+    row_box.set_margin_bottom(8);
+
+    let name_label = Label::new(Some(name));
+    name_label.set_xalign(0.0);
+    name_label.set_line_wrap(true);
+    row_box.pack_start(&name_label, false, true, 0);
+
+    if !detail.is_empty() {
+        let detail_label = Label::new(Some(detail));
+        detail_label.set_xalign(0.0);
+        detail_label.set_line_wrap(true);
+        detail_label.get_style_context().add_class("dim-label");
+        detail_label.get_style_context().add_class("small");
+        row_box.pack_start(&detail_label, false, true, 0);
+    }
+
+    let row = ListBoxRow::new();
+    row.set_selectable(false);
+    row.add(&row_box);
+    row
+}
+
+impl SearchComponent {
+    pub fn new() -> Result<Self> {
+        log::debug!("creating new SearchComponent");
+
+        let builder = Builder::new_from_string(GLADE);
+        let page: ScrolledWindow = get!(&builder, "search-page")?;
+        let results_box: Box = get!(&builder, "search-results-box")?;
+        let empty_label: Label = get!(&builder, "search-empty-label")?;
+
+        log::debug!("finish creating SearchComponent");
+
+        Ok(Self {
+            page,
+            results_box,
+            empty_label,
+            selected_handler: Rc::new(RefCell::new(None)),
+        })
+    }
+
+    pub const fn root_widget(&self) -> &ScrolledWindow {
+        &self.page
+    }
+
+    // the handler is called with the canteen and day of a clicked result
+    pub fn connect_selected<F: Fn(CanteenDescription, NaiveDate) + 'static>(&self, handler: F) {
+        self.selected_handler.replace(Some(Rc::new(handler)));
+    }
+
+    pub fn load(&self, canteens: &[Canteen], query: &str) {
+        log::debug!("searching for {} in SearchComponent", query);
+
+        for child in self.results_box.get_children() {
+            child.destroy();
+        }
+
+        let terms = query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        let mut result_count = 0;
+
+        for canteen in canteens {
+            for day in &canteen.days {
+                // results are grouped by canteen and date
+                let mut rows = Vec::new();
+
+                for meal in &day.meals {
+                    let mut texts = vec![meal.name.clone()];
+                    texts.extend(meal.allergenics.iter().map(translate_allergenic));
+                    texts.extend(meal.symbols.iter().map(translate_symbol));
+
+                    if matches(&texts, &terms) {
+                        let symbols = meal.symbols.iter().map(translate_symbol).collect::<Vec<_>>();
+                        rows.push(create_row(&meal.name, &symbols.join(", ")));
+                    }
+                }
+
+                for side_dish in &day.side_dishes {
+                    if matches(&[side_dish.clone()], &terms) {
+                        rows.push(create_row(side_dish, &t("Side dish")));
+                    }
+                }
+
+                if rows.is_empty() {
+                    continue;
+                }
+
+                log::debug!("found {} results on {} in {:?}", rows.len(), day.date, canteen.description);
+                result_count += rows.len();
+
+                let heading = Label::new(Some(&format!(
+                    "{} – {}, {}",
+                    canteen::name(canteen),
+                    translate_weekday(day.date.weekday()),
+                    day.date.format("%d.%m.%Y"),
+                )));
+                heading.set_xalign(0.0);
+                heading.set_line_wrap(true);
+                heading.get_style_context().add_class("search-result-heading");
+
+                let list_box = ListBox::new();
+                list_box.set_selection_mode(SelectionMode::None);
+                for row in rows {
+                    list_box.insert(&row, -1);
+                }
+
+                let date = day.date;
+                let description = canteen.description.clone();
+                let selected_handler = Rc::clone(&self.selected_handler);
+                list_box.connect_row_activated(move |_list_box, _row| {
+                    if let Some(handler) = selected_handler.borrow().as_ref() {
+                        handler(description.clone(), date);
+                    }
+                });
+
+                let frame = Frame::new(None);
+                frame.set_shadow_type(ShadowType::In);
+                frame.get_style_context().add_class("view");
+                frame.add(&list_box);
+
+                let group = Box::new(Orientation::Vertical, 8);
+                group.pack_start(&heading, false, true, 0);
+                group.pack_start(&frame, false, true, 0);
+                group.show_all();
+                self.results_box.pack_start(&group, false, true, 0);
+            }
+        }
+
+        self.empty_label.set_visible(result_count == 0);
+
+        log::debug!("finish searching in SearchComponent with {} results", result_count);
+    }
+}
@@ -0,0 +1,343 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use gtk::prelude::*;
+use gtk::{
+    Builder, ComboBoxText, DrawingArea, Grid, Label, ListBox, ListBoxRow, Orientation, ScrolledWindow, StateFlags,
+};
+use gettextrs::gettext as t;
+
+use crate::archive::Archive;
+use crate::canteen;
+use crate::components::meal::{translate_symbol, SYMBOLS};
+use crate::components::{get, GLADE};
+use crate::model::{Canteen, CanteenDescription, Price};
+use crate::price::PriceGroup;
+use crate::statistics::Statistics;
+use crate::util::enclose;
+
+// the number of dishes shown in the list of most frequent dishes
+const FREQUENT_DISHES: usize = 10;
+
+// the number of latest weeks shown in the symbols table
+const SYMBOL_WEEKS: usize = 8;
+
+// the colors of the student, staff and guest prices in the chart
+const PRICE_COLORS: [(f64, f64, f64); 3] = [(0.21, 0.52, 0.89), (0.2, 0.76, 0.48), (0.96, 0.47, 0.0)];
+
+#[derive(Clone)]
+pub struct StatisticsComponent {
+    page: ScrolledWindow,
+    canteen_combo_box: ComboBoxText,
+    days_label: Label,
+    dishes_list_box: ListBox,
+    symbols_grid: Grid,
+    dish_combo_box: ComboBoxText,
+    price_chart: DrawingArea,
+    statistics: Rc<RefCell<Option<Statistics>>>,
+    price_trend: Rc<RefCell<Vec<(NaiveDate, Price)>>>,
+}
+
+fn create_dish_row(name: &str, count: usize) -> ListBoxRow {
+    let row_box = gtk::Box::new(Orientation::Horizontal, 16);
+    row_box.set_margin_start(8);
+    row_box.set_margin_end(8);
+    row_box.set_margin_top(8);
+    row_box.set_margin_bottom(8);
+
+    let name_label = Label::new(Some(name));
+    name_label.set_xalign(0.0);
+    name_label.set_line_wrap(true);
+    row_box.pack_start(&name_label, true, true, 0);
+
+    let count_label = Label::new(Some(&t("{count}×").replace("{count}", &count.to_string())));
+    count_label.get_style_context().add_class("dim-label");
+    row_box.pack_start(&count_label, false, true, 0);
+
+    let row = ListBoxRow::new();
+    row.set_selectable(false);
+    row.set_activatable(false);
+    row.add(&row_box);
+    row.show_all();
+    row
+}
+
+fn grid_label(text: &str, dim: bool) -> Label {
+    let label = Label::new(Some(text));
+    label.set_xalign(0.0);
+    if dim {
+        label.get_style_context().add_class("dim-label");
+    }
+    label.show();
+    label
+}
+
+// draws the student, staff and guest prices of a dish as lines over time
+fn draw_price_chart(chart: &DrawingArea, cr: &cairo::Context, trend: &[(NaiveDate, Price)]) {
+    let width = f64::from(chart.get_allocated_width());
+    let height = f64::from(chart.get_allocated_height());
+    let foreground = chart.get_style_context().get_color(StateFlags::NORMAL);
+    cr.set_font_size(11.0);
+
+    if trend.is_empty() {
+        let text = t("No recurring dishes");
+        let extents = cr.text_extents(&text);
+        cr.set_source_rgba(foreground.red, foreground.green, foreground.blue, 0.55);
+        cr.move_to((width - extents.width) / 2.0, height / 2.0);
+        cr.show_text(&text);
+        return;
+    }
+
+    let (left, right, top, bottom) = (56.0, 16.0, 32.0, 24.0);
+    let plot_width = width - left - right;
+    let plot_height = height - top - bottom;
+
+    let prices = |price: &Price| {
+        PriceGroup::All
+            .prices(price)
+            .into_iter()
+            .map(|(_group, price)| price)
+            .collect::<Vec<_>>()
+    };
+    let all_prices = trend.iter().flat_map(|(_date, price)| prices(price)).collect::<Vec<_>>();
+    let mut min = all_prices.iter().cloned().fold(f32::INFINITY, f32::min);
+    let mut max = all_prices.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    if (max - min).abs() < 0.01 {
+        min -= 0.5;
+        max += 0.5;
+    }
+    let (min, max) = (f64::from(min.max(0.0)), f64::from(max));
+
+    let first_date = trend[0].0;
+    let span = (trend[trend.len() - 1].0 - first_date).num_days() as f64;
+    let x_of = |date: NaiveDate| {
+        if span > 0.0 {
+            ((date - first_date).num_days() as f64 / span).mul_add(plot_width, left)
+        } else {
+            left + plot_width / 2.0
+        }
+    };
+    let y_of = |price: f32| ((max - f64::from(price)) / (max - min)).mul_add(plot_height, top);
+
+    // horizontal grid lines with their price
+    cr.set_line_width(1.0);
+    for tick in 0..=4 {
+        let price = min + (max - min) * f64::from(tick) / 4.0;
+        let y = y_of(price as f32);
+        cr.set_source_rgba(foreground.red, foreground.green, foreground.blue, 0.15);
+        cr.move_to(left, y);
+        cr.line_to(width - right, y);
+        cr.stroke();
+
+        cr.set_source_rgba(foreground.red, foreground.green, foreground.blue, 0.55);
+        cr.move_to(0.0, y + 4.0);
+        cr.show_text(&format!("{:.2} €", price));
+    }
+
+    // the first and the last date below the plot
+    cr.move_to(left, height - 6.0);
+    cr.show_text(&first_date.format("%d.%m.%Y").to_string());
+    let last_date = trend[trend.len() - 1].0.format("%d.%m.%Y").to_string();
+    let extents = cr.text_extents(&last_date);
+    cr.move_to(width - right - extents.width, height - 6.0);
+    cr.show_text(&last_date);
+
+    let mut legend_x = left;
+    for (idx, group) in PriceGroup::All.shown().iter().map(|group| group.translate()).enumerate() {
+        let (red, green, blue) = PRICE_COLORS[idx];
+        cr.set_source_rgb(red, green, blue);
+
+        // the line of the price group
+        cr.set_line_width(2.0);
+        for (point, (date, price)) in trend.iter().enumerate() {
+            let (x, y) = (x_of(*date), y_of(prices(price)[idx]));
+            if point == 0 {
+                cr.move_to(x, y);
+            } else {
+                cr.line_to(x, y);
+            }
+        }
+        cr.stroke();
+
+        for (date, price) in trend {
+            cr.arc(x_of(*date), y_of(prices(price)[idx]), 3.0, 0.0, 2.0 * std::f64::consts::PI);
+            cr.fill();
+        }
+
+        // the legend above the plot
+        cr.rectangle(legend_x, 8.0, 10.0, 10.0);
+        cr.fill();
+        cr.set_source_rgba(foreground.red, foreground.green, foreground.blue, foreground.alpha);
+        cr.move_to(legend_x + 14.0, 17.0);
+        cr.show_text(&group);
+        legend_x += 14.0 + cr.text_extents(&group).width + 24.0;
+    }
+}
+
+impl StatisticsComponent {
+    pub fn new() -> Result<Self> {
+        log::debug!("creating new StatisticsComponent");
+
+        let builder = Builder::new_from_string(GLADE);
+        let page: ScrolledWindow = get!(&builder, "statistics-page")?;
+        let canteen_combo_box: ComboBoxText = get!(&builder, "statistics-canteen-combo-box")?;
+        let days_label: Label = get!(&builder, "statistics-days-label")?;
+        let dishes_list_box: ListBox = get!(&builder, "statistics-dishes-list-box")?;
+        let symbols_grid: Grid = get!(&builder, "statistics-symbols-grid")?;
+        let dish_combo_box: ComboBoxText = get!(&builder, "statistics-dish-combo-box")?;
+        let price_chart: DrawingArea = get!(&builder, "statistics-price-chart")?;
+
+        let comp = Self {
+            page,
+            canteen_combo_box,
+            days_label,
+            dishes_list_box,
+            symbols_grid,
+            dish_combo_box,
+            price_chart,
+            statistics: Rc::new(RefCell::new(None)),
+            price_trend: Rc::new(RefCell::new(Vec::new())),
+        };
+
+        comp.canteen_combo_box.connect_changed(enclose! { (comp) move |_combo_box| {
+            comp.update_canteen();
+        }});
+
+        comp.dish_combo_box.connect_changed(enclose! { (comp) move |_combo_box| {
+            comp.update_price_trend();
+        }});
+
+        let price_trend = Rc::clone(&comp.price_trend);
+        comp.price_chart.connect_draw(move |chart, cr| {
+            draw_price_chart(chart, cr, &price_trend.borrow());
+            Inhibit(false)
+        });
+
+        log::debug!("finish creating StatisticsComponent");
+
+        Ok(comp)
+    }
+
+    pub const fn root_widget(&self) -> &ScrolledWindow {
+        &self.page
+    }
+
+    fn selected_canteen(&self) -> Option<CanteenDescription> {
+        self.canteen_combo_box
+            .get_active_id()
+            .map(|id| CanteenDescription::new(id.as_str()))
+    }
+
+    // computes the statistics of the loaded and archived canteens and shows the given canteen
+    pub fn load(
+        &self,
+        canteens: &[Canteen],
+        archive: &Archive,
+        titles: &[(CanteenDescription, String)],
+        visible_canteen: Option<CanteenDescription>,
+    ) {
+        log::debug!("loading statistics into StatisticsComponent");
+
+        let statistics = Statistics::new(canteens, archive);
+        let available = statistics.canteens();
+        self.statistics.replace(Some(statistics));
+
+        self.canteen_combo_box.remove_all();
+        for canteen_desc in &available {
+            let title = titles
+                .iter()
+                .find(|(desc, _title)| desc == canteen_desc)
+                .map_or_else(|| canteen::translate(canteen_desc), |(_desc, title)| title.clone());
+            self.canteen_combo_box.append(Some(canteen_desc.id()), &title);
+        }
+
+        let active_canteen = visible_canteen
+            .filter(|visible_canteen| available.contains(visible_canteen))
+            .or_else(|| available.iter().next().cloned());
+        match active_canteen {
+            Some(canteen_desc) => {
+                self.canteen_combo_box.set_active_id(Some(canteen_desc.id()));
+            },
+            None => self.update_canteen(),
+        }
+
+        log::debug!("finish loading statistics into StatisticsComponent");
+    }
+
+    fn update_canteen(&self) {
+        for child in self.dishes_list_box.get_children() {
+            child.destroy();
+        }
+        for child in self.symbols_grid.get_children() {
+            child.destroy();
+        }
+        self.dish_combo_box.remove_all();
+
+        let statistics = self.statistics.borrow();
+        let (statistics, canteen_desc) = match (statistics.as_ref(), self.selected_canteen()) {
+            (Some(statistics), Some(canteen_desc)) => (statistics, canteen_desc),
+            _ => {
+                self.days_label.set_text(&t("No menus available"));
+                return;
+            },
+        };
+        log::debug!("showing statistics of canteen {:?}", canteen_desc);
+
+        self.days_label.set_text(
+            &t("Based on the menus of {days} days")
+                .replace("{days}", &statistics.day_count(&canteen_desc).to_string()),
+        );
+
+        let dishes = statistics.dishes(&canteen_desc);
+        for dish in dishes.iter().take(FREQUENT_DISHES) {
+            self.dishes_list_box.insert(&create_dish_row(&dish.name, dish.count), -1);
+        }
+
+        // the table has a row for every symbol and a column for every week
+        let weeks = statistics.symbols_per_week(&canteen_desc);
+        let weeks = &weeks[weeks.len().saturating_sub(SYMBOL_WEEKS)..];
+        for (column, (week, _counts)) in weeks.iter().enumerate() {
+            let label = grid_label(&format!("{} {}", t("Week"), week.iso_week().week()), true);
+            label.set_tooltip_text(Some(&week.format("%d.%m.%Y").to_string()));
+            self.symbols_grid.attach(&label, column as i32 + 1, 0, 1, 1);
+        }
+        let mut row = 1;
+        for symbol in SYMBOLS.iter() {
+            let name = serde_plain::to_string(symbol).unwrap_or_default();
+            if weeks.iter().all(|(_week, counts)| !counts.contains_key(&name)) {
+                continue;
+            }
+
+            self.symbols_grid.attach(&grid_label(&translate_symbol(symbol), false), 0, row, 1, 1);
+            for (column, (_week, counts)) in weeks.iter().enumerate() {
+                let count = counts.get(&name).copied().unwrap_or_default();
+                self.symbols_grid.attach(&grid_label(&count.to_string(), count == 0), column as i32 + 1, row, 1, 1);
+            }
+            row += 1;
+        }
+
+        // only dishes which got served more than once have a trend
+        let recurring_dishes = dishes.iter().filter(|dish| dish.count > 1).collect::<Vec<_>>();
+        for dish in &recurring_dishes {
+            self.dish_combo_box.append(Some(&dish.key), &dish.name);
+        }
+        if recurring_dishes.is_empty() {
+            self.update_price_trend();
+        } else {
+            self.dish_combo_box.set_active(Some(0));
+        }
+    }
+
+    fn update_price_trend(&self) {
+        let trend = match (self.statistics.borrow().as_ref(), self.selected_canteen(), self.dish_combo_box.get_active_id()) {
+            (Some(statistics), Some(canteen_desc), Some(dish_key)) => statistics.price_trend(&canteen_desc, &dish_key),
+            _ => Vec::new(),
+        };
+        log::debug!("showing price trend with {} prices", trend.len());
+
+        self.price_trend.replace(trend);
+        self.price_chart.queue_draw();
+    }
+}
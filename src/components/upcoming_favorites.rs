@@ -0,0 +1,122 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use chrono::{Datelike, Local};
+use gtk::prelude::*;
+use gtk::{Box, Builder, Label, ListBox, ListBoxRow, Orientation, ScrolledWindow};
+use gettextrs::gettext as t;
+
+use crate::canteen;
+use crate::components::day::translate_weekday;
+use crate::components::{get, GLADE};
+use crate::favorites;
+use crate::model::Canteen;
+
+#[derive(Clone)]
+pub struct UpcomingFavoritesComponent {
+    page: ScrolledWindow,
+    list_box: ListBox,
+    empty_label: Label,
+}
+
+impl UpcomingFavoritesComponent {
+    pub fn new() -> Result<Self> {
+        log::debug!("creating new UpcomingFavoritesComponent");
+
+        let builder = Builder::new_from_string(GLADE);
+        let page: ScrolledWindow = get!(&builder, "upcoming-favorites-page")?;
+        let list_box: ListBox = get!(&builder, "upcoming-favorites-list-box")?;
+        let empty_label: Label = get!(&builder, "upcoming-favorites-empty-label")?;
+
+        log::debug!("finish creating UpcomingFavoritesComponent");
+
+        Ok(Self {
+            page,
+            list_box,
+            empty_label,
+        })
+    }
+
+    pub const fn root_widget(&self) -> &ScrolledWindow {
+        &self.page
+    }
+
+    pub fn load(&self, canteens: &[Canteen], favorites: &BTreeSet<String>) {
+        log::debug!("loading content into UpcomingFavoritesComponent");
+
+        for child in self.list_box.get_children() {
+            child.destroy();
+        }
+
+        self.empty_label.set_visible(favorites.is_empty());
+        self.list_box.set_visible(!favorites.is_empty());
+
+        let today = Local::today().naive_local();
+
+        for favorite in favorites {
+            // collect all upcoming occurrences of this favorite in all canteens
+            let mut occurrences = canteens
+                .iter()
+                .flat_map(|canteen| {
+                    canteen.days.iter().filter(|day| day.date >= today).flat_map(move |day| {
+                        day.meals
+                            .iter()
+                            .filter(move |meal| &favorites::normalize(&meal.name) == favorite)
+                            .map(move |meal| (day.date, canteen, &meal.name))
+                    })
+                })
+                .collect::<Vec<_>>();
+            occurrences.sort_by_key(|(date, _canteen, _name)| *date);
+
+            log::debug!("favorite {} appears {} times in upcoming menus", favorite, occurrences.len());
+
+            // show the name of the meal as it is written on the menu if possible
+            let name = occurrences
+                .first()
+                .map_or(favorite.as_str(), |(_date, _canteen, name)| name.as_str());
+
+            let row_box = Box::new(Orientation::Vertical, 4);
+            row_box.set_margin_start(8);
+            row_box.set_margin_end(8);
+            row_box.set_margin_top(8);
+            row_box.set_margin_bottom(8);
+
+            let name_label = Label::new(Some(name));
+            name_label.set_xalign(0.0);
+            name_label.set_line_wrap(true);
+            name_label.set_selectable(true);
+            name_label.get_style_context().add_class("upcoming-favorite-name");
+            row_box.pack_start(&name_label, false, true, 0);
+
+            if occurrences.is_empty() {
+                let label = Label::new(Some(&t("Not on any upcoming menu")));
+                label.set_xalign(0.0);
+                label.get_style_context().add_class("dim-label");
+                label.get_style_context().add_class("small");
+                row_box.pack_start(&label, false, true, 0);
+            }
+
+            for (date, canteen, _name) in occurrences {
+                let label = Label::new(Some(&format!(
+                    "{}, {} – {}",
+                    translate_weekday(date.weekday()),
+                    date.format("%d.%m.%Y"),
+                    canteen::name(canteen),
+                )));
+                label.set_xalign(0.0);
+                label.set_line_wrap(true);
+                label.get_style_context().add_class("dim-label");
+                label.get_style_context().add_class("small");
+                row_box.pack_start(&label, false, true, 0);
+            }
+
+            let row = ListBoxRow::new();
+            row.set_selectable(false);
+            row.add(&row_box);
+            row.show_all();
+            self.list_box.insert(&row, -1);
+        }
+
+        log::debug!("finish loading UpcomingFavoritesComponent");
+    }
+}
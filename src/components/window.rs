@@ -1,44 +1,46 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::fs::File;
 use std::rc::Rc;
 use std::sync::Arc;
 
-use lazy_static::lazy_static;
 use anyhow::{bail, Context, Result};
 use cargo_author::Author;
 use gio::prelude::*;
 use gio::{Settings, SimpleAction};
 use gtk::prelude::*;
 use gtk::{
-    AboutDialog, Box, Builder, Button, ButtonRole, Label, MenuButton, ModelButtonBuilder, Stack,
-    Window
+    AboutDialog, Box, Builder, Button, ButtonRole, Label, MenuButton, ModelButton, ModelButtonBuilder,
+    SearchBar, SearchEntry, Stack, ToggleButton, Window
 };
-use ovgu_canteen::{Canteen, CanteenDescription};
 use send_wrapper::SendWrapper;
 use tokio::runtime::Handle;
 use tokio::sync::mpsc::channel;
 use tokio::sync::Notify;
 use futures::future;
-use chrono::{Local, Duration};
+use chrono::{Local, Duration, NaiveDate};
 use gettextrs::gettext as t;
 
-use crate::components::{get, preferences, CanteenComponent, GLADE};
+use crate::archive;
+use crate::cache;
+use crate::calendar;
+use crate::components::{
+    get, glib_yield, export_dialog, preferences, CanteenComponent, CompareComponent, DashboardComponent,
+    HistoryComponent, LedgerComponent, SearchComponent, StatisticsComponent, UpcomingFavoritesComponent, GLADE,
+};
+use crate::favorites::{self, Favorites};
+use crate::fetch;
+use crate::filter;
+use crate::ledger::{self, Ledger};
+use crate::model::{Canteen, CanteenDescription};
+use crate::notifications::{self, Notified};
+use crate::ratings::{self, Ratings};
+use crate::refresh;
+use crate::sorting::{self, MealOrder};
+use crate::source;
 use crate::util::enclose;
 use crate::canteen;
 
-lazy_static! {
-    // all available canteens
-    static ref CANTEENS: Vec<CanteenDescription> = vec![
-        CanteenDescription::UniCampusLowerHall,
-        CanteenDescription::UniCampusUpperHall,
-        CanteenDescription::Kellercafe,
-        CanteenDescription::Herrenkrug,
-        CanteenDescription::Stendal,
-        CanteenDescription::Wernigerode,
-        CanteenDescription::DomCafeteHalberstadt,
-    ];
-}
+type CanteenButtons = Rc<RefCell<Vec<(CanteenDescription, ModelButton)>>>;
 
 #[derive(Clone)]
 pub struct WindowComponent {
@@ -49,29 +51,62 @@ pub struct WindowComponent {
     canteens_menu: Box,
     canteen_menu_button: MenuButton,
     canteen_label: Label,
+    // the buttons of the canteen menu in the order of the canteens
+    canteen_buttons: CanteenButtons,
     reload_button: Button,
     canteen_components: Rc<RefCell<HashMap<CanteenDescription, CanteenComponent>>>,
+    canteens: Rc<RefCell<Vec<Canteen>>>,
+    // whether the canteens are currently loaded
+    loading: Rc<Cell<bool>>,
+    // whether the meal order changed while the canteens were loaded
+    meal_order_outdated: Rc<Cell<bool>>,
+    // the periodic refresh and a requested refresh waiting for further triggers
+    refresh_interval: Rc<RefCell<Option<glib::SourceId>>>,
+    pending_refresh: Rc<RefCell<Option<glib::SourceId>>>,
+    // day to scroll to as soon as the canteen components are available
+    pending_day: Rc<RefCell<Option<(CanteenDescription, NaiveDate)>>>,
+    dashboard: DashboardComponent,
+    upcoming_favorites: UpcomingFavoritesComponent,
+    compare: CompareComponent,
+    history: HistoryComponent,
+    statistics: StatisticsComponent,
+    ledger_page: LedgerComponent,
+    search_bar: SearchBar,
+    search_entry: SearchEntry,
+    search: SearchComponent,
     settings: Settings,
+    favorites: Favorites,
+    ledger: Ledger,
+    ratings: Ratings,
+    notified: Notified,
+}
+
+// the title of a canteen as shown in the canteen menu
+fn canteen_title(canteen_buttons: &CanteenButtons, description: &CanteenDescription) -> String {
+    canteen_buttons
+        .borrow()
+        .iter()
+        .find(|(desc, _btn)| desc == description)
+        .and_then(|(_desc, btn)| btn.get_property_text())
+        .map_or_else(|| canteen::translate(description), |text| text.to_string())
 }
 
 impl WindowComponent {
+    #[allow(clippy::new_ret_no_self)] // the component is kept alive by the signal handlers of the window
     pub fn new(rt: &Handle, app: &gtk::Application) -> Result<()> {
         log::debug!("creating new WindowComponent");
 
         log::debug!("fetching settings for application");
         let settings = Settings::new("io.github.fin_ger.OvGUCanteen");
         settings.connect_changed(|settings, key| {
-            match key {
-                "dark-theme-variant" => {
-                    if let Some(gtk_settings) = gtk::Settings::get_default() {
-                        log::debug!("setting dark theme to {}", settings.get_boolean(key));
-                        gtk::SettingsExt::set_property_gtk_application_prefer_dark_theme(
-                            &gtk_settings,
-                            settings.get_boolean(key),
-                        );
-                    }
-                },
-                _ => {},
+            if key == "dark-theme-variant" {
+                if let Some(gtk_settings) = gtk::Settings::get_default() {
+                    log::debug!("setting dark theme to {}", settings.get_boolean(key));
+                    gtk::SettingsExt::set_property_gtk_application_prefer_dark_theme(
+                        &gtk_settings,
+                        settings.get_boolean(key),
+                    );
+                }
             }
         });
         if let Some(gtk_settings) = gtk::Settings::get_default() {
@@ -98,6 +133,17 @@ impl WindowComponent {
         let about_button: Button = get!(&builder, "about-btn")?;
         let options_button: MenuButton = get!(&builder, "options-button")?;
         let reload_button: Button = get!(&builder, "reload-button")?;
+        let dashboard_button: Button = get!(&builder, "dashboard-btn")?;
+        let upcoming_favorites_button: Button = get!(&builder, "upcoming-favorites-btn")?;
+        let compare_button: Button = get!(&builder, "compare-btn")?;
+        let history_button: Button = get!(&builder, "history-btn")?;
+        let statistics_button: Button = get!(&builder, "statistics-btn")?;
+        let ledger_button: Button = get!(&builder, "ledger-btn")?;
+        let export_button: Button = get!(&builder, "export-btn")?;
+        let diet_profile_menu: Box = get!(&builder, "diet-profile-menu")?;
+        let search_button: ToggleButton = get!(&builder, "search-button")?;
+        let search_bar: SearchBar = get!(&builder, "search-bar")?;
+        let search_entry: SearchEntry = get!(&builder, "search-entry")?;
 
         window.set_application(Some(app));
         window.set_icon_name(Some("io.github.fin_ger.OvGUCanteen"));
@@ -144,8 +190,11 @@ impl WindowComponent {
             // single parameter which is a string containing the canteen-name
             Some(glib::VariantTy::new("s").unwrap()),
         );
+        let canteen_buttons: CanteenButtons = Rc::new(RefCell::new(Vec::new()));
+        let window_stack_handle = window_stack.clone();
         let canteens_stack_handle = canteens_stack.clone();
         let canteen_label_handle = canteen_label.clone();
+        let canteen_buttons_handle = Rc::clone(&canteen_buttons);
         canteen_selected_action.connect_activate(move |_action, maybe_canteen_variant| {
             let canteen_variant = match maybe_canteen_variant {
                 Some(v) => v,
@@ -157,18 +206,94 @@ impl WindowComponent {
             };
 
             log::debug!("switching visible canteen to {}", canteen_name);
+            window_stack_handle.set_visible_child_name("canteens-stack");
             canteens_stack_handle.set_visible_child_name(canteen_name);
             canteen_label_handle.set_text(
-                &canteen::translate(
-                    &serde_plain::from_str::<CanteenDescription>(canteen_name).unwrap()
-                )
+                &canteen_title(&canteen_buttons_handle, &CanteenDescription::new(canteen_name))
             );
         });
         app.add_action(&canteen_selected_action);
 
+        let day_selected_action = SimpleAction::new(
+            // action name
+            "day-selected",
+            // single parameter which is a string containing the canteen-name and date, e.g.
+            // UniCampusLowerHall:2020-05-04
+            Some(glib::VariantTy::new("s").unwrap()),
+        );
+        app.add_action(&day_selected_action);
+
+        log::debug!("adding diet profiles to quick toggle menu");
+        // the diet-profile action is bound directly to the diet-profile settings key
+        if let Some(diet_profile_action) = settings.create_action("diet-profile") {
+            app.add_action(&diet_profile_action);
+        }
+        for &(name, profile) in filter::DIET_PROFILES.iter() {
+            let model_btn = ModelButtonBuilder::new()
+                .visible(true)
+                .text(&profile.translate())
+                .can_focus(false)
+                .action_name("app.diet-profile")
+                .action_target(&name.to_variant())
+                .role(ButtonRole::Radio)
+                .build();
+
+            diet_profile_menu.pack_start(&model_btn, false, true, 0);
+        }
+
+        log::debug!("loading favorites");
+        let favorites = favorites::load();
+        let ledger = ledger::load();
+        let ratings = ratings::load();
+        let notified = notifications::load();
+
+        log::debug!("adding dashboard page to window");
+        let dashboard = DashboardComponent::new(&settings, &favorites, &ratings)?;
+        window_stack.add_named(dashboard.root_widget(), "dashboard");
+
+        log::debug!("adding upcoming favorites page to window");
+        let upcoming_favorites = UpcomingFavoritesComponent::new()?;
+        window_stack.add_named(upcoming_favorites.root_widget(), "upcoming-favorites");
+
+        log::debug!("adding compare page to window");
+        let compare = CompareComponent::new(&settings, &favorites, &ledger, &ratings)?;
+        window_stack.add_named(compare.root_widget(), "compare");
+
+        log::debug!("adding menu history page to window");
+        let history = HistoryComponent::new(&settings, &favorites, &ledger, &ratings)?;
+        window_stack.add_named(history.root_widget(), "history");
+
+        log::debug!("adding statistics page to window");
+        let statistics = StatisticsComponent::new()?;
+        window_stack.add_named(statistics.root_widget(), "statistics");
+
+        log::debug!("adding spending page to window");
+        let ledger_page = LedgerComponent::new(&settings, &ledger)?;
+        window_stack.add_named(ledger_page.root_widget(), "ledger");
+
+        log::debug!("adding search results page to window");
+        let search = SearchComponent::new()?;
+        window_stack.add_named(search.root_widget(), "search");
+        search_button
+            .bind_property("active", &search_bar, "search-mode-enabled")
+            .flags(glib::BindingFlags::BIDIRECTIONAL)
+            .build();
+        search_bar.connect_entry(&search_entry);
+
+        let search_action = SimpleAction::new(
+            // action name
+            "search",
+            // single parameter which is a string containing the initial search query
+            Some(glib::VariantTy::new("s").unwrap()),
+        );
+        app.add_action(&search_action);
+        app.set_accels_for_action("app.search('')", &["<Primary>f"]);
+
         log::debug!("showing window");
 
         window.show_all();
+        // the dashboard is shown on start, the canteens are one click away
+        window_stack.set_visible_child_name("dashboard");
 
         let comp = Self {
             window,
@@ -178,26 +303,218 @@ impl WindowComponent {
             canteens_menu,
             canteen_label,
             canteen_menu_button,
+            canteen_buttons,
             reload_button,
             canteen_components: Rc::new(RefCell::new(HashMap::new())),
+            canteens: Rc::new(RefCell::new(Vec::new())),
+            loading: Rc::new(Cell::new(false)),
+            meal_order_outdated: Rc::new(Cell::new(false)),
+            refresh_interval: Rc::new(RefCell::new(None)),
+            pending_refresh: Rc::new(RefCell::new(None)),
+            pending_day: Rc::new(RefCell::new(None)),
+            dashboard,
+            upcoming_favorites,
+            compare,
+            history,
+            statistics,
+            ledger_page,
+            search_bar,
+            search_entry,
+            search,
             settings,
+            favorites,
+            ledger,
+            ratings,
+            notified,
         };
 
+        day_selected_action.connect_activate(enclose! { (comp) move |_action, maybe_day_variant| {
+            let day_name = match maybe_day_variant.and_then(glib::Variant::get_str) {
+                Some(s) => s,
+                None => return,
+            };
+            let mut parts = day_name.splitn(2, ':');
+            let description = parts
+                .next()
+                .map(CanteenDescription::new);
+            let date = parts
+                .next()
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+
+            match (description, date) {
+                (Some(description), Some(date)) => comp.show_day(description, date),
+                _ => log::warn!("invalid day {} selected", day_name),
+            }
+        }});
+
+        search_action.connect_activate(enclose! { (comp) move |_action, maybe_query_variant| {
+            log::debug!("opening search bar");
+            comp.search_bar.set_search_mode(true);
+            if let Some(query) = maybe_query_variant.and_then(glib::Variant::get_str).filter(|query| !query.is_empty()) {
+                comp.search_entry.set_text(query);
+            }
+            comp.search_entry.grab_focus();
+        }});
+
+        comp.search_entry.connect_search_changed(enclose! { (comp) move |_entry| {
+            comp.update_search();
+        }});
+
+        comp.search_bar.connect_property_search_mode_enabled_notify(enclose! { (comp) move |search_bar| {
+            // leave the results page when the search gets closed
+            if !search_bar.get_search_mode() {
+                comp.search_entry.set_text("");
+            }
+        }});
+
+        comp.search.connect_selected(enclose! { (comp) move |description, date| {
+            comp.show_day(description, date);
+        }});
+
+        dashboard_button.connect_clicked(enclose! { (comp, options_button) move |_btn| {
+            if let Some(popover) = options_button.get_popover() {
+                popover.popdown();
+            }
+
+            log::debug!("showing dashboard");
+            comp.update_dashboard();
+            comp.window_stack.set_visible_child_name("dashboard");
+        }});
+
+        upcoming_favorites_button.connect_clicked(enclose! { (comp, options_button) move |_btn| {
+            if let Some(popover) = options_button.get_popover() {
+                popover.popdown();
+            }
+
+            log::debug!("showing upcoming favorites");
+            comp.update_upcoming_favorites();
+            comp.window_stack.set_visible_child_name("upcoming-favorites");
+        }});
+
+        compare_button.connect_clicked(enclose! { (comp, options_button) move |_btn| {
+            if let Some(popover) = options_button.get_popover() {
+                popover.popdown();
+            }
+
+            log::debug!("showing canteen comparison");
+            comp.compare.load(&comp.canteens.borrow(), &comp.canteen_titles(), comp.visible_canteen());
+            comp.window_stack.set_visible_child_name("compare");
+        }});
+
+        history_button.connect_clicked(enclose! { (comp, options_button) move |_btn| {
+            if let Some(popover) = options_button.get_popover() {
+                popover.popdown();
+            }
+
+            log::debug!("showing menu history");
+            let visible_canteen = comp.visible_canteen();
+            comp.history.load(&comp.canteen_titles(), visible_canteen);
+            comp.window_stack.set_visible_child_name("history");
+        }});
+
+        statistics_button.connect_clicked(enclose! { (comp, options_button) move |_btn| {
+            if let Some(popover) = options_button.get_popover() {
+                popover.popdown();
+            }
+
+            log::debug!("showing statistics");
+            let visible_canteen = comp.visible_canteen();
+            comp.statistics.load(&comp.canteens.borrow(), &archive::read(), &comp.canteen_titles(), visible_canteen);
+            comp.window_stack.set_visible_child_name("statistics");
+        }});
+
+        ledger_button.connect_clicked(enclose! { (comp, options_button) move |_btn| {
+            if let Some(popover) = options_button.get_popover() {
+                popover.popdown();
+            }
+
+            log::debug!("showing spending");
+            // the totals depend on the current date
+            comp.ledger_page.load();
+            comp.window_stack.set_visible_child_name("ledger");
+        }});
+
+        export_button.connect_clicked(enclose! { (comp, options_button) move |_btn| {
+            if let Some(popover) = options_button.get_popover() {
+                popover.popdown();
+            }
+
+            if let Err(e) = export_dialog::open(&comp, &comp.canteens.borrow()) {
+                log::error!("failed to open export dialog: {:#}", e);
+            }
+        }});
+
+        // keep the upcoming favorites up to date when a meal gets starred
+        comp.favorites.connect_changed(enclose! { (comp, rt) move |_favorites| {
+            comp.update_upcoming_favorites();
+            comp.update_dashboard();
+            comp.update_calendar_feed(&rt);
+            if MealOrder::from_settings(&comp.settings).depends_on_favorites() {
+                comp.update_meal_order(&rt);
+            }
+        }});
+
+        // meals sorted by their rating move when a meal gets rated
+        comp.ratings.connect_changed(enclose! { (comp, rt) move |_dishes| {
+            if MealOrder::from_settings(&comp.settings).depends_on_ratings() {
+                comp.update_meal_order(&rt);
+                comp.update_dashboard();
+            }
+        }});
+
+        // warn once when the meals of this month start to exceed the budget
+        let month_total = Rc::new(Cell::new(ledger::month_total(&comp.ledger.get(), Local::today().naive_local())));
+        comp.ledger.connect_changed(enclose! { (comp, month_total) move |entries| {
+            let spent = ledger::month_total(entries, Local::today().naive_local());
+            if let Some(budget) = ledger::budget(&comp.settings) {
+                if spent > budget && month_total.get() <= budget {
+                    if let Some(app) = comp.window.get_application() {
+                        ledger::notify_budget_exceeded(&app, spent, budget);
+                    }
+                }
+            }
+            month_total.set(spent);
+        }});
+
+        // regenerate the calendar feed from the loaded canteens when its settings change
+        comp.settings.connect_changed(enclose! { (comp, rt) move |_settings, key| {
+            if calendar::FEED_KEYS.contains(&key) || filter::FILTER_KEYS.contains(&key) {
+                comp.update_calendar_feed(&rt);
+            }
+            if filter::FILTER_KEYS.contains(&key) {
+                comp.update_dashboard();
+            }
+        }});
+
+        // the order of the meals is the same in all canteens, so all of them get sorted again
+        comp.settings.connect_changed(enclose! { (comp, rt) move |_settings, key| {
+            if sorting::SORTING_KEYS.contains(&key) {
+                comp.update_meal_order(&rt);
+                comp.update_dashboard();
+            }
+        }});
+
+        // show, hide and reorder the canteens when they get changed in the preferences
+        comp.settings.connect_changed(enclose! { (comp, rt) move |_settings, key| {
+            if key == "canteens" {
+                comp.update_canteens(&rt);
+            }
+        }});
+
         preferences_button.connect_clicked(enclose! { (rt, comp, options_button) move |_btn| {
             if let Some(popover) = options_button.get_popover() {
                 popover.popdown();
             }
 
-            let _preferences = preferences::open(&rt, &comp, CANTEENS.iter());
+            let _preferences = preferences::open(&rt, &comp, &comp.canteen_titles());
         }});
 
         log::debug!("creating CanteenComponents");
+        let sources = source::from_settings(Some(&comp.settings));
         let mut canteen_components_borrow = comp.canteen_components.borrow_mut();
-        for desc in CANTEENS.iter() {
-            canteen_components_borrow.insert(
-                desc.clone(),
-                CanteenComponent::new(desc, &comp).context("Failed to create canteen!")?,
-            );
+        for desc in source::enabled_canteens(&sources, Some(&comp.settings)) {
+            let canteen_comp = CanteenComponent::new(&desc, &comp).context("Failed to create canteen!")?;
+            canteen_components_borrow.insert(desc, canteen_comp);
         }
         drop(canteen_components_borrow);
 
@@ -206,17 +523,44 @@ impl WindowComponent {
             log::debug!("switching visible canteen to {}", default_canteen);
             comp.canteens_stack.set_visible_child_name(&default_canteen);
             comp.canteen_label.set_text(
-                &canteen::translate(
-                    &serde_plain::from_str::<CanteenDescription>(&default_canteen).unwrap()
-                )
+                &canteen_title(&comp.canteen_buttons, &CanteenDescription::new(default_canteen.as_str()))
             );
         }
 
+        log::debug!("setting up background refresh");
+        comp.update_refresh_interval(rt);
+        comp.settings.connect_changed(enclose! { (comp, rt) move |_settings, key| {
+            if key == "refresh-interval" {
+                comp.update_refresh_interval(&rt);
+            }
+        }});
+        if let Err(e) = refresh::connect_network_restored(enclose! { (comp, rt) move || {
+            comp.request_refresh(&rt, "network connection restored");
+        }}) {
+            log::warn!("refreshing on network reconnect not available: {:#}", e);
+        }
+        match refresh::connect_resumed(enclose! { (comp, rt) move || {
+            comp.request_refresh(&rt, "resumed from suspend");
+        }}) {
+            // the callback holds the component, so the subscription has to end with the window
+            Ok(subscription) => {
+                let subscription = RefCell::new(Some(subscription));
+                comp.window.connect_destroy(move |_window| drop(subscription.replace(None)));
+            },
+            Err(e) => log::warn!("refreshing on resume not available: {:#}", e),
+        }
+
         log::debug!("loading CanteenComponents");
         comp.load(rt, None);
         comp.reload_button
             .clone()
             .connect_clicked(enclose! { (rt) move |_btn| {
+                // the button is insensitive while loading, but the click might have been queued already
+                if comp.loading.get() {
+                    log::debug!("canteens are already loading, skipping reload");
+                    return;
+                }
+
                 log::debug!("reloading CanteenComponents");
                 comp.load(&rt, None);
             }});
@@ -226,13 +570,13 @@ impl WindowComponent {
         Ok(())
     }
 
-    pub fn add_canteen(&self, canteen_stack: &Stack, canteen: String, canteen_name: String) -> Result<()> {
+    pub fn add_canteen(&self, canteen_stack: &Stack, canteen: String, canteen_name: &str) -> Result<()> {
         log::debug!("adding canteen {} to WindowComponent", canteen_name);
         self.canteens_stack.add_named(canteen_stack, &canteen);
 
         let model_btn = ModelButtonBuilder::new()
             .visible(true)
-            .text(&canteen_name)
+            .text(canteen_name)
             .can_focus(false)
             .action_name("app.canteen-selected")
             .action_target(&canteen.to_variant())
@@ -240,48 +584,346 @@ impl WindowComponent {
             .build();
 
         self.canteens_menu.pack_start(&model_btn, false, true, 0);
+        self.canteen_buttons.borrow_mut().push((CanteenDescription::new(canteen), model_btn));
 
         Ok(())
     }
 
-    pub fn window(&self) -> &Window {
+    // all available canteens with their title, including the hidden ones
+    pub fn canteen_titles(&self) -> Vec<(CanteenDescription, String)> {
+        source::canteens(&source::from_settings(Some(&self.settings)))
+            .into_iter()
+            .map(|desc| {
+                let title = canteen_title(&self.canteen_buttons, &desc);
+                (desc, title)
+            })
+            .collect()
+    }
+
+    // creates and removes canteen components to match the enabled canteens,
+    // the canteens get reloaded if a canteen got enabled or disabled
+    fn update_canteens(&self, rt: &Handle) {
+        // the canteen components are in use while loading, they get updated after loading then
+        if self.loading.get() {
+            log::debug!("canteens are loading, updating enabled canteens afterwards");
+            return;
+        }
+
+        log::debug!("updating enabled canteens in WindowComponent");
+
+        let sources = source::from_settings(Some(&self.settings));
+        let enabled_canteens = source::enabled_canteens(&sources, Some(&self.settings));
+        let mut canteen_components = self.canteen_components.borrow_mut();
+        let canteen_count = canteen_components.len();
+
+        canteen_components.retain(|desc, comp| {
+            if enabled_canteens.contains(desc) {
+                return true;
+            }
+
+            log::debug!("removing disabled canteen {:?}", desc);
+            comp.root_widget().destroy();
+            false
+        });
+        self.canteen_buttons.borrow_mut().retain(|(desc, btn)| {
+            if enabled_canteens.contains(desc) {
+                return true;
+            }
+
+            btn.destroy();
+            false
+        });
+        self.canteens.borrow_mut().retain(|canteen| enabled_canteens.contains(&canteen.description));
+        let mut changed = canteen_components.len() != canteen_count;
+
+        for desc in &enabled_canteens {
+            if canteen_components.contains_key(desc) {
+                continue;
+            }
+
+            log::debug!("adding enabled canteen {:?}", desc);
+            match CanteenComponent::new(desc, self) {
+                Ok(comp) => {
+                    canteen_components.insert(desc.clone(), comp);
+                    changed = true;
+                },
+                Err(e) => log::error!("failed to create canteen {:?}: {:#}", desc, e),
+            }
+        }
+        drop(canteen_components);
+
+        // the canteen menu is sorted like the enabled canteens
+        let mut canteen_buttons = self.canteen_buttons.borrow_mut();
+        canteen_buttons.sort_by_key(|(desc, _btn)| enabled_canteens.iter().position(|enabled| enabled == desc));
+        for (idx, (_desc, btn)) in canteen_buttons.iter().enumerate() {
+            self.canteens_menu.reorder_child(btn, idx as i32);
+        }
+        drop(canteen_buttons);
+
+        // another canteen is visible now if the visible canteen got disabled
+        if let Some(visible_canteen) = self.canteens_stack.get_visible_child_name() {
+            self.canteen_label.set_text(
+                &canteen_title(&self.canteen_buttons, &CanteenDescription::new(visible_canteen.as_str()))
+            );
+        }
+
+        // reloading fetches newly enabled canteens and removes disabled ones from the cache
+        if changed {
+            self.update_upcoming_favorites();
+            self.load(rt, None);
+        }
+    }
+
+    // sorts the meals of the loaded canteens again, without fetching them
+    fn update_meal_order(&self, rt: &Handle) {
+        // the canteen components are in use while loading, they get sorted after loading then
+        if self.loading.get() {
+            log::debug!("canteens are loading, sorting meals afterwards");
+            self.meal_order_outdated.set(true);
+            return;
+        }
+
+        log::debug!("sorting meals of loaded canteens in WindowComponent");
+
+        self.reload_button.set_sensitive(false);
+        self.loading.set(true);
+
+        let comp = self.clone();
+        let c = glib::MainContext::default();
+        c.spawn_local(enclose! { (rt) async move {
+            let canteens = comp.canteens.borrow().clone();
+            for canteen in &canteens {
+                if let Some(canteen_comp) = comp.canteen_components.borrow_mut().get_mut(&canteen.description) {
+                    canteen_comp.load_days(canteen).await;
+                }
+            }
+
+            log::debug!("finish sorting meals of loaded canteens");
+
+            comp.reload_button.set_sensitive(true);
+            comp.loading.set(false);
+            comp.update_canteens(&rt);
+            if comp.meal_order_outdated.replace(false) {
+                comp.update_meal_order(&rt);
+            }
+        }});
+    }
+
+    // restarts the periodic refresh with the interval from the settings
+    fn update_refresh_interval(&self, rt: &Handle) {
+        if let Some(source_id) = self.refresh_interval.borrow_mut().take() {
+            glib::source_remove(source_id);
+        }
+
+        let minutes = self.settings.get_uint("refresh-interval");
+        if minutes == 0 {
+            log::debug!("periodic refresh is disabled");
+            return;
+        }
+
+        log::debug!("refreshing canteens every {} minutes", minutes);
+        let comp = self.clone();
+        let source_id = glib::timeout_add_seconds_local(minutes * 60, enclose! { (rt) move || {
+            comp.request_refresh(&rt, "refresh interval elapsed");
+            glib::Continue(true)
+        }});
+        self.refresh_interval.replace(Some(source_id));
+    }
+
+    // reloads the canteens after a short delay, requests in the meantime are
+    // merged into this refresh and a refresh already in flight is not interrupted
+    fn request_refresh(&self, rt: &Handle, reason: &str) {
+        log::debug!("refresh requested: {}", reason);
+
+        if self.pending_refresh.borrow().is_some() {
+            log::debug!("refresh is already pending");
+            return;
+        }
+
+        let comp = self.clone();
+        let source_id = glib::timeout_add_seconds_local(refresh::DEBOUNCE_SECONDS, enclose! { (rt) move || {
+            comp.pending_refresh.replace(None);
+
+            // the canteens in flight are fresh enough
+            if comp.loading.get() {
+                log::debug!("canteens are already loading, skipping refresh");
+            } else {
+                log::debug!("refreshing CanteenComponents");
+                comp.load(&rt, None);
+            }
+
+            glib::Continue(false)
+        }});
+        self.pending_refresh.replace(Some(source_id));
+    }
+
+    // canteens of other sources than the OvGU are named after they got fetched
+    fn update_canteen_title(&self, canteen: &Canteen) {
+        let name = match &canteen.name {
+            Some(name) => name,
+            None => return,
+        };
+
+        if let Some((_desc, btn)) = self.canteen_buttons.borrow().iter().find(|(desc, _btn)| desc == &canteen.description) {
+            btn.set_property_text(Some(name.as_str()));
+        }
+
+        let visible = self.canteens_stack
+            .get_visible_child_name()
+            .map_or(false, |visible_name| visible_name.as_str() == canteen.description.id());
+        if visible {
+            self.canteen_label.set_text(name);
+        }
+    }
+
+    pub const fn window(&self) -> &Window {
         &self.window
     }
 
-    pub fn settings(&self) -> &Settings {
+    pub const fn settings(&self) -> &Settings {
         &self.settings
     }
 
-    #[cfg(feature = "test-with-local-files")]
-    async fn load_canteen(cached_canteen: Option<Canteen>, canteen_desc: CanteenDescription) -> Result<Canteen> {
-        log::debug!("loading canteen {:?} from local file", canteen_desc);
-        let file = File::open("data/canteens.json").context("'data/canteens.json' not found!")?;
-        let mut canteens: Vec<Canteen> =
-            serde_json::from_reader(&file).context("Could not parse 'data/cateens.json'")?;
-        let canteen = canteens
-            .drain(..)
-            .find(|c| c.description == canteen_desc)
-            .context("Canteen not found!")?;
-
-        if let Some(mut cached_canteen) = cached_canteen {
-            failure::ResultExt::compat(cached_canteen.merge(canteen))
-                .context("Failed to update canteen")?;
-            Ok(cached_canteen)
-        } else {
-            Ok(canteen)
+    pub const fn favorites(&self) -> &Favorites {
+        &self.favorites
+    }
+
+    pub const fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    pub const fn ratings(&self) -> &Ratings {
+        &self.ratings
+    }
+
+    fn update_upcoming_favorites(&self) {
+        self.upcoming_favorites.load(&self.canteens.borrow(), &self.favorites.get());
+    }
+
+    fn update_dashboard(&self) {
+        self.dashboard.load(&self.canteens.borrow(), &self.canteen_titles());
+    }
+
+    fn update_search(&self) {
+        let query = self.search_entry.get_text().map(|text| text.trim().to_owned()).unwrap_or_default();
+        let showing_results = self.window_stack.get_visible_child_name().map_or(false, |name| name.as_str() == "search");
+
+        if query.is_empty() {
+            if showing_results {
+                self.window_stack.set_visible_child_name("canteens-stack");
+            }
+            return;
+        }
+
+        self.search.load(&self.canteens.borrow(), &query);
+        if !showing_results {
+            self.window_stack.set_visible_child_name("search");
+        }
+    }
+
+    // makes the canteen visible and scrolls to the given day
+    pub fn show_day(&self, description: CanteenDescription, date: NaiveDate) {
+        log::debug!("showing day {} of canteen {:?}", date, description);
+
+        if let Some(app) = self.window.get_application() {
+            app.activate_action(
+                "canteen-selected",
+                Some(&description.id().to_variant()),
+            );
+        }
+
+        self.pending_day.replace(Some((description, date)));
+
+        let c = glib::MainContext::default();
+        let comp = self.clone();
+        c.spawn_local(async move {
+            glib_yield!(); // give gtk a chance to allocate the visible canteen
+            comp.scroll_to_pending_day();
+        });
+    }
+
+    fn scroll_to_pending_day(&self) {
+        // the canteen components are borrowed while loading and a component is taken out of the map
+        // while rendering, the pending day is scrolled to after loading or rendering finished then
+        if let Ok(canteen_components) = self.canteen_components.try_borrow() {
+            let mut pending_day = self.pending_day.borrow_mut();
+            if let Some((description, date)) = pending_day.clone() {
+                if let Some(comp) = canteen_components.get(&description) {
+                    pending_day.take();
+                    if !comp.scroll_to_day(date) {
+                        log::info!("no menu for {} available in canteen {:?}", date, description);
+                    }
+                }
+            }
+        }
+    }
+
+    fn visible_canteen(&self) -> Option<CanteenDescription> {
+        self.canteens_stack
+            .get_visible_child_name()
+            .map(|name| CanteenDescription::new(name.as_str()))
+    }
+
+    // the component is taken out of the map while rendering, so the map is not borrowed across the await
+    async fn render_pending_canteen(&self, canteen_desc: &CanteenDescription) {
+        let canteen_comp = self.canteen_components.borrow_mut().remove(canteen_desc);
+        if let Some(mut canteen_comp) = canteen_comp {
+            canteen_comp.render_pending().await;
+            self.canteen_components.borrow_mut().insert(canteen_desc.clone(), canteen_comp);
+
+            let day_pending = self
+                .pending_day
+                .borrow()
+                .as_ref()
+                .map_or(false, |(description, _date)| description == canteen_desc);
+            if day_pending {
+                glib_yield!(); // give gtk a chance to allocate the rendered days
+                self.scroll_to_pending_day();
+            }
+        }
+    }
+
+    async fn render_visible_canteen(&self) {
+        if let Some(visible_canteen) = self.visible_canteen() {
+            self.render_pending_canteen(&visible_canteen).await;
+        }
+    }
+
+    // renders the deferred days of the canteens whenever the app is idle, the visible
+    // canteen goes first and the others follow in the order of the canteen menu
+    async fn render_pending_canteens(&self) {
+        loop {
+            glib::timeout_future_with_priority(glib::PRIORITY_LOW, 0).await;
+
+            let canteen_components = self.canteen_components.borrow();
+            let is_pending = |desc: &CanteenDescription| canteen_components.get(desc).map_or(false, CanteenComponent::is_pending);
+            let next_canteen = self
+                .visible_canteen()
+                .filter(|desc| is_pending(desc))
+                .or_else(|| {
+                    self.canteen_buttons
+                        .borrow()
+                        .iter()
+                        .map(|(desc, _btn)| desc)
+                        .find(|desc| is_pending(*desc))
+                        .cloned()
+                });
+            drop(canteen_components);
+
+            match next_canteen {
+                Some(canteen_desc) => self.render_pending_canteen(&canteen_desc).await,
+                None => break,
+            }
         }
+
+        log::debug!("finish rendering deferred canteens");
     }
 
-    #[cfg(not(feature = "test-with-local-files"))]
-    async fn load_canteen(cached_canteen: Option<Canteen>, canteen_desc: CanteenDescription) -> Result<Canteen> {
-        log::debug!("loading canteen {:?}", canteen_desc);
-        if let Some(mut canteen) = cached_canteen {
-            failure::ResultExt::compat(canteen.update().await)
-                .context("Failed to update canteen")?;
-            Ok(canteen)
-        } else {
-            failure::ResultExt::compat(Canteen::new(canteen_desc.clone()).await)
-                .context("Failed to fetch canteen")
+    fn update_calendar_feed(&self, rt: &Handle) {
+        if let Some(feed) = calendar::render_feed(&self.settings, &self.favorites, &self.canteens.borrow()) {
+            rt.spawn(async move {
+                calendar::write_feed(&feed);
+            });
         }
     }
 
@@ -289,7 +931,13 @@ impl WindowComponent {
         log::debug!("loading canteens into WindowComponent");
 
         self.reload_button.set_sensitive(false);
-        self.window_stack.set_visible_child_name("canteens-stack");
+        // loading runs in the background as well, so the page the user is on stays visible,
+        // only an error of the previous load is replaced, the initial page is set on startup
+        let showing_error = self.window_stack.get_visible_child_name().map_or(false, |name| name.as_str() == "window-error");
+        if showing_error {
+            self.window_stack.set_visible_child_name("canteens-stack");
+        }
+        self.loading.set(true);
 
         let menu_history_length = self.settings.get_uint64("menu-history-length");
         let history_duration = Duration::days(menu_history_length as i64);
@@ -301,51 +949,26 @@ impl WindowComponent {
         // in an order which is later sequentially inserted into the GUI.
         let (tx, mut rx) = channel(self.canteen_components.borrow().len());
 
-        rt.spawn(async move {
-            log::debug!("loading canteens from cache");
-            let mut canteen_cache = HashMap::new();
-
-            // loop will only run once, used to abort early with break as ? is not available in scopes
-            for xdg in xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk") {
-                log::debug!("found cache directory for application");
-                let history_path = match xdg.find_cache_file("history.json") {
-                    Some(path) => path,
-                    // if no cache is available, just skip
-                    None => {
-                        log::debug!("no history cache available for application");
-                        break;
-                    },
-                };
-                log::debug!("found history cache in {:?}", history_path);
-
-                // this cannot fail, as xdg.find_cache_file makes sure the file exists
-                let history_file = File::open(history_path).unwrap();
-                let history: Vec<Canteen> = match serde_json::from_reader(history_file) {
-                    Ok(history) => history,
-                    // if parsing the cache fails, just skip
-                    Err(e) => {
-                        log::warn!("failed to parse cache: {:#}", e);
-                        break;
-                    },
-                };
-
-                for canteen in history {
-                    canteen_cache.insert(canteen.description.clone(), canteen);
-                }
-            }
+        // only canteens with a component can be loaded, the sources are
+        // created again to respect changes of their settings
+        let sources = source::from_settings(Some(&self.settings));
+        let canteen_descs = self.canteen_buttons
+            .borrow()
+            .iter()
+            .map(|(canteen_desc, _btn)| canteen_desc.clone())
+            .collect::<Vec<_>>();
 
-            log::debug!("finish loading cache");
+        rt.spawn(async move {
+            let mut canteen_cache = cache::read_history();
 
-            future::join_all(CANTEENS.iter().map(|canteen_desc| {
+            future::join_all(canteen_descs.iter().map(|canteen_desc| {
                 let cached_canteen = canteen_cache.remove(canteen_desc);
+                let sources = &sources;
                 enclose! { (mut tx) async move {
-                    let canteen_result = Self::load_canteen(cached_canteen, canteen_desc.clone()).await
-                        .map(|mut canteen| {
-                            canteen.days = canteen.days.drain(..)
-                                // remove old menus
-                                .filter(|day| day.date >= history_oldest)
-                                .collect();
-                            canteen
+                    let canteen_result = fetch::load_canteen_or_cached(sources, cached_canteen, canteen_desc.clone()).await
+                        .map(|loaded| fetch::Loaded {
+                            canteen: fetch::prune_history(loaded.canteen, history_oldest),
+                            offline: loaded.offline,
                         });
 
                     log::debug!("sending filtered canteen {:?} to UI", canteen_desc);
@@ -360,18 +983,21 @@ impl WindowComponent {
         let fetch_canteen_components = Rc::clone(&self.canteen_components);
         let window_stack = SendWrapper::new(self.window_stack.clone());
         let window_error_label = SendWrapper::new(self.window_error_label.clone());
+        let comp = self.clone();
         c.spawn_local(enclose! { (rt) async move {
             // fetching parallel loaded canteens here and inserting
             // one canteen after another into the GUI.
-            // TODO: render currently visible canteen first
+            // only the visible canteen is rendered right away, the days of the
+            // other canteens are rendered after all canteens got downloaded
             let mut canteen_cache = Vec::new();
 
             log::debug!("waiting for canteens to be downloaded...");
             while let Some((canteen_desc, canteen_result)) = rx.recv().await {
                 log::debug!("canteen {:?} got downloaded", canteen_desc);
-                if let Some(comp) = fetch_canteen_components.borrow_mut().get_mut(&canteen_desc) {
+                let visible = comp.visible_canteen().as_ref() == Some(&canteen_desc);
+                if let Some(canteen_comp) = fetch_canteen_components.borrow_mut().get_mut(&canteen_desc) {
                     log::debug!("loading canteen {:?} into CanteenComponent", canteen_desc);
-                    if let Some(canteen) = comp.load(canteen_result).await {
+                    if let Some(canteen) = canteen_comp.load(canteen_result, visible).await {
                         canteen_cache.push(canteen);
                     }
                 } else {
@@ -383,41 +1009,63 @@ impl WindowComponent {
                         canteen_desc,
                     ));
                 }
+
+                // the user might have switched to a canteen which got downloaded already
+                comp.render_visible_canteen().await;
             }
 
             log::debug!("finish loading canteens");
 
-            fetch_reload_button.set_sensitive(true);
+            for canteen in &canteen_cache {
+                comp.update_canteen_title(canteen);
+            }
+
+            comp.canteens.replace(canteen_cache.clone());
+            comp.update_upcoming_favorites();
+            comp.update_dashboard();
+            if comp.search_bar.get_search_mode() {
+                comp.update_search();
+            }
+
+            if let Some(app) = comp.window.get_application() {
+                notifications::notify_watched_meals(
+                    &app,
+                    &comp.settings,
+                    &comp.favorites,
+                    &comp.notified,
+                    &canteen_cache,
+                );
+            }
 
             if let Some(loaded) = loaded {
                 log::debug!("notifying canteens loaded");
                 loaded.notify();
             }
 
+            comp.update_calendar_feed(&rt);
+
+            comp.render_pending_canteens().await;
+
+            glib_yield!(); // give gtk a chance to allocate the loaded days
+            comp.scroll_to_pending_day();
+
+            let archive_enabled = comp.settings.get_boolean("archive");
             rt.spawn(async move {
-                log::debug!("write loaded canteens into history cache");
-                // loop will only run once, used to abort early with break as ? is not available in scopes
-                for xdg in xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk") {
-                    log::debug!("found cache directory for application");
-                    let history_path = match xdg.place_cache_file("history.json") {
-                        Ok(path) => path,
-                        // if no cache is available, just skip
-                        Err(e) => {
-                            log::warn!("could not place history cache: {:#}", e);
-                            break;
-                        },
-                    };
-                    log::debug!("writing history cache to {:?}", history_path);
-
-                    // this cannot fail, as xdg.find_cache_file makes sure the file exists
-                    let history_file = File::create(history_path).unwrap();
-                    if let Err(e) = serde_json::to_writer_pretty(history_file, &canteen_cache) {
-                        log::warn!("failed to write history cache: {:#}", e);
-                    };
-
-                    log::debug!("finish writing history cache");
+                cache::write_history(&canteen_cache);
+                if archive_enabled {
+                    archive::append(&canteen_cache);
                 }
             });
+
+            // reloading is possible again after the deferred canteens got rendered
+            fetch_reload_button.set_sensitive(true);
+
+            // the enabled canteens and the meal order might have changed while loading
+            comp.loading.set(false);
+            comp.update_canteens(&rt);
+            if comp.meal_order_outdated.replace(false) {
+                comp.update_meal_order(&rt);
+            }
         }});
     }
 }
@@ -0,0 +1,499 @@
+use std::collections::BTreeSet;
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate, Utc};
+use gettextrs::gettext as t;
+use itertools::Itertools;
+
+use crate::canteen;
+use crate::components::day::translate_weekday;
+use crate::components::meal::{translate_additive, translate_allergenic, translate_symbol};
+use crate::favorites;
+use crate::filter::MealFilter;
+use crate::model::{Canteen, CanteenDescription, Meal};
+use crate::price::PriceGroup;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Markdown,
+    ICalendar,
+}
+
+// names of the formats as used on the command-line
+pub const FORMATS: &[(&str, Format)] = &[
+    ("json", Format::Json),
+    ("csv", Format::Csv),
+    ("markdown", Format::Markdown),
+    ("ics", Format::ICalendar),
+];
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        FORMATS
+            .iter()
+            .find(|(format_name, _format)| *format_name == name)
+            .map(|(_name, format)| *format)
+    }
+
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+            Self::Markdown => "md",
+            Self::ICalendar => "ics",
+        }
+    }
+
+    pub fn translate(self) -> String {
+        match self {
+            Self::Json => t("JSON"),
+            Self::Csv => t("CSV (one row per meal)"),
+            Self::Markdown => t("Markdown (weekly table)"),
+            Self::ICalendar => t("iCalendar (one event per day)"),
+        }
+    }
+}
+
+// restricts which canteens, days and meals get exported, None means no restriction
+#[derive(Debug, Default, Clone)]
+pub struct Selection {
+    pub canteens: Option<Vec<CanteenDescription>>,
+    pub from: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    // only meals passing the filter are exported
+    pub meal_filter: Option<MealFilter>,
+    // only the favorite meals are exported
+    pub favorites: Option<BTreeSet<String>>,
+}
+
+impl Selection {
+    fn contains_canteen(&self, description: &CanteenDescription) -> bool {
+        self.canteens
+            .as_ref()
+            .map_or(true, |canteens| canteens.contains(description))
+    }
+
+    fn contains_date(&self, date: NaiveDate) -> bool {
+        self.from.map_or(true, |from| date >= from) && self.until.map_or(true, |until| date <= until)
+    }
+
+    fn contains_meal(&self, meal: &Meal) -> bool {
+        let passes_filter = self
+            .meal_filter
+            .as_ref()
+            .map_or(true, |meal_filter| meal_filter.check(meal).is_empty());
+        let is_favorite = self
+            .favorites
+            .as_ref()
+            .map_or(true, |favorites| favorites.contains(&favorites::normalize(&meal.name)));
+
+        passes_filter && is_favorite
+    }
+
+    pub fn apply(&self, canteens: &[Canteen]) -> Vec<Canteen> {
+        canteens
+            .iter()
+            .filter(|canteen| self.contains_canteen(&canteen.description))
+            .map(|canteen| {
+                let mut canteen = canteen.clone();
+                canteen.days.retain(|day| self.contains_date(day.date));
+                for day in &mut canteen.days {
+                    day.meals.retain(|meal| self.contains_meal(meal));
+                }
+                canteen
+            })
+            .collect()
+    }
+}
+
+fn format_price<P: std::fmt::Display>(price: P) -> String {
+    format!("{:.2} €", price)
+}
+
+fn translate_symbols(meal: &Meal) -> String {
+    meal.symbols.iter().map(translate_symbol).join(", ")
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+pub fn csv_row<I: IntoIterator<Item = String>>(fields: I) -> String {
+    format!("{}\n", fields.into_iter().map(|field| csv_field(&field)).join(","))
+}
+
+fn export_csv(canteens: &[Canteen], price_group: PriceGroup) -> String {
+    let mut header = vec![t("Canteen"), t("Date"), t("Meal")];
+    header.extend(price_group.shown().iter().map(|group| group.translate()));
+    header.extend(vec![t("Symbols"), t("Additives"), t("Allergenics")]);
+    let mut csv = csv_row(header);
+
+    for canteen in canteens {
+        for day in &canteen.days {
+            for meal in &day.meals {
+                let mut row = vec![canteen::name(canteen), format!("{}", day.date), meal.name.clone()];
+                row.extend(
+                    price_group
+                        .prices(&meal.price)
+                        .into_iter()
+                        .map(|(_group, price)| format!("{:.2}", price)),
+                );
+                row.extend(vec![
+                    translate_symbols(meal),
+                    meal.additives.iter().map(translate_additive).join(", "),
+                    meal.allergenics.iter().map(translate_allergenic).join(", "),
+                ]);
+                csv += &csv_row(row);
+            }
+        }
+    }
+
+    csv
+}
+
+fn markdown_cell(cell: &str) -> String {
+    cell.replace('|', "\\|")
+}
+
+fn export_markdown(canteens: &[Canteen], price_group: PriceGroup) -> String {
+    let mut markdown = String::new();
+    let price_columns = price_group.shown();
+
+    for canteen in canteens.iter().filter(|canteen| !canteen.days.is_empty()) {
+        markdown += &format!("## {}\n\n", canteen::name(canteen));
+
+        let weeks = canteen.days.iter().group_by(|day| day.date.iso_week());
+        for (week, days) in &weeks {
+            let days = days.collect::<Vec<_>>();
+            markdown += &format!(
+                "### {} {} ({} – {})\n\n",
+                t("Week"),
+                week.week(),
+                days[0].date.format("%d.%m.%Y"),
+                days[days.len() - 1].date.format("%d.%m.%Y"),
+            );
+            markdown += &format!(
+                "| {} | {} | {} | {} |\n|---|---|{}---|\n",
+                t("Day"),
+                t("Meal"),
+                price_columns.iter().map(|group| group.translate()).join(" | "),
+                t("Symbols"),
+                "--:|".repeat(price_columns.len()),
+            );
+
+            for day in days {
+                let day_name = translate_weekday(day.date.weekday());
+                if day.meals.is_empty() {
+                    markdown += &format!(
+                        "| **{}** | {} |{} |\n",
+                        day_name,
+                        t("No meals available"),
+                        " |".repeat(price_columns.len()),
+                    );
+                }
+
+                // the day is only named in its first row to keep the table readable
+                for (idx, meal) in day.meals.iter().enumerate() {
+                    markdown += &format!(
+                        "| {} | {} | {} | {} |\n",
+                        if idx == 0 { format!("**{}**", day_name) } else { String::new() },
+                        markdown_cell(&meal.name),
+                        price_group
+                            .prices(&meal.price)
+                            .into_iter()
+                            .map(|(_group, price)| format_price(price))
+                            .join(" | "),
+                        markdown_cell(&translate_symbols(meal)),
+                    );
+                }
+            }
+
+            markdown += "\n";
+        }
+    }
+
+    markdown
+}
+
+// escapes TEXT values as described in RFC 5545 section 3.3.11
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// content lines must not be longer than 75 octets, so longer lines are folded
+// by inserting a line break followed by a space (RFC 5545 section 3.1)
+fn ics_line(ics: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > 75 {
+            *ics += "\r\n ";
+            octets = 1;
+        }
+        ics.push(c);
+        octets += c.len_utf8();
+    }
+    *ics += "\r\n";
+}
+
+fn export_icalendar(canteens: &[Canteen], price_group: PriceGroup) -> String {
+    let mut ics = String::new();
+    let timestamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+
+    ics_line(&mut ics, "BEGIN:VCALENDAR");
+    ics_line(&mut ics, "VERSION:2.0");
+    ics_line(&mut ics, "PRODID:-//fin-ger//ovgu-canteen-gtk//EN");
+    ics_line(&mut ics, "CALSCALE:GREGORIAN");
+    ics_line(&mut ics, &format!(
+        "X-WR-CALNAME:{}",
+        ics_text(&canteens.iter().map(canteen::name).join(", ")),
+    ));
+
+    for canteen in canteens {
+        let canteen_name = canteen::name(canteen);
+
+        // days without (matching) meals would only clutter the calendar
+        for day in canteen.days.iter().filter(|day| !day.meals.is_empty()) {
+            let description = day
+                .meals
+                .iter()
+                .map(|meal| format!(
+                    "{} – {}",
+                    meal.name,
+                    price_group
+                        .prices(&meal.price)
+                        .into_iter()
+                        .map(|(_group, price)| format_price(price))
+                        .join(" / "),
+                ))
+                .join("\n");
+
+            ics_line(&mut ics, "BEGIN:VEVENT");
+            ics_line(&mut ics, &format!(
+                "UID:{}-{}@ovgu-canteen-gtk",
+                day.date.format("%Y%m%d"),
+                canteen.description.id(),
+            ));
+            ics_line(&mut ics, &format!("DTSTAMP:{}", timestamp));
+            ics_line(&mut ics, &format!("DTSTART;VALUE=DATE:{}", day.date.format("%Y%m%d")));
+            ics_line(&mut ics, &format!("DTEND;VALUE=DATE:{}", day.date.succ().format("%Y%m%d")));
+            ics_line(&mut ics, &format!("SUMMARY:{}", ics_text(&format!("{} – {}", t("Menu"), canteen_name))));
+            ics_line(&mut ics, &format!("LOCATION:{}", ics_text(&canteen_name)));
+            ics_line(&mut ics, &format!("DESCRIPTION:{}", ics_text(&description)));
+            ics_line(&mut ics, "TRANSP:TRANSPARENT");
+            ics_line(&mut ics, "END:VEVENT");
+        }
+    }
+
+    ics_line(&mut ics, "END:VCALENDAR");
+
+    ics
+}
+
+// the JSON export has the structure of the cached canteens, but the prices
+// of a meal only contain the groups shown for the price group
+fn export_json(canteens: &[Canteen], price_group: PriceGroup) -> Result<String> {
+    let mut json = serde_json::to_value(canteens).context("Failed to serialize canteens")?;
+
+    for (canteen, json_canteen) in canteens.iter().zip(json.as_array_mut().into_iter().flatten()) {
+        for (day, json_day) in canteen.days.iter().zip(json_canteen["days"].as_array_mut().into_iter().flatten()) {
+            for (meal, json_meal) in day.meals.iter().zip(json_day["meals"].as_array_mut().into_iter().flatten()) {
+                let prices = price_group
+                    .prices(&meal.price)
+                    .into_iter()
+                    .map(|(group, price)| (group.name().to_owned(), serde_json::Value::from(price)))
+                    .collect::<serde_json::Map<_, _>>();
+                json_meal["price"] = prices.into();
+            }
+        }
+    }
+
+    serde_json::to_string_pretty(&json).context("Failed to serialize canteens")
+}
+
+// the price group restricts the prices of all formats
+pub fn export(canteens: &[Canteen], format: Format, price_group: PriceGroup) -> Result<String> {
+    log::debug!("exporting {} canteens as {:?} with {:?} prices", canteens.len(), format, price_group);
+
+    match format {
+        Format::Json => export_json(canteens, price_group),
+        Format::Csv => Ok(export_csv(canteens, price_group)),
+        Format::Markdown => Ok(export_markdown(canteens, price_group)),
+        Format::ICalendar => Ok(export_icalendar(canteens, price_group)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Day, Price};
+
+    // staff pay one euro and guests two euros more than students
+    fn meal(name: &str, student: f32) -> Meal {
+        Meal {
+            name: name.to_owned(),
+            price: Price {
+                student,
+                staff: student + 1.0,
+                guest: student + 2.0,
+            },
+            symbols: Vec::new(),
+            additives: Vec::new(),
+            allergenics: Vec::new(),
+        }
+    }
+
+    fn day(day: u32, meals: Vec<Meal>) -> Day {
+        Day {
+            date: NaiveDate::from_ymd(2020, 5, day),
+            meals,
+            side_dishes: Vec::new(),
+        }
+    }
+
+    fn canteens() -> Vec<Canteen> {
+        let lower_hall = Canteen {
+            description: CanteenDescription::new("UniCampusLowerHall"),
+            name: Some("Lower Hall".to_owned()),
+            source: None,
+            fetched: None,
+            days: vec![
+                day(4, vec![meal("Pasta, with sauce", 2.0), meal("Schnitzel", 3.0)]),
+                day(5, vec![meal("Soup", 1.0)]),
+                day(6, Vec::new()),
+            ],
+        };
+        let herrenkrug = Canteen {
+            description: CanteenDescription::new("Herrenkrug"),
+            name: Some("Herrenkrug".to_owned()),
+            source: None,
+            fetched: None,
+            days: vec![day(4, vec![meal("Pasta", 2.5)])],
+        };
+
+        vec![lower_hall, herrenkrug]
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        for (name, format) in FORMATS {
+            assert_eq!(Format::from_name(name), Some(*format));
+        }
+        assert_eq!(Format::from_name("xlsx"), None);
+        assert_eq!(Format::Markdown.extension(), "md");
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("Pasta"), "Pasta");
+        assert_eq!(csv_field("Pasta, with sauce"), "\"Pasta, with sauce\"");
+        assert_eq!(csv_field("\"Spicy\" curry"), "\"\"\"Spicy\"\" curry\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_row(vec!["a".to_owned(), "b,c".to_owned(), String::new()]), "a,\"b,c\",\n");
+    }
+
+    #[test]
+    fn csv_contains_a_row_per_meal() {
+        let csv = export_csv(&canteens(), PriceGroup::Staff);
+        let rows = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(rows.len(), 5);
+        assert!(rows[1].starts_with("Lower Hall,2020-05-04,\"Pasta, with sauce\",3.00,"));
+        assert!(rows[4].starts_with("Herrenkrug,2020-05-04,Pasta,3.50,"));
+    }
+
+    #[test]
+    fn markdown_has_a_table_per_week() {
+        let markdown = export_markdown(&canteens(), PriceGroup::Guest);
+
+        assert_eq!(markdown.matches("## Lower Hall\n").count(), 1);
+        assert_eq!(markdown.matches("### Week 19 ").count(), 2);
+        assert!(markdown.contains("| Pasta, with sauce | 4.00 € |"));
+        assert_eq!(markdown_cell("a|b"), "a\\|b");
+    }
+
+    #[test]
+    fn selection_restricts_canteens_days_and_meals() {
+        let selection = Selection {
+            canteens: Some(vec![CanteenDescription::new("UniCampusLowerHall")]),
+            from: Some(NaiveDate::from_ymd(2020, 5, 4)),
+            until: Some(NaiveDate::from_ymd(2020, 5, 5)),
+            meal_filter: None,
+            favorites: Some(vec!["pasta, with sauce".to_owned(), "soup".to_owned()].into_iter().collect()),
+        };
+        let selected = selection.apply(&canteens());
+
+        assert_eq!(selected.len(), 1);
+        let days = &selected[0].days;
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].meals.len(), 1);
+        assert_eq!(days[0].meals[0].name, "Pasta, with sauce");
+        assert_eq!(days[1].meals.len(), 1);
+
+        // an empty selection exports everything
+        let selected = Selection::default().apply(&canteens());
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[0].days.len(), 3);
+    }
+
+    #[test]
+    fn ics_text_is_escaped() {
+        assert_eq!(ics_text("a\\b;c,d\ne"), "a\\\\b\\;c\\,d\\ne");
+    }
+
+    #[test]
+    fn ics_lines_are_folded() {
+        let mut ics = String::new();
+        ics_line(&mut ics, "SUMMARY:short");
+        assert_eq!(ics, "SUMMARY:short\r\n");
+
+        let mut ics = String::new();
+        let line = format!("DESCRIPTION:{}", "ä".repeat(60));
+        ics_line(&mut ics, &line);
+        let lines = ics.split("\r\n").collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.len() <= 75));
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines[2], "");
+        assert_eq!(ics.replace("\r\n ", ""), format!("{}\r\n", line));
+    }
+
+    #[test]
+    fn icalendar_skips_days_without_meals() {
+        let ics = export_icalendar(&canteens(), PriceGroup::All);
+
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 3);
+        assert!(ics.contains("UID:20200504-UniCampusLowerHall@ovgu-canteen-gtk\r\n"));
+        assert!(!ics.contains("DTSTART;VALUE=DATE:20200506"));
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(Format::from_name("ics").map(Format::extension), Some("ics"));
+    }
+
+    #[test]
+    fn json_prices_are_restricted_to_the_price_group() {
+        // the price of the first meal of the canteen
+        let price = |price_group, canteen_idx: usize| {
+            let json = export_json(&canteens(), price_group).unwrap();
+            let canteens = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+            canteens[canteen_idx]["days"][0]["meals"][0]["price"].clone()
+        };
+
+        let staff = price(PriceGroup::Staff, 0);
+        assert_eq!(staff.as_object().map(serde_json::Map::len), Some(1));
+        assert_eq!(staff["staff"].as_f64(), Some(3.0));
+
+        let all = price(PriceGroup::All, 1);
+        assert_eq!(all.as_object().map(serde_json::Map::len), Some(3));
+        assert_eq!(all["guest"].as_f64(), Some(4.5));
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::BTreeSet;
+
+use crate::store::Store;
+
+// favorite meals are stored by their normalized name
+pub type Favorites = Store<BTreeSet<String>>;
+
+pub fn load() -> Favorites {
+    Store::load("favorites.json")
+}
+
+// meal names differ in casing and whitespace between days and canteens,
+// so they are normalized before being compared
+pub fn normalize(meal_name: &str) -> String {
+    meal_name
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub fn is_favorite(favorites: &Favorites, meal_name: &str) -> bool {
+    favorites.get().contains(&normalize(meal_name))
+}
+
+pub fn set_favorite(favorites: &Favorites, meal_name: &str, favorite: bool) {
+    let name = normalize(meal_name);
+    log::debug!("setting favorite state of {} to {}", name, favorite);
+
+    favorites.update(|favorites| {
+        if favorite {
+            favorites.insert(name)
+        } else {
+            favorites.remove(&name)
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn favorites_are_found_by_normalized_name() {
+        assert_eq!(normalize("  Pasta \t with\nSauce "), "pasta with sauce");
+
+        let favorites = Store::with_data(vec![normalize("Pasta with Sauce")].into_iter().collect());
+        assert!(is_favorite(&favorites, "PASTA  with sauce"));
+        assert!(!is_favorite(&favorites, "Pasta"));
+    }
+}
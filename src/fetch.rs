@@ -0,0 +1,69 @@
+use anyhow::{Context, Error, Result};
+use chrono::{NaiveDate, Utc};
+
+use crate::model::{Canteen, CanteenDescription};
+use crate::source::MenuSource;
+
+// fetches the canteen from the source providing it and merges it into the cached canteen
+pub async fn load_canteen(
+    sources: &[Box<dyn MenuSource>],
+    cached_canteen: Option<Canteen>,
+    canteen_desc: CanteenDescription,
+) -> Result<Canteen> {
+    let source = sources
+        .iter()
+        .find(|source| source.canteens().contains(&canteen_desc))
+        .with_context(|| format!("No menu source provides canteen {}", canteen_desc.id()))?;
+    let mut canteen = source.fetch(&canteen_desc).await?;
+    canteen.source = Some(source.name().to_owned());
+    canteen.fetched = Some(Utc::now());
+
+    match cached_canteen {
+        Some(mut cached_canteen) => {
+            cached_canteen.merge(canteen);
+            Ok(cached_canteen)
+        },
+        None => Ok(canteen),
+    }
+}
+
+// a loaded canteen, which is taken from the cache if fetching it failed
+#[derive(Debug)]
+pub struct Loaded {
+    pub canteen: Canteen,
+    // the reason why the cached canteen is used, e.g. no network connection
+    pub offline: Option<Error>,
+}
+
+// like load_canteen, but falls back to the cached canteen instead of failing
+pub async fn load_canteen_or_cached(
+    sources: &[Box<dyn MenuSource>],
+    cached_canteen: Option<Canteen>,
+    canteen_desc: CanteenDescription,
+) -> Result<Loaded> {
+    match load_canteen(sources, cached_canteen.clone(), canteen_desc).await {
+        Ok(canteen) => Ok(Loaded {
+            canteen,
+            offline: None,
+        }),
+        Err(e) => match cached_canteen {
+            Some(canteen) => {
+                log::warn!("using cached canteen {:?}: {:#}", canteen.description, e);
+                Ok(Loaded {
+                    canteen,
+                    offline: Some(e),
+                })
+            },
+            None => Err(e),
+        },
+    }
+}
+
+// removes all menus older than history_oldest from the canteen
+pub fn prune_history(mut canteen: Canteen, history_oldest: NaiveDate) -> Canteen {
+    canteen.days = canteen.days.drain(..)
+        // remove old menus
+        .filter(|day| day.date >= history_oldest)
+        .collect();
+    canteen
+}
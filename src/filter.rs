@@ -0,0 +1,299 @@
+use gio::prelude::*;
+use gio::Settings;
+use gettextrs::gettext as t;
+
+use crate::components::meal::{allergenic_name, symbol_name, translate_allergenic, translate_symbol};
+use crate::model::Meal;
+
+// all settings keys which have an influence on the result of a MealFilter
+pub const FILTER_KEYS: &[&str] = &[
+    "avoided-allergenics",
+    "excluded-meals-mode",
+    "diet-profile",
+    "custom-diet-excluded-symbols",
+];
+
+// symbols of meals containing meat
+const MEAT_SYMBOLS: &[&str] = &["Pig", "Cattle", "Poultry", "Game", "Lamb"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusionMode {
+    Hide,
+    Dim,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DietProfile {
+    None,
+    Vegan,
+    Vegetarian,
+    Pescetarian,
+    Halal,
+    Custom,
+}
+
+// all available diet profiles with their settings value
+pub const DIET_PROFILES: &[(&str, DietProfile)] = &[
+    ("none", DietProfile::None),
+    ("vegan", DietProfile::Vegan),
+    ("vegetarian", DietProfile::Vegetarian),
+    ("pescetarian", DietProfile::Pescetarian),
+    ("halal", DietProfile::Halal),
+    ("custom", DietProfile::Custom),
+];
+
+impl DietProfile {
+    pub fn from_name(name: &str) -> Self {
+        DIET_PROFILES
+            .iter()
+            .find(|(profile_name, _profile)| *profile_name == name)
+            .map_or(Self::None, |(_profile_name, profile)| *profile)
+    }
+
+    pub fn translate(self) -> String {
+        match self {
+            Self::None => t("No diet"),
+            Self::Vegan => t("Vegan"),
+            Self::Vegetarian => t("Vegetarian"),
+            Self::Pescetarian => t("Pescetarian"),
+            Self::Halal => t("No pork or alcohol"),
+            Self::Custom => t("Custom"),
+        }
+    }
+
+    // a meal needs at least one of these symbols to match the profile,
+    // an empty list means no symbol is required
+    const fn required_symbols(self) -> &'static [&'static str] {
+        match self {
+            Self::Vegan => &["Vegan"],
+            Self::Vegetarian => &["Vegan", "Vegetarian"],
+            Self::Pescetarian => &["Vegan", "Vegetarian", "Fish"],
+            Self::None | Self::Halal | Self::Custom => &[],
+        }
+    }
+
+    // a meal must not have any of these symbols to match the profile
+    fn forbidden_symbols(self, custom: &[String]) -> Vec<String> {
+        let symbols: &[&str] = match self {
+            Self::Pescetarian => MEAT_SYMBOLS,
+            Self::Halal => &["Pig", "Alcohol"],
+            Self::Custom => return custom.to_vec(),
+            Self::None | Self::Vegan | Self::Vegetarian => &[],
+        };
+
+        symbols.iter().map(ToString::to_string).collect()
+    }
+}
+
+// decides which meals should be excluded from the menu according to the user's preferences
+#[derive(Debug, Clone)]
+pub struct MealFilter {
+    avoided_allergenics: Vec<String>,
+    diet_profile: DietProfile,
+    custom_excluded_symbols: Vec<String>,
+    mode: ExclusionMode,
+}
+
+impl MealFilter {
+    pub fn from_settings(settings: &Settings) -> Self {
+        log::debug!("creating MealFilter from settings");
+
+        let avoided_allergenics = settings
+            .get_strv("avoided-allergenics")
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let diet_profile = settings
+            .get_string("diet-profile")
+            .map_or(DietProfile::None, |name| DietProfile::from_name(&name));
+        let custom_excluded_symbols = settings
+            .get_strv("custom-diet-excluded-symbols")
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let mode = match settings.get_string("excluded-meals-mode").as_deref() {
+            Some("hide") => ExclusionMode::Hide,
+            _ => ExclusionMode::Dim,
+        };
+
+        Self {
+            avoided_allergenics,
+            diet_profile,
+            custom_excluded_symbols,
+            mode,
+        }
+    }
+
+    pub const fn mode(&self) -> ExclusionMode {
+        self.mode
+    }
+
+    // returns a translated explanation for each reason the meal is excluded,
+    // the meal passes the filter if no reasons are returned
+    pub fn check(&self, meal: &Meal) -> Vec<String> {
+        let mut reasons = Vec::new();
+
+        let allergenics = meal
+            .allergenics
+            .iter()
+            .filter(|allergenic| {
+                let name = allergenic_name(allergenic);
+                self.avoided_allergenics.iter().any(|avoided| avoided == name)
+            })
+            .map(translate_allergenic)
+            .collect::<Vec<_>>();
+
+        if !allergenics.is_empty() {
+            reasons.push(format!(
+                "{}: {}",
+                t("Contains allergenics you avoid"),
+                allergenics.join(", "),
+            ));
+        }
+
+        let symbol_names = meal.symbols.iter().map(symbol_name).collect::<Vec<_>>();
+
+        let required = self.diet_profile.required_symbols();
+        if !required.is_empty() && !required.iter().any(|req| symbol_names.iter().any(|name| name == req)) {
+            reasons.push(format!(
+                "{}: {}",
+                t("Not marked as suitable for your diet"),
+                self.diet_profile.translate(),
+            ));
+        }
+
+        let forbidden = self.diet_profile.forbidden_symbols(&self.custom_excluded_symbols);
+        let forbidden_symbols = meal
+            .symbols
+            .iter()
+            .filter(|symbol| {
+                let name = symbol_name(symbol);
+                forbidden.iter().any(|forbidden| forbidden == name)
+            })
+            .map(translate_symbol)
+            .collect::<Vec<_>>();
+
+        if !forbidden_symbols.is_empty() {
+            reasons.push(format!(
+                "{}: {}",
+                t("Contains what your diet excludes"),
+                forbidden_symbols.join(", "),
+            ));
+        }
+
+        reasons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Allergenic, Price, Symbol};
+
+    fn meal(symbols: Vec<Symbol>, allergenics: Vec<Allergenic>) -> Meal {
+        Meal {
+            name: "Pasta".to_owned(),
+            price: Price {
+                student: 2.0,
+                staff: 3.0,
+                guest: 4.0,
+            },
+            symbols,
+            additives: Vec::new(),
+            allergenics,
+        }
+    }
+
+    fn allergenic_filter(avoided_allergenics: &[Allergenic]) -> MealFilter {
+        MealFilter {
+            avoided_allergenics: avoided_allergenics
+                .iter()
+                .map(|allergenic| allergenic_name(allergenic).to_owned())
+                .collect(),
+            diet_profile: DietProfile::None,
+            custom_excluded_symbols: Vec::new(),
+            mode: ExclusionMode::Dim,
+        }
+    }
+
+    fn diet_filter(diet_profile: DietProfile, custom_excluded_symbols: &[Symbol]) -> MealFilter {
+        MealFilter {
+            avoided_allergenics: Vec::new(),
+            diet_profile,
+            custom_excluded_symbols: custom_excluded_symbols
+                .iter()
+                .map(|symbol| symbol_name(symbol).to_owned())
+                .collect(),
+            mode: ExclusionMode::Dim,
+        }
+    }
+
+    #[test]
+    fn avoided_allergenics_exclude_meals() {
+        let filter = allergenic_filter(&[Allergenic::Egg, Allergenic::Walnut]);
+
+        let cake = meal(Vec::new(), vec![Allergenic::Wheat, Allergenic::Egg, Allergenic::Walnut]);
+        let bread = meal(Vec::new(), vec![Allergenic::Wheat]);
+
+        // all avoided allergenics of a meal are listed in a single reason
+        assert_eq!(filter.check(&cake).len(), 1);
+        assert!(filter.check(&bread).is_empty());
+    }
+
+    #[test]
+    fn no_avoided_allergenics_keep_all_meals() {
+        let filter = allergenic_filter(&[]);
+        assert!(filter.check(&meal(Vec::new(), vec![Allergenic::Egg])).is_empty());
+    }
+
+    #[test]
+    fn unknown_profile_is_no_diet() {
+        assert_eq!(DietProfile::from_name("pescetarian"), DietProfile::Pescetarian);
+        assert_eq!(DietProfile::from_name("carnivore"), DietProfile::None);
+    }
+
+    #[test]
+    fn meat_symbols_are_symbol_names() {
+        for symbol in &[Symbol::Pig, Symbol::Cattle, Symbol::Poultry, Symbol::Game, Symbol::Lamb] {
+            assert!(MEAT_SYMBOLS.contains(&symbol_name(symbol)));
+        }
+    }
+
+    #[test]
+    fn diets_require_their_markings() {
+        let vegan = diet_filter(DietProfile::Vegan, &[]);
+        assert!(vegan.check(&meal(vec![Symbol::Vegan], Vec::new())).is_empty());
+        assert_eq!(vegan.check(&meal(vec![Symbol::Vegetarian], Vec::new())).len(), 1);
+        assert_eq!(vegan.check(&meal(Vec::new(), Vec::new())).len(), 1);
+
+        let vegetarian = diet_filter(DietProfile::Vegetarian, &[]);
+        assert!(vegetarian.check(&meal(vec![Symbol::Vegetarian], Vec::new())).is_empty());
+        assert!(vegetarian.check(&meal(vec![Symbol::Vegan], Vec::new())).is_empty());
+    }
+
+    #[test]
+    fn pescetarian_excludes_meat() {
+        let pescetarian = diet_filter(DietProfile::Pescetarian, &[]);
+        assert!(pescetarian.check(&meal(vec![Symbol::Fish], Vec::new())).is_empty());
+        // not marked as suitable and containing meat
+        assert_eq!(pescetarian.check(&meal(vec![Symbol::Cattle], Vec::new())).len(), 2);
+        // inconsistent markings still exclude the meal
+        assert_eq!(pescetarian.check(&meal(vec![Symbol::Fish, Symbol::Poultry], Vec::new())).len(), 1);
+    }
+
+    #[test]
+    fn halal_and_custom_exclude_symbols() {
+        let halal = diet_filter(DietProfile::Halal, &[]);
+        assert!(halal.check(&meal(vec![Symbol::Poultry], Vec::new())).is_empty());
+        assert_eq!(halal.check(&meal(vec![Symbol::Pig], Vec::new())).len(), 1);
+        assert_eq!(halal.check(&meal(vec![Symbol::Alcohol], Vec::new())).len(), 1);
+
+        let custom = diet_filter(DietProfile::Custom, &[Symbol::Garlic]);
+        assert!(custom.check(&meal(vec![Symbol::Pig], Vec::new())).is_empty());
+        assert_eq!(custom.check(&meal(vec![Symbol::Garlic], Vec::new())).len(), 1);
+
+        // the custom symbols only apply to the custom profile
+        let vegetarian = diet_filter(DietProfile::Vegetarian, &[Symbol::Garlic]);
+        assert!(vegetarian.check(&meal(vec![Symbol::Garlic, Symbol::Vegan], Vec::new())).is_empty());
+    }
+}
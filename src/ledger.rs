@@ -0,0 +1,128 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use gio::prelude::*;
+use gio::{Notification, Settings};
+use gettextrs::gettext as t;
+use serde::{Deserialize, Serialize};
+
+use crate::canteen;
+use crate::export::csv_row;
+use crate::favorites;
+use crate::model::CanteenDescription;
+use crate::store::Store;
+
+// a meal the user ate together with the price they paid for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub canteen: CanteenDescription,
+    pub date: NaiveDate,
+    pub meal: String,
+    pub price: f32,
+}
+
+impl Entry {
+    fn is_meal(&self, canteen_desc: &CanteenDescription, date: NaiveDate, meal_name: &str) -> bool {
+        self.canteen == *canteen_desc
+            && self.date == date
+            && favorites::normalize(&self.meal) == favorites::normalize(meal_name)
+    }
+}
+
+// the meals the user ate, ordered by date
+pub type Ledger = Store<Vec<Entry>>;
+
+pub fn load() -> Ledger {
+    Store::load("ledger.json")
+}
+
+pub fn has_eaten(ledger: &Ledger, canteen_desc: &CanteenDescription, date: NaiveDate, meal_name: &str) -> bool {
+    ledger
+        .get()
+        .iter()
+        .any(|entry| entry.is_meal(canteen_desc, date, meal_name))
+}
+
+pub fn set_eaten(ledger: &Ledger, entry: Entry, eaten: bool) {
+    log::debug!("setting eaten state of {} on {} to {}", entry.meal, entry.date, eaten);
+
+    ledger.update(|entries| {
+        let position = entries
+            .iter()
+            .position(|other| other.is_meal(&entry.canteen, entry.date, &entry.meal));
+        match (position, eaten) {
+            (None, true) => {
+                // meals of the same day stay in the order they got marked
+                let idx = entries
+                    .iter()
+                    .position(|other| other.date > entry.date)
+                    .unwrap_or_else(|| entries.len());
+                entries.insert(idx, entry);
+                true
+            },
+            (Some(idx), false) => {
+                entries.remove(idx);
+                true
+            },
+            _ => false,
+        }
+    });
+}
+
+// the sum of the prices of all meals eaten in the week of the given day
+pub fn week_total(entries: &[Entry], today: NaiveDate) -> f32 {
+    let monday = today - Duration::days(i64::from(today.weekday().num_days_from_monday()));
+    let sunday = monday + Duration::days(6);
+
+    entries
+        .iter()
+        .filter(|entry| entry.date >= monday && entry.date <= sunday)
+        .map(|entry| entry.price)
+        .sum()
+}
+
+// the sum of the prices of all meals eaten in the month of the given day
+pub fn month_total(entries: &[Entry], today: NaiveDate) -> f32 {
+    entries
+        .iter()
+        .filter(|entry| entry.date.year() == today.year() && entry.date.month() == today.month())
+        .map(|entry| entry.price)
+        .sum()
+}
+
+// the monthly budget of the user, None if the budget is disabled
+pub fn budget(settings: &Settings) -> Option<f32> {
+    let budget = settings.get_double("monthly-budget");
+    if budget > 0.0 {
+        Some(budget as f32)
+    } else {
+        None
+    }
+}
+
+// warns the user that the meals of this month cost more than the budget
+pub fn notify_budget_exceeded(app: &gtk::Application, spent: f32, budget: f32) {
+    log::info!("spent {:.2} € this month, exceeding the budget of {:.2} €", spent, budget);
+
+    let notification = Notification::new(&t("Monthly budget exceeded"));
+    notification.set_body(Some(
+        &t("You spent {spent} of your {budget} budget this month")
+            .replace("{spent}", &format!("{:.2} €", spent))
+            .replace("{budget}", &format!("{:.2} €", budget)),
+    ));
+    app.send_notification(Some("budget-exceeded"), &notification);
+}
+
+pub fn export_csv(entries: &[Entry]) -> String {
+    log::debug!("exporting {} ledger entries as csv", entries.len());
+
+    let mut csv = csv_row(vec![t("Date"), t("Canteen"), t("Meal"), t("Price")]);
+    for entry in entries {
+        csv += &csv_row(vec![
+            format!("{}", entry.date),
+            canteen::translate(&entry.canteen),
+            entry.meal.clone(),
+            format!("{:.2}", entry.price),
+        ]);
+    }
+
+    csv
+}
@@ -0,0 +1,140 @@
+use gettextrs::gettext as t;
+
+use crate::model::{Additive, Allergenic};
+
+// the 14 allergens which have to be declared in the EU, see annex II of regulation (EU) No 1169/2011
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EuAllergen {
+    Gluten,
+    Crustaceans,
+    Eggs,
+    Fish,
+    Peanuts,
+    Soybeans,
+    Milk,
+    Nuts,
+    Celery,
+    Mustard,
+    Sesame,
+    Sulphites,
+    Lupin,
+    Molluscs,
+}
+
+impl EuAllergen {
+    pub const fn of(allergenic: &Allergenic) -> Self {
+        match allergenic {
+            Allergenic::Wheat
+            | Allergenic::Rye
+            | Allergenic::Barley
+            | Allergenic::Oat
+            | Allergenic::Spelt
+            | Allergenic::Kamut => Self::Gluten,
+            Allergenic::Crustacean => Self::Crustaceans,
+            Allergenic::Egg => Self::Eggs,
+            Allergenic::Fish => Self::Fish,
+            Allergenic::Peanut => Self::Peanuts,
+            Allergenic::Soya => Self::Soybeans,
+            Allergenic::Lactose => Self::Milk,
+            Allergenic::Almond
+            | Allergenic::Hazelnut
+            | Allergenic::Walnut
+            | Allergenic::Cashew
+            | Allergenic::PecanNut
+            | Allergenic::BrazilNut
+            | Allergenic::Pistachio
+            | Allergenic::MacadamiaNut
+            | Allergenic::QueenslandNut => Self::Nuts,
+            Allergenic::Celery => Self::Celery,
+            Allergenic::Mustard => Self::Mustard,
+            Allergenic::Sesame => Self::Sesame,
+            Allergenic::Sulphite => Self::Sulphites,
+            Allergenic::Lupin => Self::Lupin,
+            Allergenic::Mollusc => Self::Molluscs,
+        }
+    }
+
+    pub fn translate(self) -> String {
+        match self {
+            Self::Gluten => t("Cereals containing gluten"),
+            Self::Crustaceans => t("Crustaceans"),
+            Self::Eggs => t("Eggs"),
+            Self::Fish => t("Fish"),
+            Self::Peanuts => t("Peanuts"),
+            Self::Soybeans => t("Soybeans"),
+            Self::Milk => t("Milk"),
+            Self::Nuts => t("Nuts"),
+            Self::Celery => t("Celery"),
+            Self::Mustard => t("Mustard"),
+            Self::Sesame => t("Sesame seeds"),
+            Self::Sulphites => t("Sulphur dioxide and sulphites"),
+            Self::Lupin => t("Lupin"),
+            Self::Molluscs => t("Molluscs"),
+        }
+    }
+}
+
+// the code of the allergenic as printed in the legend of the OvGU menus
+pub const fn allergenic_code(allergenic: &Allergenic) -> &'static str {
+    match allergenic {
+        Allergenic::Wheat => "a1",
+        Allergenic::Rye => "a2",
+        Allergenic::Barley => "a3",
+        Allergenic::Oat => "a4",
+        Allergenic::Spelt => "a5",
+        Allergenic::Kamut => "a6",
+        Allergenic::Crustacean => "b",
+        Allergenic::Egg => "c",
+        Allergenic::Fish => "d",
+        Allergenic::Peanut => "e",
+        Allergenic::Soya => "f",
+        Allergenic::Lactose => "g",
+        Allergenic::Almond => "h1",
+        Allergenic::Hazelnut => "h2",
+        Allergenic::Walnut => "h3",
+        Allergenic::Cashew => "h4",
+        Allergenic::PecanNut => "h5",
+        Allergenic::BrazilNut => "h6",
+        Allergenic::Pistachio => "h7",
+        Allergenic::MacadamiaNut => "h8",
+        Allergenic::QueenslandNut => "h9",
+        Allergenic::Celery => "i",
+        Allergenic::Mustard => "j",
+        Allergenic::Sesame => "k",
+        Allergenic::Sulphite => "l",
+        Allergenic::Lupin => "m",
+        Allergenic::Mollusc => "n",
+    }
+}
+
+// the number of the additive as printed in the legend of the OvGU menus
+pub const fn additive_code(additive: &Additive) -> &'static str {
+    match additive {
+        Additive::FoodColoring => "1",
+        Additive::FoodPreservatives => "2",
+        Additive::AntiOxidants => "3",
+        Additive::FlavorEnhancer => "4",
+        Additive::Sulfurized => "5",
+        Additive::Blackend => "6",
+        Additive::Waxed => "7",
+        Additive::Phosphates => "8",
+        Additive::Sweetener => "9",
+        Additive::Phenylalanine => "10",
+    }
+}
+
+// the full wording of the additive, the badges only show a short name
+pub fn describe_additive(additive: &Additive) -> String {
+    match additive {
+        Additive::FoodColoring => t("With food coloring"),
+        Additive::FoodPreservatives => t("With preservatives"),
+        Additive::AntiOxidants => t("With antioxidants"),
+        Additive::FlavorEnhancer => t("With flavor enhancer"),
+        Additive::Sulfurized => t("Sulfurized"),
+        Additive::Blackend => t("Blackened"),
+        Additive::Waxed => t("Waxed"),
+        Additive::Phosphates => t("With phosphate"),
+        Additive::Sweetener => t("With sweeteners"),
+        Additive::Phenylalanine => t("Contains a source of phenylalanine"),
+    }
+}
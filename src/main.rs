@@ -11,11 +11,59 @@
     clippy::non_ascii_literal,
     clippy::multiple_crate_versions,
     clippy::module_name_repetitions,
-    clippy::else_if_without_else
+    clippy::else_if_without_else,
+    clippy::blanket_clippy_restriction_lints,
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::pattern_type_mismatch,
+    clippy::as_conversions,
+    clippy::float_arithmetic,
+    clippy::integer_arithmetic,
+    clippy::indexing_slicing,
+    clippy::wildcard_enum_match_arm,
+    clippy::shadow_reuse,
+    clippy::exit,
+    clippy::clone_on_ref_ptr,
+    clippy::future_not_send,
+    clippy::filter_map,
+    clippy::find_map,
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss,
+    clippy::single_match_else,
+    clippy::must_use_candidate,
+    clippy::missing_errors_doc,
+    clippy::too_many_lines,
+    clippy::cognitive_complexity,
+    clippy::single_component_path_imports,
+    clippy::redundant_pub_crate
 )]
+// the assertions of the tests are reported as panics
+#![cfg_attr(test, allow(clippy::panic))]
 
 mod application;
+mod archive;
+mod cache;
+mod calendar;
+mod cli;
 mod components;
+mod export;
+mod favorites;
+mod fetch;
+mod filter;
+mod ledger;
+mod legend;
+mod model;
+mod notifications;
+mod price;
+mod ratings;
+mod refresh;
+mod search_provider;
+mod sorting;
+mod source;
+mod statistics;
+mod store;
 mod util;
 
 pub use components::canteen;
@@ -36,9 +84,14 @@ fn main() {
     domain.init()
         .expect("Failed to initialize translation domain");
 
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Some(exit_code) = cli::run(&args) {
+        std::process::exit(exit_code);
+    }
+
     match application::Application::new() {
         Ok(app) => {
-            std::process::exit(app.run(&std::env::args().collect::<Vec<_>>()));
+            std::process::exit(app.run(&args));
         }
         Err(msg) => {
             log::error!("error: {}", msg);
@@ -0,0 +1,85 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+// symbols, additives and allergenics are the common vocabulary of all menu sources,
+// every source has to map the markings of its meals onto these
+pub use ovgu_canteen::{Additive, Allergenic, Symbol};
+
+// identifies a canteen across all menu sources, the canteens of the OvGU
+// keep the names used by the ovgu-canteen crate, e.g. UniCampusLowerHall
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CanteenDescription(String);
+
+impl CanteenDescription {
+    pub fn new<S: Into<String>>(id: S) -> Self {
+        Self(id.into())
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Price {
+    pub student: f32,
+    pub staff: f32,
+    pub guest: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Meal {
+    pub name: String,
+    pub price: Price,
+    pub symbols: Vec<Symbol>,
+    pub additives: Vec<Additive>,
+    pub allergenics: Vec<Allergenic>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub meals: Vec<Meal>,
+    pub side_dishes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Canteen {
+    pub description: CanteenDescription,
+    // the name provided by the menu source, canteens without a name get a translated one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // the menu source and when the menus were fetched the last time,
+    // both are kept in the header of the cache file of the canteen
+    #[serde(skip)]
+    pub source: Option<String>,
+    #[serde(skip)]
+    pub fetched: Option<DateTime<Utc>>,
+    pub days: Vec<Day>,
+}
+
+impl Canteen {
+    // replaces all known days with the fetched ones and adds all new days,
+    // days which are not available anymore are kept as history
+    pub fn merge(&mut self, fetched: Self) {
+        if fetched.name.is_some() {
+            self.name = fetched.name;
+        }
+        if fetched.source.is_some() {
+            self.source = fetched.source;
+        }
+        if fetched.fetched.is_some() {
+            self.fetched = fetched.fetched;
+        }
+
+        for day in fetched.days {
+            match self.days.iter_mut().find(|known_day| known_day.date == day.date) {
+                Some(known_day) => *known_day = day,
+                None => self.days.push(day),
+            }
+        }
+
+        self.days.sort_by_key(|day| day.date);
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, Local, NaiveDate};
+use gio::prelude::*;
+use gio::{Notification, Settings};
+use gettextrs::gettext as t;
+use regex::{Regex, RegexBuilder};
+
+use crate::canteen;
+use crate::components::day::translate_weekday;
+use crate::favorites::{self, Favorites};
+use crate::model::Canteen;
+use crate::store::Store;
+
+// meals which already triggered a notification, see notification_id for the format
+pub type Notified = Store<BTreeSet<String>>;
+
+pub fn load() -> Notified {
+    Store::load("notified.json")
+}
+
+#[derive(Debug)]
+enum Pattern {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Option<Self> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return None;
+        }
+
+        // patterns enclosed in slashes are regular expressions
+        if pattern.len() > 2 && pattern.starts_with('/') && pattern.ends_with('/') {
+            let regex = RegexBuilder::new(&pattern[1..pattern.len() - 1])
+                .case_insensitive(true)
+                .build();
+            return match regex {
+                Ok(regex) => Some(Self::Regex(regex)),
+                Err(e) => {
+                    log::warn!("ignoring invalid watched meal pattern {}: {:#}", pattern, e);
+                    None
+                },
+            };
+        }
+
+        Some(Self::Substring(pattern.to_lowercase()))
+    }
+
+    fn matches(&self, meal_name: &str) -> bool {
+        match self {
+            Self::Substring(substring) => meal_name.to_lowercase().contains(substring),
+            Self::Regex(regex) => regex.is_match(meal_name),
+        }
+    }
+}
+
+// decides which meals the user wants to be notified about
+#[derive(Debug)]
+pub struct WatchList {
+    patterns: Vec<Pattern>,
+    favorites: Option<BTreeSet<String>>,
+}
+
+impl WatchList {
+    pub fn from_settings(settings: &Settings, favorites: &Favorites) -> Self {
+        let patterns = settings
+            .get_strv("watched-meals")
+            .iter()
+            .filter_map(|pattern| Pattern::parse(pattern))
+            .collect();
+        let favorites = if settings.get_boolean("notify-favorites") {
+            Some(favorites.get().clone())
+        } else {
+            None
+        };
+
+        Self { patterns, favorites }
+    }
+
+    pub fn matches(&self, meal_name: &str) -> bool {
+        let favorite = self
+            .favorites
+            .as_ref()
+            .map_or(false, |favorites| favorites.contains(&favorites::normalize(meal_name)));
+
+        favorite || self.patterns.iter().any(|pattern| pattern.matches(meal_name))
+    }
+}
+
+fn notification_id(date: NaiveDate, canteen: &Canteen, meal_name: &str) -> String {
+    format!(
+        "{}:{}:{}",
+        date,
+        canteen.description.id(),
+        favorites::normalize(meal_name),
+    )
+}
+
+// shows a desktop notification for each watched meal on an upcoming menu,
+// which was not notified about before
+pub fn notify_watched_meals(
+    app: &gtk::Application,
+    settings: &Settings,
+    favorites: &Favorites,
+    notified: &Notified,
+    canteens: &[Canteen],
+) {
+    log::debug!("looking for watched meals in upcoming menus");
+
+    let watch_list = WatchList::from_settings(settings, favorites);
+    let today = Local::today().naive_local();
+
+    let mut notifications = Vec::new();
+    for canteen in canteens {
+        for day in canteen.days.iter().filter(|day| day.date >= today) {
+            for meal in day.meals.iter().filter(|meal| watch_list.matches(&meal.name)) {
+                let id = notification_id(day.date, canteen, &meal.name);
+                if notified.get().contains(&id) {
+                    continue;
+                }
+
+                log::info!("watched meal {} appears on {} in {:?}", meal.name, day.date, canteen.description);
+
+                let notification = Notification::new(&meal.name);
+                notification.set_body(Some(
+                    &t("at {canteen} on {day}")
+                        .replace("{canteen}", &canteen::name(canteen))
+                        .replace("{day}", &translate_weekday(day.date.weekday())),
+                ));
+                // clicking the notification shows the canteen the meal is served in
+                notification.set_default_action_and_target_value(
+                    "app.canteen-selected",
+                    Some(&canteen.description.id().to_variant()),
+                );
+                app.send_notification(Some(&id), &notification);
+
+                notifications.push(id);
+            }
+        }
+    }
+
+    // remember notified meals and forget about the ones which are in the past
+    // ids start with the date, so all ids of past days are ordered before today
+    let today = format!("{}", today);
+    notified.update(move |notified| {
+        let count = notified.len();
+        *notified = notified.split_off(&today);
+        let pruned = notified.len() != count;
+
+        notifications
+            .into_iter()
+            .fold(pruned, |changed, id| notified.insert(id) || changed)
+    });
+
+    log::debug!("finish looking for watched meals");
+}
@@ -0,0 +1,123 @@
+use gio::prelude::*;
+use gio::Settings;
+use gettextrs::gettext as t;
+
+use crate::model::Price;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceGroup {
+    All,
+    Student,
+    Staff,
+    Guest,
+}
+
+// all available price groups with their settings value, also used on the command-line
+pub const PRICE_GROUPS: &[(&str, PriceGroup)] = &[
+    ("all", PriceGroup::All),
+    ("student", PriceGroup::Student),
+    ("staff", PriceGroup::Staff),
+    ("guest", PriceGroup::Guest),
+];
+
+impl PriceGroup {
+    pub fn from_name(name: &str) -> Option<Self> {
+        PRICE_GROUPS
+            .iter()
+            .find(|(group_name, _group)| *group_name == name)
+            .map(|(_group_name, group)| *group)
+    }
+
+    // the settings value of the group, which is also the name of its field in a Price
+    pub fn name(self) -> &'static str {
+        PRICE_GROUPS
+            .iter()
+            .find(|(_group_name, group)| *group == self)
+            .map_or("all", |(group_name, _group)| *group_name)
+    }
+
+    // the cli can be used without installed settings, then all prices are shown
+    pub fn from_settings(settings: Option<&Settings>) -> Self {
+        settings
+            .and_then(|settings| settings.get_string("price-group"))
+            .and_then(|name| Self::from_name(name.as_str()))
+            .unwrap_or(Self::All)
+    }
+
+    pub fn translate(self) -> String {
+        match self {
+            Self::All => t("All prices"),
+            Self::Student => t("Student"),
+            Self::Staff => t("Staff"),
+            Self::Guest => t("Guest"),
+        }
+    }
+
+    // the groups whose prices are shown, in the order of the price columns
+    pub const fn shown(self) -> &'static [Self] {
+        match self {
+            Self::All => &[Self::Student, Self::Staff, Self::Guest],
+            Self::Student => &[Self::Student],
+            Self::Staff => &[Self::Staff],
+            Self::Guest => &[Self::Guest],
+        }
+    }
+
+    // the shown prices of a meal together with their group
+    pub fn prices(self, price: &Price) -> Vec<(Self, f32)> {
+        self.shown()
+            .iter()
+            .map(|&group| {
+                let value = match group {
+                    Self::Staff => price.staff,
+                    Self::Guest => price.guest,
+                    Self::All | Self::Student => price.student,
+                };
+                (group, value)
+            })
+            .collect()
+    }
+
+    // the price relevant for the user, the student price if all prices are shown
+    pub fn relevant_price(self, price: &Price) -> f32 {
+        self.prices(price)
+            .first()
+            .map_or(price.student, |(_group, value)| *value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRICE: Price = Price {
+        student: 2.5,
+        staff: 3.5,
+        guest: 4.5,
+    };
+
+    #[test]
+    fn names_round_trip() {
+        for (name, group) in PRICE_GROUPS {
+            assert_eq!(PriceGroup::from_name(name), Some(*group));
+            assert_eq!(group.name(), *name);
+        }
+        assert_eq!(PriceGroup::from_name("pensioner"), None);
+    }
+
+    #[test]
+    fn all_prices_are_shown_in_column_order() {
+        let prices = PriceGroup::All.prices(&PRICE);
+        let groups = prices.iter().map(|(group, _value)| *group).collect::<Vec<_>>();
+        assert_eq!(groups, [PriceGroup::Student, PriceGroup::Staff, PriceGroup::Guest]);
+        assert!((PriceGroup::All.relevant_price(&PRICE) - PRICE.student).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn single_group_shows_its_price_only() {
+        assert_eq!(PriceGroup::Staff.prices(&PRICE).len(), 1);
+        assert!((PriceGroup::Student.relevant_price(&PRICE) - PRICE.student).abs() < f32::EPSILON);
+        assert!((PriceGroup::Staff.relevant_price(&PRICE) - PRICE.staff).abs() < f32::EPSILON);
+        assert!((PriceGroup::Guest.relevant_price(&PRICE) - PRICE.guest).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,293 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::favorites;
+use crate::model::CanteenDescription;
+use crate::store::Store;
+
+pub const MAX_STARS: u8 = 5;
+
+// the rating of a dish on the day it got served
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rating {
+    pub canteen: CanteenDescription,
+    // the normalized name of the meal
+    pub meal: String,
+    pub date: NaiveDate,
+    pub stars: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    pub canteen: CanteenDescription,
+    // the normalized name of the meal
+    pub meal: String,
+    pub text: String,
+}
+
+// a dish is identified by its canteen and its normalized name,
+// it has a rating for every day it got rated and a single note
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RatedDishes {
+    #[serde(default)]
+    pub ratings: Vec<Rating>,
+    #[serde(default)]
+    pub notes: Vec<Note>,
+}
+
+// the ratings file can be exported and imported to pool the ratings of a team
+pub type Ratings = Store<RatedDishes>;
+
+pub fn load() -> Ratings {
+    Store::load("ratings.json")
+}
+
+impl RatedDishes {
+    // the average number of stars of the dish and the number of ratings
+    pub fn average(&self, canteen_desc: &CanteenDescription, meal_name: &str) -> Option<(f32, usize)> {
+        let meal = favorites::normalize(meal_name);
+        let stars = self
+            .ratings
+            .iter()
+            .filter(|rating| rating.canteen == *canteen_desc && rating.meal == meal)
+            .map(|rating| f32::from(rating.stars))
+            .collect::<Vec<_>>();
+
+        if stars.is_empty() {
+            None
+        } else {
+            Some((stars.iter().sum::<f32>() / stars.len() as f32, stars.len()))
+        }
+    }
+
+    pub fn stars(&self, canteen_desc: &CanteenDescription, date: NaiveDate, meal_name: &str) -> Option<u8> {
+        let meal = favorites::normalize(meal_name);
+        self.ratings
+            .iter()
+            .find(|rating| rating.canteen == *canteen_desc && rating.date == date && rating.meal == meal)
+            .map(|rating| rating.stars)
+    }
+
+    pub fn note(&self, canteen_desc: &CanteenDescription, meal_name: &str) -> Option<&str> {
+        let meal = favorites::normalize(meal_name);
+        self.notes
+            .iter()
+            .find(|note| note.canteen == *canteen_desc && note.meal == meal)
+            .map(|note| note.text.as_str())
+    }
+
+    // adds all ratings and notes which are not known yet, returns how many got added
+    fn merge(&mut self, other: Self) -> usize {
+        let mut added = 0;
+
+        for rating in other.ratings {
+            // ratings of other files may have been edited by hand
+            if rating.stars == 0 || rating.stars > MAX_STARS {
+                log::warn!("skipping rating of {} with {} stars", rating.meal, rating.stars);
+                continue;
+            }
+
+            let rating = Rating {
+                meal: favorites::normalize(&rating.meal),
+                ..rating
+            };
+            // a dish has a single rating per day, on a conflict the own rating is kept,
+            // as the imported one is the opinion of someone else
+            let known = self
+                .ratings
+                .iter()
+                .any(|own| own.canteen == rating.canteen && own.date == rating.date && own.meal == rating.meal);
+            if !known {
+                self.ratings.push(rating);
+                added += 1;
+            }
+        }
+
+        for note in other.notes.into_iter().filter(|note| !note.text.trim().is_empty()) {
+            let meal = favorites::normalize(&note.meal);
+            match self
+                .notes
+                .iter_mut()
+                .find(|own| own.canteen == note.canteen && own.meal == meal)
+            {
+                // notes of the same dish are joined, so nothing gets lost
+                Some(own) if !own.text.contains(note.text.trim()) => {
+                    own.text = format!("{}\n\n{}", own.text, note.text.trim());
+                    added += 1;
+                },
+                Some(_own) => {},
+                None => {
+                    self.notes.push(Note { meal, ..note });
+                    added += 1;
+                },
+            }
+        }
+
+        added
+    }
+}
+
+// rates the dish on the given day, None removes the rating
+pub fn set_stars(
+    ratings: &Ratings,
+    canteen_desc: &CanteenDescription,
+    date: NaiveDate,
+    meal_name: &str,
+    stars: Option<u8>,
+) {
+    let meal = favorites::normalize(meal_name);
+    log::debug!("rating {} on {} with {:?} stars", meal, date, stars);
+
+    ratings.update(|dishes| {
+        let position = dishes
+            .ratings
+            .iter()
+            .position(|rating| rating.canteen == *canteen_desc && rating.date == date && rating.meal == meal);
+        match (position, stars) {
+            (Some(idx), Some(stars)) => {
+                let changed = dishes.ratings[idx].stars != stars;
+                dishes.ratings[idx].stars = stars;
+                changed
+            },
+            (Some(idx), None) => {
+                dishes.ratings.remove(idx);
+                true
+            },
+            (None, Some(stars)) => {
+                dishes.ratings.push(Rating {
+                    canteen: canteen_desc.clone(),
+                    meal,
+                    date,
+                    stars,
+                });
+                true
+            },
+            (None, None) => false,
+        }
+    });
+}
+
+// replaces the note of the dish, an empty text removes the note
+pub fn set_note(ratings: &Ratings, canteen_desc: &CanteenDescription, meal_name: &str, text: &str) {
+    let meal = favorites::normalize(meal_name);
+    let text = text.trim();
+
+    ratings.update(|dishes| {
+        let position = dishes
+            .notes
+            .iter()
+            .position(|note| note.canteen == *canteen_desc && note.meal == meal);
+        match position {
+            Some(idx) if text.is_empty() => {
+                dishes.notes.remove(idx);
+                true
+            },
+            Some(idx) => {
+                let changed = dishes.notes[idx].text != text;
+                dishes.notes[idx].text = text.to_owned();
+                changed
+            },
+            None if text.is_empty() => false,
+            None => {
+                log::debug!("adding note to {}", meal);
+                dishes.notes.push(Note {
+                    canteen: canteen_desc.clone(),
+                    meal,
+                    text: text.to_owned(),
+                });
+                true
+            },
+        }
+    });
+}
+
+// merges the ratings and notes of the file into the own ones, returns how many got added
+pub fn import(ratings: &Ratings, path: &Path) -> Result<usize> {
+    log::debug!("importing ratings from {:?}", path);
+
+    let file = File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+    let other: RatedDishes =
+        serde_json::from_reader(file).with_context(|| format!("Could not parse ratings of {}", path.display()))?;
+
+    let mut added = 0;
+    ratings.update(|dishes| {
+        added = dishes.merge(other);
+        added > 0
+    });
+    log::debug!("imported {} ratings and notes", added);
+
+    Ok(added)
+}
+
+pub fn export(ratings: &Ratings, path: &Path) -> Result<()> {
+    log::debug!("exporting ratings to {:?}", path);
+
+    let content = serde_json::to_string_pretty(&*ratings.get()).context("Failed to serialize ratings")?;
+    fs::write(path, content).with_context(|| format!("Could not write to {}", path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(meal: &str, day: u32, stars: u8) -> Rating {
+        Rating {
+            canteen: CanteenDescription::new("UniCampusLowerHall"),
+            meal: meal.to_owned(),
+            date: NaiveDate::from_ymd(2020, 5, day),
+            stars,
+        }
+    }
+
+    fn note(meal: &str, text: &str) -> Note {
+        Note {
+            canteen: CanteenDescription::new("UniCampusLowerHall"),
+            meal: meal.to_owned(),
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn merge_keeps_own_rating_on_conflict() {
+        let mut own = RatedDishes {
+            ratings: vec![rating("pasta", 4, 5)],
+            notes: Vec::new(),
+        };
+        let other = RatedDishes {
+            ratings: vec![rating("  Pasta ", 4, 2), rating("pasta", 5, 3), rating("soup", 4, 0)],
+            notes: Vec::new(),
+        };
+
+        assert_eq!(own.merge(other), 1);
+        assert_eq!(own.ratings.len(), 2);
+
+        let canteen = CanteenDescription::new("UniCampusLowerHall");
+        assert_eq!(own.stars(&canteen, NaiveDate::from_ymd(2020, 5, 4), "Pasta"), Some(5));
+        assert_eq!(own.stars(&canteen, NaiveDate::from_ymd(2020, 5, 5), "Pasta"), Some(3));
+        assert_eq!(own.average(&canteen, "pasta"), Some((4.0, 2)));
+    }
+
+    #[test]
+    fn merge_joins_conflicting_notes() {
+        let mut own = RatedDishes {
+            ratings: Vec::new(),
+            notes: vec![note("pasta", "too salty")],
+        };
+        let other = RatedDishes {
+            ratings: Vec::new(),
+            notes: vec![note("Pasta", "great sauce"), note("pasta", "too salty"), note("soup", "  ")],
+        };
+
+        assert_eq!(own.merge(other), 1);
+
+        let canteen = CanteenDescription::new("UniCampusLowerHall");
+        assert_eq!(own.note(&canteen, "pasta"), Some("too salty\n\ngreat sauce"));
+        assert_eq!(own.note(&canteen, "soup"), None);
+    }
+}
@@ -0,0 +1,112 @@
+use std::cell::Cell;
+use std::os::raw::{c_char, c_uint};
+use std::ptr;
+
+use anyhow::{Context, Result};
+use gio::prelude::*;
+use glib::translate::{from_glib_full, ToGlibPtr};
+
+// how long to wait for further triggers before refreshing,
+// e.g. a resume from suspend is usually followed by a network reconnect
+pub const DEBOUNCE_SECONDS: u32 = 5;
+
+// logind announces suspend and resume on the system bus
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const LOGIND_OBJECT_PATH: &str = "/org/freedesktop/login1";
+const LOGIND_INTERFACE_NAME: &str = "org.freedesktop.login1.Manager";
+const PREPARE_FOR_SLEEP: &str = "PrepareForSleep";
+
+type ResumedCallback = Box<dyn Fn()>;
+
+// keeps the subscription to the logind sleep signals, it is cancelled when dropped
+pub struct SleepSubscription {
+    connection: *mut gio_sys::GDBusConnection,
+    id: c_uint,
+}
+
+impl Drop for SleepSubscription {
+    fn drop(&mut self) {
+        log::debug!("unsubscribing from logind sleep signals");
+
+        unsafe {
+            // the callback is freed by free_callback as soon as the subscription is gone
+            gio_sys::g_dbus_connection_signal_unsubscribe(self.connection, self.id);
+            glib::gobject_sys::g_object_unref(self.connection.cast());
+        }
+    }
+}
+
+// calls f whenever the network becomes available again after it was gone
+pub fn connect_network_restored<F: Fn() + 'static>(f: F) -> Result<()> {
+    let monitor = gio::NetworkMonitor::get_default().context("No network monitor available")?;
+    let available = Cell::new(monitor.get_network_available());
+
+    monitor.connect_network_changed(move |_monitor, now_available| {
+        // the network changes quite often while it stays available, e.g. when routes change
+        if now_available && !available.get() {
+            log::debug!("network connection got restored");
+            f();
+        }
+        available.set(now_available);
+    });
+
+    Ok(())
+}
+
+unsafe extern "C" fn prepare_for_sleep(
+    _connection: *mut gio_sys::GDBusConnection,
+    _sender_name: *const c_char,
+    _object_path: *const c_char,
+    _interface_name: *const c_char,
+    _signal_name: *const c_char,
+    parameters: *mut glib_sys::GVariant,
+    user_data: glib_sys::gpointer,
+) {
+    // the argument is true before suspending and false after resuming
+    let child = glib_sys::g_variant_get_child_value(parameters, 0);
+    let suspending = glib_sys::g_variant_get_boolean(child) != glib_sys::GFALSE;
+    glib_sys::g_variant_unref(child);
+
+    if suspending {
+        log::debug!("system is going to suspend");
+    } else {
+        log::debug!("system resumed from suspend");
+        let f = &*(user_data as *const ResumedCallback);
+        f();
+    }
+}
+
+unsafe extern "C" fn free_callback(user_data: glib_sys::gpointer) {
+    drop(Box::from_raw(user_data as *mut ResumedCallback));
+}
+
+// calls f whenever the system resumes from suspend, until the subscription is dropped
+pub fn connect_resumed<F: Fn() + 'static>(f: F) -> Result<SleepSubscription> {
+    log::debug!("subscribing to logind sleep signals");
+
+    unsafe {
+        // the reference to the system bus is kept by the subscription
+        let mut error = ptr::null_mut();
+        let connection = gio_sys::g_bus_get_sync(gio_sys::G_BUS_TYPE_SYSTEM, ptr::null_mut(), &mut error);
+        if connection.is_null() {
+            let error: glib::Error = from_glib_full(error);
+            return Err(error).context("System bus is not available");
+        }
+
+        let callback: ResumedCallback = Box::new(f);
+        let id = gio_sys::g_dbus_connection_signal_subscribe(
+            connection,
+            LOGIND_NAME.to_glib_none().0,
+            LOGIND_INTERFACE_NAME.to_glib_none().0,
+            PREPARE_FOR_SLEEP.to_glib_none().0,
+            LOGIND_OBJECT_PATH.to_glib_none().0,
+            ptr::null(),
+            gio_sys::G_DBUS_SIGNAL_FLAGS_NONE,
+            Some(prepare_for_sleep),
+            Box::into_raw(Box::new(callback)) as glib_sys::gpointer,
+            Some(free_callback),
+        );
+
+        Ok(SleepSubscription { connection, id })
+    }
+}
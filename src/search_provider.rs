@@ -0,0 +1,367 @@
+use std::cell::{Cell, RefCell};
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Local, NaiveDate};
+use gio::prelude::*;
+use glib::translate::{from_glib_full, from_glib_none, FromGlibPtrContainer, ToGlibPtr};
+use glib::ToVariant;
+use gettextrs::gettext as t;
+
+use crate::cache;
+use crate::canteen;
+use crate::components::day::translate_weekday;
+use crate::favorites;
+use crate::model::{Canteen, CanteenDescription, Meal};
+
+// the search provider is registered on the application's own D-Bus connection,
+// see data/io.github.fin_ger.OvGUCanteen.search-provider.ini
+pub const OBJECT_PATH: &str = "/io/github/fin_ger/OvGUCanteen/SearchProvider";
+
+const INTERFACE_NAME: &str = "org.gnome.Shell.SearchProvider2";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="org.gnome.Shell.SearchProvider2">
+    <method name="GetInitialResultSet">
+      <arg type="as" name="terms" direction="in" />
+      <arg type="as" name="results" direction="out" />
+    </method>
+    <method name="GetSubsearchResultSet">
+      <arg type="as" name="previous_results" direction="in" />
+      <arg type="as" name="terms" direction="in" />
+      <arg type="as" name="results" direction="out" />
+    </method>
+    <method name="GetResultMetas">
+      <arg type="as" name="identifiers" direction="in" />
+      <arg type="aa{sv}" name="metas" direction="out" />
+    </method>
+    <method name="ActivateResult">
+      <arg type="s" name="identifier" direction="in" />
+      <arg type="as" name="terms" direction="in" />
+      <arg type="u" name="timestamp" direction="in" />
+    </method>
+    <method name="LaunchSearch">
+      <arg type="as" name="terms" direction="in" />
+      <arg type="u" name="timestamp" direction="in" />
+    </method>
+  </interface>
+</node>
+"#;
+
+// results are identified by canteen, date and the normalized name of the meal, so they stay valid
+// when the meals of the day get reordered, e.g. UniCampusLowerHall:2020-05-04:pasta with tomato sauce
+fn result_id(canteen: &Canteen, date: NaiveDate, meal: &Meal) -> String {
+    format!(
+        "{}:{}:{}",
+        canteen.description.id(),
+        date.format("%Y-%m-%d"),
+        favorites::normalize(&meal.name),
+    )
+}
+
+fn parse_result_id(id: &str) -> Option<(CanteenDescription, NaiveDate, String)> {
+    let mut parts = id.splitn(3, ':');
+    let description = CanteenDescription::new(parts.next()?);
+    let date = NaiveDate::parse_from_str(parts.next()?, "%Y-%m-%d").ok()?;
+    let meal_name = parts.next()?.to_owned();
+
+    Some((description, date, meal_name))
+}
+
+// terms which only say that the user is looking for the canteen, e.g. "mensa pizza"
+fn is_keyword(term: &str) -> bool {
+    ["mensa", "canteen", "menu"].contains(&term) || term == t("canteen").to_lowercase() || term == t("menu").to_lowercase()
+}
+
+// a meal matches if every term is part of its name or the name of its canteen
+fn matches(canteen_name: &str, meal_name: &str, terms: &[String]) -> bool {
+    let canteen_name = canteen_name.to_lowercase();
+    let meal_name = meal_name.to_lowercase();
+    let terms = terms
+        .iter()
+        .map(|term| term.to_lowercase())
+        .filter(|term| !is_keyword(term))
+        .collect::<Vec<_>>();
+
+    !terms.is_empty() && terms.iter().all(|term| meal_name.contains(term) || canteen_name.contains(term))
+}
+
+struct SearchProvider {
+    app: gtk::Application,
+    // the cached canteens, they are only read again when the cache changed
+    canteens: RefCell<Vec<Canteen>>,
+    cache_modified: Cell<Option<SystemTime>>,
+    cache_loaded: Cell<bool>,
+}
+
+impl SearchProvider {
+    fn search<'a, I: Iterator<Item = &'a String>>(&self, candidates: Option<I>, terms: &[String]) -> Vec<String> {
+        let today = Local::today().naive_local();
+        let canteens = self.canteens.borrow();
+
+        let results = canteens
+            .iter()
+            .flat_map(|canteen| {
+                let canteen_name = canteen::name(canteen);
+                canteen
+                    .days
+                    .iter()
+                    .filter(|day| day.date >= today)
+                    .flat_map(move |day| {
+                        day.meals
+                            .iter()
+                            .filter(|meal| matches(&canteen_name, &meal.name, terms))
+                            .map(move |meal| result_id(canteen, day.date, meal))
+                            .collect::<Vec<_>>()
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        match candidates {
+            Some(candidates) => candidates.filter(|id| results.contains(id)).cloned().collect(),
+            None => results,
+        }
+    }
+
+    // the initial result set is requested on every keystroke, so the cache is only read when it changed
+    fn update_canteens(&self) {
+        let modified = cache::modified();
+        if self.cache_loaded.get() && modified == self.cache_modified.get() {
+            return;
+        }
+
+        log::debug!("reading cached canteens for search provider");
+        self.canteens.replace(cache::read_history().into_iter().map(|(_desc, canteen)| canteen).collect());
+        self.cache_modified.set(modified);
+        self.cache_loaded.set(true);
+    }
+
+    fn initial_result_set(&self, terms: &[String]) -> Vec<String> {
+        log::debug!("searching for {:?} in cached canteens", terms);
+
+        self.update_canteens();
+        self.search(None::<std::slice::Iter<String>>, terms)
+    }
+
+    fn subsearch_result_set(&self, previous_results: &[String], terms: &[String]) -> Vec<String> {
+        log::debug!("refining search for {:?} in cached canteens", terms);
+
+        self.search(Some(previous_results.iter()), terms)
+    }
+
+    fn result_metas(&self, ids: &[String]) -> Vec<Vec<(&'static str, String)>> {
+        let canteens = self.canteens.borrow();
+
+        ids.iter()
+            .filter_map(|id| {
+                let (description, date, meal_name) = parse_result_id(id)?;
+                let canteen = canteens.iter().find(|canteen| canteen.description == description)?;
+                let day = canteen.days.iter().find(|day| day.date == date)?;
+                let meal = day.meals.iter().find(|meal| favorites::normalize(&meal.name) == meal_name)?;
+
+                Some(vec![
+                    ("id", id.clone()),
+                    ("name", meal.name.clone()),
+                    ("description", format!(
+                        "{} – {}, {}",
+                        canteen::name(canteen),
+                        translate_weekday(date.weekday()),
+                        date.format("%d.%m.%Y"),
+                    )),
+                    ("gicon", "io.github.fin_ger.OvGUCanteen".to_owned()),
+                ])
+            })
+            .collect()
+    }
+
+    fn activate_result(&self, id: &str) {
+        log::debug!("activating search result {}", id);
+
+        let (description, date, _meal_name) = match parse_result_id(id) {
+            Some(result) => result,
+            None => {
+                log::warn!("invalid search result {} activated", id);
+                return;
+            },
+        };
+
+        // opens the main window, which provides the day-selected action
+        self.app.activate();
+        self.app.activate_action(
+            "day-selected",
+            Some(&format!("{}:{}", description.id(), date.format("%Y-%m-%d")).to_variant()),
+        );
+    }
+
+    fn launch_search(&self, terms: &[String]) {
+        log::debug!("launching search for {:?}", terms);
+
+        let query = terms
+            .iter()
+            .filter(|term| !is_keyword(&term.to_lowercase()))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // opens the main window, which provides the search action
+        self.app.activate();
+        self.app.activate_action("search", Some(&query.to_variant()));
+    }
+}
+
+unsafe fn strv_child(parameters: *mut glib_sys::GVariant, idx: usize) -> Vec<String> {
+    let child = glib_sys::g_variant_get_child_value(parameters, idx);
+    let strv = glib_sys::g_variant_dup_strv(child, ptr::null_mut());
+    glib_sys::g_variant_unref(child);
+
+    <Vec<String> as FromGlibPtrContainer<*mut c_char, *mut *mut c_char>>::from_glib_full(strv)
+}
+
+unsafe fn string_child(parameters: *mut glib_sys::GVariant, idx: usize) -> String {
+    let child = glib_sys::g_variant_get_child_value(parameters, idx);
+    let string: String = from_glib_none(glib_sys::g_variant_get_string(child, ptr::null_mut()));
+    glib_sys::g_variant_unref(child);
+
+    string
+}
+
+// menu sources may provide names containing NUL characters, which cannot be passed to gio
+unsafe fn new_string(string: &str) -> *mut glib_sys::GVariant {
+    let c_string = CString::new(string.replace('\0', "")).unwrap_or_default();
+    glib_sys::g_variant_new_string(c_string.as_ptr())
+}
+
+unsafe fn new_tuple(child: *mut glib_sys::GVariant) -> *mut glib_sys::GVariant {
+    glib_sys::g_variant_new_tuple(&child, 1)
+}
+
+// creates the (as) reply, results containing NUL characters are skipped
+// as they could not be looked up again with their id
+unsafe fn strv_reply(strings: &[String]) -> *mut glib_sys::GVariant {
+    let c_strings = strings
+        .iter()
+        .filter_map(|string| CString::new(string.as_str()).ok())
+        .collect::<Vec<_>>();
+    let pointers = c_strings.iter().map(|string| string.as_ptr()).collect::<Vec<_>>();
+
+    new_tuple(glib_sys::g_variant_new_strv(pointers.as_ptr(), pointers.len() as isize))
+}
+
+// creates the (aa{sv}) reply
+unsafe fn metas_reply(metas: &[Vec<(&'static str, String)>]) -> *mut glib_sys::GVariant {
+    let list_builder = glib_sys::g_variant_builder_new(b"aa{sv}\0".as_ptr() as *const glib_sys::GVariantType);
+    for meta in metas {
+        let dict_builder = glib_sys::g_variant_builder_new(b"a{sv}\0".as_ptr() as *const glib_sys::GVariantType);
+        for (key, value) in meta {
+            glib_sys::g_variant_builder_add_value(
+                dict_builder,
+                glib_sys::g_variant_new_dict_entry(new_string(key), glib_sys::g_variant_new_variant(new_string(value))),
+            );
+        }
+        glib_sys::g_variant_builder_add_value(list_builder, glib_sys::g_variant_builder_end(dict_builder));
+        glib_sys::g_variant_builder_unref(dict_builder);
+    }
+    let reply = glib_sys::g_variant_builder_end(list_builder);
+    glib_sys::g_variant_builder_unref(list_builder);
+
+    new_tuple(reply)
+}
+
+unsafe extern "C" fn method_call(
+    _connection: *mut gio_sys::GDBusConnection,
+    _sender: *const c_char,
+    _object_path: *const c_char,
+    _interface_name: *const c_char,
+    method_name: *const c_char,
+    parameters: *mut glib_sys::GVariant,
+    invocation: *mut gio_sys::GDBusMethodInvocation,
+    user_data: glib_sys::gpointer,
+) {
+    let provider = &*(user_data as *const SearchProvider);
+    let method_name: String = from_glib_none(method_name);
+
+    // the method names and parameter types are checked by gio against the interface
+    let reply = match method_name.as_str() {
+        "GetInitialResultSet" => strv_reply(&provider.initial_result_set(&strv_child(parameters, 0))),
+        "GetSubsearchResultSet" => strv_reply(
+            &provider.subsearch_result_set(&strv_child(parameters, 0), &strv_child(parameters, 1)),
+        ),
+        "GetResultMetas" => metas_reply(&provider.result_metas(&strv_child(parameters, 0))),
+        "ActivateResult" => {
+            provider.activate_result(&string_child(parameters, 0));
+            ptr::null_mut()
+        },
+        "LaunchSearch" => {
+            provider.launch_search(&strv_child(parameters, 0));
+            ptr::null_mut()
+        },
+        _ => {
+            log::warn!("unknown search provider method {} called", method_name);
+            ptr::null_mut()
+        },
+    };
+
+    gio_sys::g_dbus_method_invocation_return_value(invocation, reply);
+}
+
+unsafe extern "C" fn free_provider(user_data: glib_sys::gpointer) {
+    drop(Box::from_raw(user_data as *mut SearchProvider));
+}
+
+pub fn register(app: &gtk::Application) -> Result<()> {
+    log::debug!("registering search provider");
+
+    unsafe {
+        // gio does not bind the connection of an application, it is owned by the application
+        let connection = gio_sys::g_application_get_dbus_connection(app.upcast_ref::<gio::Application>().to_glib_none().0);
+        if connection.is_null() {
+            bail!("Application is not connected to the session bus");
+        }
+
+        let mut error = ptr::null_mut();
+        let node_info = gio_sys::g_dbus_node_info_new_for_xml(INTERFACE_XML.to_glib_none().0, &mut error);
+        if node_info.is_null() {
+            let error: glib::Error = from_glib_full(error);
+            return Err(error).context("Failed to parse search provider interface");
+        }
+        let interface_info = gio_sys::g_dbus_node_info_lookup_interface(node_info, INTERFACE_NAME.to_glib_none().0);
+
+        // gio keeps the vtable around for as long as the object is registered
+        let vtable = Box::leak(Box::new(gio_sys::GDBusInterfaceVTable {
+            method_call: Some(method_call),
+            get_property: None,
+            set_property: None,
+            padding: [ptr::null_mut(); 8],
+        }));
+        let provider = Box::into_raw(Box::new(SearchProvider {
+            app: app.clone(),
+            canteens: RefCell::new(Vec::new()),
+            cache_modified: Cell::new(None),
+            cache_loaded: Cell::new(false),
+        }));
+
+        let registration_id = gio_sys::g_dbus_connection_register_object(
+            connection,
+            OBJECT_PATH.to_glib_none().0,
+            interface_info,
+            vtable,
+            provider as glib_sys::gpointer,
+            Some(free_provider),
+            &mut error,
+        );
+        gio_sys::g_dbus_node_info_unref(node_info);
+
+        if registration_id == 0 {
+            let error: glib::Error = from_glib_full(error);
+            return Err(error).context("Failed to register search provider");
+        }
+    }
+
+    log::debug!("finish registering search provider");
+
+    Ok(())
+}
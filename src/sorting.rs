@@ -0,0 +1,339 @@
+use std::cmp::Ordering;
+
+use gio::prelude::*;
+use gio::Settings;
+use gettextrs::gettext as t;
+
+use crate::favorites::{self, Favorites};
+use crate::model::{CanteenDescription, Meal, Symbol};
+use crate::price::PriceGroup;
+use crate::ratings::RatedDishes;
+
+// all settings keys which have an influence on the order of the meals of a day
+pub const SORTING_KEYS: &[&str] = &["meal-sort-mode", "group-meals-by-protein", "price-group"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    Menu,
+    Price,
+    Favorites,
+    Vegetarian,
+    Rating,
+}
+
+// all available sort modes with their settings value
+pub const SORT_MODES: &[(&str, SortMode)] = &[
+    ("menu", SortMode::Menu),
+    ("price", SortMode::Price),
+    ("favorites", SortMode::Favorites),
+    ("vegetarian", SortMode::Vegetarian),
+    ("rating", SortMode::Rating),
+];
+
+impl SortMode {
+    pub fn from_name(name: &str) -> Self {
+        SORT_MODES
+            .iter()
+            .find(|(mode_name, _mode)| *mode_name == name)
+            .map_or(Self::Menu, |(_mode_name, mode)| *mode)
+    }
+
+    pub fn translate(self) -> String {
+        match self {
+            Self::Menu => t("As on the menu"),
+            Self::Price => t("Cheapest first"),
+            Self::Favorites => t("Favorites first"),
+            Self::Vegetarian => t("Vegan and vegetarian first"),
+            Self::Rating => t("Best rated first"),
+        }
+    }
+}
+
+// the main protein of a meal, the meals of a day are grouped by it in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Protein {
+    Vegan,
+    Vegetarian,
+    Fish,
+    Poultry,
+    Pork,
+    Beef,
+    Lamb,
+    Game,
+    Other,
+}
+
+impl Protein {
+    // meat wins over fish and fish over the vegetarian markings, as
+    // the markings of the canteens are not always consistent
+    const PRIORITY: &'static [Self] = &[
+        Self::Pork,
+        Self::Beef,
+        Self::Poultry,
+        Self::Lamb,
+        Self::Game,
+        Self::Fish,
+        Self::Vegan,
+        Self::Vegetarian,
+    ];
+
+    pub fn of(meal: &Meal) -> Self {
+        let proteins = meal
+            .symbols
+            .iter()
+            .filter_map(|symbol| match symbol {
+                Symbol::Pig => Some(Self::Pork),
+                Symbol::Cattle => Some(Self::Beef),
+                Symbol::Poultry => Some(Self::Poultry),
+                Symbol::Lamb => Some(Self::Lamb),
+                Symbol::Game => Some(Self::Game),
+                Symbol::Fish => Some(Self::Fish),
+                Symbol::Vegan => Some(Self::Vegan),
+                Symbol::Vegetarian => Some(Self::Vegetarian),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        Self::PRIORITY
+            .iter()
+            .copied()
+            .find(|protein| proteins.contains(protein))
+            .unwrap_or(Self::Other)
+    }
+
+    pub fn translate(self) -> String {
+        match self {
+            Self::Vegan => t("Vegan"),
+            Self::Vegetarian => t("Vegetarian"),
+            Self::Fish => t("Fish"),
+            Self::Poultry => t("Poultry"),
+            Self::Pork => t("Pork"),
+            Self::Beef => t("Beef"),
+            Self::Lamb => t("Lamb"),
+            Self::Game => t("Game"),
+            Self::Other => t("Other dishes"),
+        }
+    }
+}
+
+// decides in which order the meals of a day are shown according to the user's preferences
+#[derive(Debug, Clone)]
+pub struct MealOrder {
+    mode: SortMode,
+    group_by_protein: bool,
+    price_group: PriceGroup,
+}
+
+impl MealOrder {
+    pub fn from_settings(settings: &Settings) -> Self {
+        log::debug!("creating MealOrder from settings");
+
+        let mode = settings
+            .get_string("meal-sort-mode")
+            .map_or(SortMode::Menu, |name| SortMode::from_name(&name));
+
+        Self {
+            mode,
+            group_by_protein: settings.get_boolean("group-meals-by-protein"),
+            price_group: PriceGroup::from_settings(Some(settings)),
+        }
+    }
+
+    pub const fn group_by_protein(&self) -> bool {
+        self.group_by_protein
+    }
+
+    // the order has to be updated when the favorites change
+    pub fn depends_on_favorites(&self) -> bool {
+        self.mode == SortMode::Favorites
+    }
+
+    // the order has to be updated when the ratings change
+    pub fn depends_on_ratings(&self) -> bool {
+        self.mode == SortMode::Rating
+    }
+
+    // returns the meals in the order they should be shown, meals which are equal
+    // according to the sort mode keep the order of the menu
+    pub fn sort<'a>(
+        &self,
+        canteen_desc: &CanteenDescription,
+        meals: &'a [Meal],
+        favorites: &Favorites,
+        ratings: &RatedDishes,
+    ) -> Vec<&'a Meal> {
+        let mut sorted = meals.iter().collect::<Vec<_>>();
+
+        match self.mode {
+            SortMode::Menu => {},
+            SortMode::Price => sorted.sort_by(|a, b| {
+                self.price_group
+                    .relevant_price(&a.price)
+                    .partial_cmp(&self.price_group.relevant_price(&b.price))
+                    .unwrap_or(Ordering::Equal)
+            }),
+            SortMode::Favorites => sorted.sort_by_key(|meal| !favorites::is_favorite(favorites, &meal.name)),
+            SortMode::Vegetarian => sorted.sort_by_key(|meal| match Protein::of(meal) {
+                Protein::Vegan => 0,
+                Protein::Vegetarian => 1,
+                _ => 2,
+            }),
+            SortMode::Rating => sorted.sort_by(|a, b| {
+                let average = |meal: &Meal| ratings.average(canteen_desc, &meal.name).map(|(average, _count)| average);
+                // unrated meals come last
+                match (average(a), average(b)) {
+                    (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+                    (Some(_a), None) => Ordering::Less,
+                    (None, Some(_b)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            }),
+        }
+
+        // the sorting is stable, so the meals of a group keep the order of the sort mode
+        if self.group_by_protein {
+            sorted.sort_by_key(|meal| Protein::of(meal));
+        }
+
+        sorted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::model::Price;
+    use crate::ratings::Rating;
+    use crate::store::Store;
+
+    // the guest price is ordered like the student price
+    fn meal(name: &str, student: f32, symbols: Vec<Symbol>) -> Meal {
+        Meal {
+            name: name.to_owned(),
+            price: Price {
+                student,
+                staff: student + 1.0,
+                guest: student + 2.0,
+            },
+            symbols,
+            additives: Vec::new(),
+            allergenics: Vec::new(),
+        }
+    }
+
+    const fn order(mode: SortMode, group_by_protein: bool, price_group: PriceGroup) -> MealOrder {
+        MealOrder {
+            mode,
+            group_by_protein,
+            price_group,
+        }
+    }
+
+    fn names(meals: &[&Meal]) -> Vec<String> {
+        meals.iter().map(|meal| meal.name.clone()).collect()
+    }
+
+    fn menu() -> Vec<Meal> {
+        vec![
+            meal("Schnitzel", 3.0, vec![Symbol::Pig]),
+            meal("Salad", 1.5, vec![Symbol::Vegan]),
+            meal("Salmon", 4.0, vec![Symbol::Fish]),
+            meal("Omelette", 1.5, vec![Symbol::Vegetarian]),
+        ]
+    }
+
+    fn sort(order: &MealOrder, favorites: &[&str], ratings: &RatedDishes) -> Vec<String> {
+        let meals = menu();
+        let favorites = Store::with_data(favorites.iter().map(|name| favorites::normalize(name)).collect());
+        names(&order.sort(&CanteenDescription::new("UniCampusLowerHall"), &meals, &favorites, ratings))
+    }
+
+    #[test]
+    fn unknown_sort_mode_keeps_the_menu() {
+        assert_eq!(SortMode::from_name("rating"), SortMode::Rating);
+        assert_eq!(SortMode::from_name("alphabetical"), SortMode::Menu);
+    }
+
+    #[test]
+    fn protein_prefers_meat_over_markings() {
+        assert_eq!(Protein::of(&meal("Bowl", 1.0, vec![Symbol::Vegan, Symbol::Pig])), Protein::Pork);
+        assert_eq!(Protein::of(&meal("Fish", 1.0, vec![Symbol::Vegetarian, Symbol::Fish])), Protein::Fish);
+        assert_eq!(Protein::of(&meal("Bowl", 1.0, vec![Symbol::Vegetarian, Symbol::Vegan])), Protein::Vegan);
+        assert_eq!(Protein::of(&meal("Water", 1.0, Vec::new())), Protein::Other);
+    }
+
+    #[test]
+    fn menu_order_is_kept() {
+        let sorted = sort(&order(SortMode::Menu, false, PriceGroup::All), &[], &RatedDishes::default());
+        assert_eq!(sorted, ["Schnitzel", "Salad", "Salmon", "Omelette"]);
+    }
+
+    #[test]
+    fn cheapest_first_is_stable() {
+        let sorted = sort(&order(SortMode::Price, false, PriceGroup::Guest), &[], &RatedDishes::default());
+        assert_eq!(sorted, ["Salad", "Omelette", "Schnitzel", "Salmon"]);
+    }
+
+    #[test]
+    fn favorites_first() {
+        let sorted = sort(
+            &order(SortMode::Favorites, false, PriceGroup::All),
+            &[" salmon", "OMELETTE"],
+            &RatedDishes::default(),
+        );
+        assert_eq!(sorted, ["Salmon", "Omelette", "Schnitzel", "Salad"]);
+    }
+
+    #[test]
+    fn vegan_before_vegetarian() {
+        let sorted = sort(&order(SortMode::Vegetarian, false, PriceGroup::All), &[], &RatedDishes::default());
+        assert_eq!(sorted, ["Salad", "Omelette", "Schnitzel", "Salmon"]);
+    }
+
+    #[test]
+    fn best_rated_first_and_unrated_last() {
+        let rating = |meal: &str, stars| Rating {
+            canteen: CanteenDescription::new("UniCampusLowerHall"),
+            meal: meal.to_owned(),
+            date: NaiveDate::from_ymd(2020, 5, 4),
+            stars,
+        };
+        let ratings = RatedDishes {
+            ratings: vec![rating("salad", 2), rating("salmon", 5)],
+            notes: Vec::new(),
+        };
+
+        let sorted = sort(&order(SortMode::Rating, false, PriceGroup::All), &[], &ratings);
+        assert_eq!(sorted, ["Salmon", "Salad", "Schnitzel", "Omelette"]);
+    }
+
+    #[test]
+    fn groups_keep_the_order_of_the_sort_mode() {
+        let meals = vec![
+            meal("Goulash", 3.0, vec![Symbol::Cattle]),
+            meal("Chili", 2.0, vec![Symbol::Vegan]),
+            meal("Burger", 2.5, vec![Symbol::Cattle]),
+            meal("Curry", 1.0, vec![Symbol::Vegan]),
+        ];
+        let favorites = Store::with_data(BTreeSet::new());
+        let sorted = order(SortMode::Price, true, PriceGroup::Student).sort(
+            &CanteenDescription::new("UniCampusLowerHall"),
+            &meals,
+            &favorites,
+            &RatedDishes::default(),
+        );
+        assert_eq!(names(&sorted), ["Curry", "Chili", "Burger", "Goulash"]);
+    }
+
+    #[test]
+    fn dependencies_follow_the_sort_mode() {
+        assert!(order(SortMode::Favorites, false, PriceGroup::All).depends_on_favorites());
+        assert!(!order(SortMode::Favorites, false, PriceGroup::All).depends_on_ratings());
+        assert!(order(SortMode::Rating, true, PriceGroup::All).depends_on_ratings());
+        assert!(!order(SortMode::Menu, true, PriceGroup::All).depends_on_favorites());
+    }
+}
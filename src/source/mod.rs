@@ -0,0 +1,74 @@
+pub mod openmensa;
+mod ovgu;
+
+pub use openmensa::OpenMensaSource;
+pub use ovgu::OvGUSource;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use gio::prelude::*;
+use gio::Settings;
+use itertools::Itertools;
+
+use crate::model::{Canteen, CanteenDescription};
+
+// a provider of canteen menus, e.g. the website of a student union or a public api
+#[async_trait]
+pub trait MenuSource: Send + Sync {
+    // identifies the source in the cache, e.g. ovgu
+    fn name(&self) -> &'static str;
+
+    // all canteens which can be fetched from this source
+    fn canteens(&self) -> Vec<CanteenDescription>;
+
+    // fetches all menus currently published for the canteen
+    async fn fetch(&self, canteen_desc: &CanteenDescription) -> Result<Canteen>;
+}
+
+// creates all menu sources configured in the settings,
+// without settings only the OvGU canteens are available
+pub fn from_settings(settings: Option<&Settings>) -> Vec<Box<dyn MenuSource>> {
+    let mut sources: Vec<Box<dyn MenuSource>> = vec![Box::new(OvGUSource)];
+
+    if let Some(settings) = settings {
+        let canteen_ids = settings
+            .get_strv("openmensa-canteens")
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        if !canteen_ids.is_empty() {
+            let url = settings
+                .get_string("openmensa-url")
+                .map(|url| url.to_string())
+                .unwrap_or_default();
+            log::debug!("using OpenMensa canteens {:?} from {}", canteen_ids, url);
+            sources.push(Box::new(OpenMensaSource::new(url, canteen_ids)));
+        }
+    }
+
+    sources
+}
+
+// all canteens of all sources in the order of the sources
+pub fn canteens(sources: &[Box<dyn MenuSource>]) -> Vec<CanteenDescription> {
+    sources.iter().flat_map(|source| source.canteens()).collect()
+}
+
+// the canteens enabled in the settings in their configured order,
+// without settings all canteens are enabled
+pub fn enabled_canteens(sources: &[Box<dyn MenuSource>], settings: Option<&Settings>) -> Vec<CanteenDescription> {
+    let available = canteens(sources);
+
+    match settings {
+        Some(settings) => settings
+            .get_strv("canteens")
+            .iter()
+            .map(|id| CanteenDescription::new(id.as_str()))
+            // canteens of sources which are not configured anymore are skipped
+            .filter(|canteen_desc| available.contains(canteen_desc))
+            .unique()
+            .collect(),
+        None => available,
+    }
+}
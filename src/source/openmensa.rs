@@ -0,0 +1,413 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use futures::future;
+use hyper::{body, Body, Client, Uri};
+use hyper_tls::HttpsConnector;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::model::{Additive, Allergenic, Canteen, CanteenDescription, Day, Meal, Price, Symbol};
+use crate::source::MenuSource;
+
+// prefix of the descriptions of OpenMensa canteens, followed by the OpenMensa canteen id
+const PREFIX: &str = "openmensa-";
+
+// the markings of OpenMensa meals are free text notes in the language of the canteen, a note marks
+// the meal if it contains one of the keywords as a whole word. keywords ending with '*' also match
+// words starting with them, e.g. the German compounds "Weizenmehl" or "Schweinefleisch"
+const SYMBOL_KEYWORDS: &[(Symbol, &[&str])] = &[
+    (Symbol::Pig, &["schwein*", "pork"]),
+    (Symbol::Cattle, &["rind", "rindfleisch", "beef"]),
+    (Symbol::Poultry, &["geflügel*", "hähnchen*", "huhn", "pute*", "poultry", "chicken"]),
+    (Symbol::Fish, &["fisch*", "fish"]),
+    (Symbol::Game, &["wild", "wildfleisch", "game"]),
+    (Symbol::Lamb, &["lamm*", "lamb"]),
+    (Symbol::Vegan, &["vegan"]),
+    (Symbol::Organic, &["bio", "organic"]),
+    (Symbol::Vegetarian, &["vegetarisch*", "vegetarian", "fleischlos"]),
+    (Symbol::Alcohol, &["alkohol*", "alcohol"]),
+    (Symbol::Garlic, &["knoblauch", "garlic"]),
+];
+
+const ADDITIVE_KEYWORDS: &[(Additive, &[&str])] = &[
+    (Additive::FoodColoring, &["farbstoff*", "coloring", "colouring"]),
+    (Additive::FoodPreservatives, &["konservierungsstoff*", "preservative*"]),
+    (Additive::AntiOxidants, &["antioxidationsmittel*", "antioxidant*"]),
+    (Additive::FlavorEnhancer, &["geschmacksverstärker*", "flavor enhancer*", "flavour enhancer*"]),
+    (Additive::Sulfurized, &["geschwefelt", "sulfurized", "sulphurized"]),
+    (Additive::Waxed, &["gewachst", "waxed"]),
+    (Additive::Blackend, &["geschwärzt", "blackened"]),
+    (Additive::Phosphates, &["phosphat*"]),
+    (Additive::Sweetener, &["süßungsmittel*", "sweetener*"]),
+    (Additive::Phenylalanine, &["phenylalanin*"]),
+];
+
+const ALLERGENIC_KEYWORDS: &[(Allergenic, &[&str])] = &[
+    (Allergenic::Wheat, &["weizen*", "wheat"]),
+    (Allergenic::Rye, &["roggen*", "rye"]),
+    (Allergenic::Barley, &["gerste*", "barley"]),
+    (Allergenic::Oat, &["hafer*", "oat", "oats"]),
+    (Allergenic::Spelt, &["dinkel*", "spelt"]),
+    (Allergenic::Kamut, &["kamut"]),
+    (Allergenic::Crustacean, &["krebstier*", "krebs", "crustacean*"]),
+    (Allergenic::Egg, &["ei", "eier", "hühnerei*", "egg", "eggs"]),
+    (Allergenic::Fish, &["fisch*", "fish"]),
+    (Allergenic::Peanut, &["erdnuss*", "erdnüsse", "peanut*"]),
+    (Allergenic::Soya, &["soja*", "soy", "soya", "soybean*"]),
+    (Allergenic::Lactose, &["milch*", "laktose", "lactose", "milk"]),
+    (Allergenic::Almond, &["mandel*", "almond*"]),
+    (Allergenic::Hazelnut, &["haselnuss*", "haselnüsse", "hazelnut*"]),
+    (Allergenic::Walnut, &["walnuss*", "walnüsse", "walnut*"]),
+    (Allergenic::Cashew, &["cashew*"]),
+    (Allergenic::PecanNut, &["pekannuss*", "pekannüsse", "pecan*"]),
+    (Allergenic::BrazilNut, &["paranuss*", "paranüsse", "brazil"]),
+    (Allergenic::Pistachio, &["pistazie*", "pistachio*"]),
+    (Allergenic::MacadamiaNut, &["macadamia*"]),
+    (Allergenic::QueenslandNut, &["queensland*"]),
+    (Allergenic::Celery, &["sellerie*", "celery"]),
+    (Allergenic::Mustard, &["senf*", "mustard"]),
+    (Allergenic::Sesame, &["sesam*"]),
+    (Allergenic::Sulphite, &["sulfit*", "schwefeldioxid*", "sulphite*", "sulfite*"]),
+    (Allergenic::Lupin, &["lupin*"]),
+    (Allergenic::Mollusc, &["weichtier*", "mollusc*", "mollusk*"]),
+];
+
+#[derive(Debug, Deserialize)]
+struct OpenMensaCanteen {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenMensaDay {
+    date: NaiveDate,
+    closed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenMensaPrices {
+    students: Option<f32>,
+    employees: Option<f32>,
+    others: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenMensaMeal {
+    name: String,
+    category: String,
+    prices: OpenMensaPrices,
+    notes: Vec<String>,
+}
+
+// fetches menus from an OpenMensa v2 api, e.g. https://openmensa.org/api/v2
+pub struct OpenMensaSource {
+    url: String,
+    canteen_ids: Vec<String>,
+}
+
+// returns the OpenMensa id of the canteen, or None if it is not an OpenMensa canteen
+pub fn canteen_id(canteen_desc: &CanteenDescription) -> Option<&str> {
+    let id = canteen_desc.id();
+    if id.starts_with(PREFIX) {
+        Some(&id[PREFIX.len()..])
+    } else {
+        None
+    }
+}
+
+// splits a note into its lowercase words, e.g. "Enthält Weizen (Gluten)" into enthält, weizen and gluten
+fn words(note: &str) -> Vec<String> {
+    note.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// whether the keyword appears in the words, the words of a keyword have to appear in a row
+fn contains_keyword(words: &[String], keyword: &str) -> bool {
+    let (keyword, prefix) = match keyword.strip_suffix('*') {
+        Some(keyword) => (keyword, true),
+        None => (keyword, false),
+    };
+    let keyword_words = keyword.split(' ').collect::<Vec<_>>();
+    let last = keyword_words.len() - 1;
+
+    words.windows(keyword_words.len()).any(|window| {
+        window.iter().zip(&keyword_words).enumerate().all(|(idx, (word, keyword_word))| {
+            if prefix && idx == last {
+                word.starts_with(keyword_word)
+            } else {
+                word == keyword_word
+            }
+        })
+    })
+}
+
+// all markings whose keywords appear in the notes of the meal
+fn markings<T: Clone>(notes: &[Vec<String>], keywords: &[(T, &[&str])]) -> Vec<T> {
+    keywords
+        .iter()
+        .filter(|(_marking, keywords)| {
+            notes.iter().any(|words| keywords.iter().any(|keyword| contains_keyword(words, keyword)))
+        })
+        .map(|(marking, _keywords)| marking.clone())
+        .collect()
+}
+
+fn convert_day(date: NaiveDate, meals: Vec<OpenMensaMeal>) -> Day {
+    let (side_dishes, meals): (Vec<_>, Vec<_>) = meals.into_iter().partition(|meal| {
+        let category = meal.category.to_lowercase();
+        category.contains("beilage") || category.contains("side")
+    });
+
+    Day {
+        date,
+        meals: meals
+            .into_iter()
+            .map(|meal| {
+                let notes = meal.notes.iter().map(|note| words(note)).collect::<Vec<_>>();
+                let prices = meal.prices;
+
+                Meal {
+                    name: meal.name,
+                    // the app always shows all price groups, missing prices are taken from another group
+                    price: Price {
+                        student: prices.students.or(prices.employees).or(prices.others).unwrap_or_default(),
+                        staff: prices.employees.or(prices.others).or(prices.students).unwrap_or_default(),
+                        guest: prices.others.or(prices.employees).or(prices.students).unwrap_or_default(),
+                    },
+                    symbols: markings(&notes, SYMBOL_KEYWORDS),
+                    additives: markings(&notes, ADDITIVE_KEYWORDS),
+                    allergenics: markings(&notes, ALLERGENIC_KEYWORDS),
+                }
+            })
+            .collect(),
+        side_dishes: side_dishes.into_iter().map(|meal| meal.name).collect(),
+    }
+}
+
+impl OpenMensaSource {
+    pub fn new(url: String, canteen_ids: Vec<String>) -> Self {
+        Self {
+            url,
+            canteen_ids,
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let uri = format!("{}/{}", self.url.trim_end_matches('/'), path)
+            .parse::<Uri>()
+            .with_context(|| format!("Invalid OpenMensa url {}", self.url))?;
+        log::debug!("requesting {}", uri);
+
+        // the https connector also takes care of plain http, e.g. for a local api
+        let client = Client::builder().build::<_, Body>(HttpsConnector::new());
+        let response = client
+            .get(uri.clone())
+            .await
+            .with_context(|| format!("Failed to request {}", uri))?;
+        if !response.status().is_success() {
+            bail!("Request to {} failed with {}", uri, response.status());
+        }
+
+        let bytes = body::to_bytes(response.into_body())
+            .await
+            .with_context(|| format!("Failed to receive {}", uri))?;
+        serde_json::from_slice(&bytes).with_context(|| format!("Could not parse response of {}", uri))
+    }
+}
+
+#[async_trait]
+impl MenuSource for OpenMensaSource {
+    fn name(&self) -> &'static str {
+        "openmensa"
+    }
+
+    fn canteens(&self) -> Vec<CanteenDescription> {
+        self.canteen_ids
+            .iter()
+            .map(|id| CanteenDescription::new(format!("{}{}", PREFIX, id)))
+            .collect()
+    }
+
+    async fn fetch(&self, canteen_desc: &CanteenDescription) -> Result<Canteen> {
+        log::debug!("loading canteen {:?} from OpenMensa", canteen_desc);
+        let id = canteen_id(canteen_desc)
+            .with_context(|| format!("{} is not an OpenMensa canteen", canteen_desc.id()))?;
+
+        let canteen: OpenMensaCanteen = self.get(&format!("canteens/{}", id)).await?;
+        let open_days = self
+            .get::<Vec<OpenMensaDay>>(&format!("canteens/{}/days", id))
+            .await?
+            .into_iter()
+            .filter(|day| !day.closed);
+
+        let days = future::try_join_all(open_days.map(|day| async move {
+            let meals: Vec<OpenMensaMeal> = self
+                .get(&format!("canteens/{}/days/{}/meals", id, day.date.format("%Y-%m-%d")))
+                .await?;
+            Ok::<_, anyhow::Error>(convert_day(day.date, meals))
+        }))
+        .await?;
+
+        Ok(Canteen {
+            description: canteen_desc.clone(),
+            name: Some(canteen.name),
+            source: None,
+            fetched: None,
+            days,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Request, Response, Server, StatusCode};
+    use serde::Serialize;
+
+    use super::*;
+
+    // the markings cannot be compared directly, so their names are compared
+    fn names<T: Serialize>(markings: &[T]) -> Vec<String> {
+        markings
+            .iter()
+            .map(|marking| serde_plain::to_string(marking).unwrap())
+            .collect()
+    }
+
+    fn notes(notes: &[&str]) -> Vec<Vec<String>> {
+        notes.iter().map(|note| words(note)).collect()
+    }
+
+    // serves the given bodies at their paths on a random local port and returns the api url,
+    // all other paths respond with 404
+    fn serve(routes: Vec<(&'static str, &'static str)>) -> String {
+        let make_service = make_service_fn(move |_conn| {
+            let routes = routes.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let body = routes
+                        .iter()
+                        .find(|(path, _body)| *path == request.uri().path())
+                        .map(|(_path, body)| *body);
+                    async move {
+                        Ok::<_, Infallible>(match body {
+                            Some(body) => Response::new(Body::from(body)),
+                            None => Response::builder()
+                                .status(StatusCode::NOT_FOUND)
+                                .body(Body::empty())
+                                .unwrap(),
+                        })
+                    }
+                }))
+            }
+        });
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let url = format!("http://{}/api/v2", server.local_addr());
+        tokio::spawn(server);
+
+        url
+    }
+
+    #[test]
+    fn markings_match_whole_words() {
+        let meal_notes = notes(&["Veggie-Burger", "Wildreis", "Biografie", "Eggplant"]);
+        assert!(markings(&meal_notes, ALLERGENIC_KEYWORDS).is_empty());
+        assert!(markings(&meal_notes, SYMBOL_KEYWORDS).is_empty());
+
+        let meal_notes = notes(&["mit Ei", "Bio-Qualität", "Wild"]);
+        assert_eq!(names(&markings(&meal_notes, ALLERGENIC_KEYWORDS)), names(&[Allergenic::Egg]));
+        assert_eq!(names(&markings(&meal_notes, SYMBOL_KEYWORDS)), names(&[Symbol::Game, Symbol::Organic]));
+    }
+
+    #[test]
+    fn markings_match_compounds_and_phrases() {
+        let meal_notes = notes(&["Weizenmehl", "Schweinefleisch", "with flavour enhancers"]);
+        assert_eq!(names(&markings(&meal_notes, ALLERGENIC_KEYWORDS)), names(&[Allergenic::Wheat]));
+        assert_eq!(names(&markings(&meal_notes, SYMBOL_KEYWORDS)), names(&[Symbol::Pig]));
+        assert_eq!(names(&markings(&meal_notes, ADDITIVE_KEYWORDS)), names(&[Additive::FlavorEnhancer]));
+    }
+
+    #[tokio::test]
+    async fn fetch_converts_canteen() {
+        let url = serve(vec![
+            ("/api/v2/canteens/42", r#"{"id": 42, "name": "Mensa Test"}"#),
+            (
+                "/api/v2/canteens/42/days",
+                r#"[{"date": "2020-05-04", "closed": false}, {"date": "2020-05-05", "closed": true}]"#,
+            ),
+            (
+                "/api/v2/canteens/42/days/2020-05-04/meals",
+                r#"[
+                    {
+                        "id": 1,
+                        "name": "Veggie-Burger",
+                        "category": "Hauptgericht",
+                        "prices": {"students": 2.5, "employees": 3.5, "pupils": null, "others": null},
+                        "notes": ["vegetarisch", "Weizen", "Eier"]
+                    },
+                    {
+                        "id": 2,
+                        "name": "Pommes",
+                        "category": "Beilagen",
+                        "prices": {"students": 1.0, "employees": 1.0, "pupils": null, "others": 1.5},
+                        "notes": []
+                    }
+                ]"#,
+            ),
+        ]);
+        let source = OpenMensaSource::new(url, vec!["42".to_owned()]);
+        let canteen_desc = CanteenDescription::new("openmensa-42");
+
+        let canteen = source.fetch(&canteen_desc).await.unwrap();
+
+        assert_eq!(canteen.description, canteen_desc);
+        assert_eq!(canteen.name.as_deref(), Some("Mensa Test"));
+        // closed days are skipped
+        assert_eq!(canteen.days.len(), 1);
+
+        let day = &canteen.days[0];
+        assert_eq!(day.date, NaiveDate::from_ymd(2020, 5, 4));
+        assert_eq!(day.side_dishes, vec!["Pommes".to_owned()]);
+        assert_eq!(day.meals.len(), 1);
+
+        let meal = &day.meals[0];
+        assert_eq!(meal.name, "Veggie-Burger");
+        assert_eq!(names(&meal.symbols), names(&[Symbol::Vegetarian]));
+        assert_eq!(names(&meal.allergenics), names(&[Allergenic::Wheat, Allergenic::Egg]));
+        assert!(meal.additives.is_empty());
+        // the guest price is missing and taken from the staff price
+        assert!((meal.price.student - 2.5).abs() < f32::EPSILON);
+        assert!((meal.price.staff - 3.5).abs() < f32::EPSILON);
+        assert!((meal.price.guest - 3.5).abs() < f32::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn fetch_fails_for_unknown_canteen() {
+        let url = serve(Vec::new());
+        let source = OpenMensaSource::new(url, vec!["42".to_owned()]);
+
+        let error = source.fetch(&CanteenDescription::new("openmensa-42")).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("404"));
+    }
+
+    #[tokio::test]
+    async fn fetch_fails_for_invalid_response() {
+        let url = serve(vec![("/api/v2/canteens/42", "<html>maintenance</html>")]);
+        let source = OpenMensaSource::new(url, vec!["42".to_owned()]);
+
+        let error = source.fetch(&CanteenDescription::new("openmensa-42")).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("Could not parse"));
+    }
+
+    #[tokio::test]
+    async fn fetch_rejects_other_canteens() {
+        let source = OpenMensaSource::new("http://127.0.0.1:1/api/v2".to_owned(), Vec::new());
+
+        assert!(source.fetch(&CanteenDescription::new("UniCampusLowerHall")).await.is_err());
+    }
+}
@@ -0,0 +1,95 @@
+#[cfg(feature = "test-with-local-files")]
+use std::fs::File;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+
+use crate::model::{Canteen, CanteenDescription, Day, Meal, Price};
+use crate::source::MenuSource;
+
+lazy_static! {
+    // all canteens of the OvGU
+    static ref CANTEENS: Vec<ovgu_canteen::CanteenDescription> = vec![
+        ovgu_canteen::CanteenDescription::UniCampusLowerHall,
+        ovgu_canteen::CanteenDescription::UniCampusUpperHall,
+        ovgu_canteen::CanteenDescription::Kellercafe,
+        ovgu_canteen::CanteenDescription::Herrenkrug,
+        ovgu_canteen::CanteenDescription::Stendal,
+        ovgu_canteen::CanteenDescription::Wernigerode,
+        ovgu_canteen::CanteenDescription::DomCafeteHalberstadt,
+    ];
+}
+
+// scrapes the menus from the website of the Studentenwerk Magdeburg
+pub struct OvGUSource;
+
+#[cfg_attr(feature = "test-with-local-files", allow(dead_code))]
+fn convert(canteen_desc: &CanteenDescription, canteen: ovgu_canteen::Canteen) -> Canteen {
+    Canteen {
+        description: canteen_desc.clone(),
+        name: None,
+        source: None,
+        fetched: None,
+        days: canteen
+            .days
+            .into_iter()
+            .map(|day| Day {
+                date: day.date,
+                meals: day
+                    .meals
+                    .into_iter()
+                    .map(|meal| Meal {
+                        name: meal.name,
+                        price: Price {
+                            student: meal.price.student,
+                            staff: meal.price.staff,
+                            guest: meal.price.guest,
+                        },
+                        symbols: meal.symbols,
+                        additives: meal.additives,
+                        allergenics: meal.allergenics,
+                    })
+                    .collect(),
+                side_dishes: day.side_dishes,
+            })
+            .collect(),
+    }
+}
+
+#[async_trait]
+impl MenuSource for OvGUSource {
+    fn name(&self) -> &'static str {
+        "ovgu"
+    }
+
+    fn canteens(&self) -> Vec<CanteenDescription> {
+        CANTEENS
+            .iter()
+            .map(|canteen_desc| CanteenDescription::new(serde_plain::to_string(canteen_desc).unwrap()))
+            .collect()
+    }
+
+    #[cfg(feature = "test-with-local-files")]
+    async fn fetch(&self, canteen_desc: &CanteenDescription) -> Result<Canteen> {
+        log::debug!("loading canteen {:?} from local file", canteen_desc);
+        let file = File::open("data/canteens.json").context("'data/canteens.json' not found!")?;
+        let mut canteens: Vec<Canteen> =
+            serde_json::from_reader(&file).context("Could not parse 'data/cateens.json'")?;
+        canteens
+            .drain(..)
+            .find(|c| &c.description == canteen_desc)
+            .context("Canteen not found!")
+    }
+
+    #[cfg(not(feature = "test-with-local-files"))]
+    async fn fetch(&self, canteen_desc: &CanteenDescription) -> Result<Canteen> {
+        log::debug!("loading canteen {:?}", canteen_desc);
+        let ovgu_desc = serde_plain::from_str::<ovgu_canteen::CanteenDescription>(canteen_desc.id())
+            .with_context(|| format!("{} is not a canteen of the OvGU", canteen_desc.id()))?;
+        let canteen = failure::ResultExt::compat(ovgu_canteen::Canteen::new(ovgu_desc).await)
+            .context("Failed to fetch canteen")?;
+
+        Ok(convert(canteen_desc, canteen))
+    }
+}
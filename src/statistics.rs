@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::archive::Archive;
+use crate::favorites;
+use crate::model::{Canteen, CanteenDescription, Day, Price};
+
+// statistics about all known days of all canteens, either cached or archived
+pub struct Statistics {
+    days: BTreeMap<(CanteenDescription, NaiveDate), Day>,
+}
+
+// a dish with the name it had on its latest menu and on how many days it was served
+#[derive(Debug, Clone)]
+pub struct Dish {
+    pub key: String,
+    pub name: String,
+    pub count: usize,
+}
+
+// the monday of the week of the date
+fn week_of(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+}
+
+impl Statistics {
+    pub fn new(canteens: &[Canteen], archive: &Archive) -> Self {
+        let mut days = archive
+            .iter()
+            .map(|(canteen_desc, day)| ((canteen_desc.clone(), day.date), day.clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        // the cache holds the latest version of a day
+        for canteen in canteens {
+            for day in &canteen.days {
+                days.insert((canteen.description.clone(), day.date), day.clone());
+            }
+        }
+
+        Self { days }
+    }
+
+    pub fn canteens(&self) -> BTreeSet<CanteenDescription> {
+        self.days.keys().map(|(canteen_desc, _date)| canteen_desc.clone()).collect()
+    }
+
+    fn days_of<'a>(&'a self, canteen_desc: &'a CanteenDescription) -> impl Iterator<Item = &'a Day> + 'a {
+        self.days
+            .iter()
+            .filter(move |((desc, _date), _day)| desc == canteen_desc)
+            .map(|(_key, day)| day)
+    }
+
+    pub fn day_count(&self, canteen_desc: &CanteenDescription) -> usize {
+        self.days_of(canteen_desc).count()
+    }
+
+    // all dishes of the canteen, the most frequent ones come first
+    pub fn dishes(&self, canteen_desc: &CanteenDescription) -> Vec<Dish> {
+        // dishes are identified by their normalized name, like favorites
+        let mut dishes = BTreeMap::<String, Dish>::new();
+        for day in self.days_of(canteen_desc) {
+            for meal in &day.meals {
+                let key = favorites::normalize(&meal.name);
+                let dish = dishes.entry(key.clone()).or_insert_with(|| Dish {
+                    key,
+                    name: meal.name.clone(),
+                    count: 0,
+                });
+                // the days are ordered by date, so the latest name is kept
+                dish.name = meal.name.clone();
+                dish.count += 1;
+            }
+        }
+
+        let mut dishes = dishes.into_iter().map(|(_key, dish)| dish).collect::<Vec<_>>();
+        dishes.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+        dishes
+    }
+
+    // how many meals carry each symbol in every week, the symbols are given by their serde name
+    pub fn symbols_per_week(&self, canteen_desc: &CanteenDescription) -> Vec<(NaiveDate, BTreeMap<String, usize>)> {
+        let mut weeks = BTreeMap::<NaiveDate, BTreeMap<String, usize>>::new();
+        for day in self.days_of(canteen_desc) {
+            let week = weeks.entry(week_of(day.date)).or_default();
+            for symbol in day.meals.iter().flat_map(|meal| &meal.symbols) {
+                if let Ok(name) = serde_plain::to_string(symbol) {
+                    *week.entry(name).or_default() += 1;
+                }
+            }
+        }
+
+        weeks.into_iter().collect()
+    }
+
+    // the prices of the dish on every day it got served
+    pub fn price_trend(&self, canteen_desc: &CanteenDescription, dish_key: &str) -> Vec<(NaiveDate, Price)> {
+        self.days_of(canteen_desc)
+            .filter_map(|day| {
+                day.meals
+                    .iter()
+                    .find(|meal| favorites::normalize(&meal.name) == dish_key)
+                    .map(|meal| (day.date, meal.price.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Meal, Symbol};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2020, 5, day)
+    }
+
+    fn meal(name: &str, student: f32, symbols: Vec<Symbol>) -> Meal {
+        Meal {
+            name: name.to_owned(),
+            price: Price {
+                student,
+                staff: student + 1.0,
+                guest: student + 2.0,
+            },
+            symbols,
+            additives: Vec::new(),
+            allergenics: Vec::new(),
+        }
+    }
+
+    fn canteen(id: &str, days: Vec<(u32, Vec<Meal>)>) -> Canteen {
+        Canteen {
+            description: CanteenDescription::new(id),
+            name: None,
+            source: None,
+            fetched: None,
+            days: days
+                .into_iter()
+                .map(|(day, meals)| Day {
+                    date: date(day),
+                    meals,
+                    side_dishes: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn statistics() -> Statistics {
+        let archived = canteen(
+            "UniCampusLowerHall",
+            vec![
+                (4, vec![meal("Pasta", 2.0, vec![Symbol::Vegan]), meal("Soup", 1.0, vec![Symbol::Vegan])]),
+                (5, vec![meal("Pasta", 2.0, vec![Symbol::Vegan])]),
+            ],
+        );
+        // the cached version of monday replaces the archived one
+        let cached = canteen(
+            "UniCampusLowerHall",
+            vec![
+                (4, vec![meal("pasta ", 2.5, vec![Symbol::Vegan]), meal("Curry", 3.0, vec![Symbol::Pig])]),
+                (11, vec![meal("PASTA", 3.0, vec![Symbol::Vegan, Symbol::Organic])]),
+            ],
+        );
+        let other = canteen("Herrenkrug", vec![(4, vec![meal("Pasta", 2.0, Vec::new())])]);
+
+        Statistics::new(&[cached, other], &Archive::from_canteens(&[archived]))
+    }
+
+    #[test]
+    fn week_starts_on_monday() {
+        assert_eq!(week_of(date(4)), date(4));
+        assert_eq!(week_of(date(7)), date(4));
+        assert_eq!(week_of(date(10)), date(4));
+        assert_eq!(week_of(date(11)), date(11));
+        assert_eq!(week_of(NaiveDate::from_ymd(2021, 1, 1)), NaiveDate::from_ymd(2020, 12, 28));
+    }
+
+    #[test]
+    fn days_of_cache_and_archive_are_joined() {
+        let statistics = statistics();
+
+        assert_eq!(statistics.canteens().len(), 2);
+        assert_eq!(statistics.day_count(&CanteenDescription::new("UniCampusLowerHall")), 3);
+        assert_eq!(statistics.day_count(&CanteenDescription::new("Herrenkrug")), 1);
+        assert_eq!(statistics.day_count(&CanteenDescription::new("Kellercafe")), 0);
+    }
+
+    #[test]
+    fn dishes_are_counted_by_normalized_name() {
+        let dishes = statistics().dishes(&CanteenDescription::new("UniCampusLowerHall"));
+        let dishes = dishes
+            .iter()
+            .map(|dish| (dish.key.as_str(), dish.name.as_str(), dish.count))
+            .collect::<Vec<_>>();
+
+        // the soup of the archived monday got replaced by the cached menu
+        assert_eq!(dishes, [("pasta", "PASTA", 3), ("curry", "Curry", 1)]);
+    }
+
+    #[test]
+    fn symbols_are_counted_per_week() {
+        let weeks = statistics().symbols_per_week(&CanteenDescription::new("UniCampusLowerHall"));
+        assert_eq!(weeks.len(), 2);
+
+        let (first_monday, first_symbols) = &weeks[0];
+        assert_eq!(*first_monday, date(4));
+        assert_eq!(first_symbols.get("Vegan"), Some(&2));
+        assert_eq!(first_symbols.get("Pig"), Some(&1));
+        assert_eq!(first_symbols.len(), 2);
+
+        let (second_monday, second_symbols) = &weeks[1];
+        assert_eq!(*second_monday, date(11));
+        assert_eq!(second_symbols.get("Vegan"), Some(&1));
+        assert_eq!(second_symbols.get("Organic"), Some(&1));
+    }
+
+    #[test]
+    fn price_trend_is_ordered_by_date() {
+        let statistics = statistics();
+        let lower_hall = CanteenDescription::new("UniCampusLowerHall");
+        let trend = statistics
+            .price_trend(&lower_hall, "pasta")
+            .iter()
+            .map(|(date, price)| (*date, price.student))
+            .collect::<Vec<_>>();
+
+        assert_eq!(trend, [(date(4), 2.5), (date(5), 2.0), (date(11), 3.0)]);
+        assert!(statistics.price_trend(&lower_hall, "soup").is_empty());
+    }
+}
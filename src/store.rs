@@ -0,0 +1,239 @@
+use std::cell::{Cell, Ref, RefCell};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::util;
+
+type Listener<T> = Rc<dyn Fn(&T)>;
+type Listeners<T> = Rc<RefCell<Vec<(ListenerId, Listener<T>)>>>;
+
+// identifies a listener connected to a Store, used to disconnect it again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListenerId(usize);
+
+// a JSON file in the data directory of the application holding user data,
+// listeners get notified whenever the data gets updated
+pub struct Store<T> {
+    file_name: &'static str,
+    data: Rc<RefCell<T>>,
+    listeners: Listeners<T>,
+    next_listener_id: Rc<Cell<usize>>,
+}
+
+// a derived Clone would require T: Clone, but only the handles are cloned here
+impl<T> Clone for Store<T> {
+    fn clone(&self) -> Self {
+        Self {
+            file_name: self.file_name,
+            data: Rc::clone(&self.data),
+            listeners: Rc::clone(&self.listeners),
+            next_listener_id: Rc::clone(&self.next_listener_id),
+        }
+    }
+}
+
+#[cfg(test)]
+impl<T> Store<T> {
+    // a store holding the given data without reading its file, tests must not update it
+    pub fn with_data(data: T) -> Self {
+        Self {
+            file_name: "test.json",
+            data: Rc::new(RefCell::new(data)),
+            listeners: Rc::new(RefCell::new(Vec::new())),
+            next_listener_id: Rc::new(Cell::new(0)),
+        }
+    }
+}
+
+fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let file = File::open(path).context("Could not open file")?;
+    serde_json::from_reader(BufReader::new(file)).context("Could not parse file")
+}
+
+fn write_file<T: Serialize>(path: &Path, data: &T) -> Result<()> {
+    let content = serde_json::to_vec_pretty(data).context("Could not serialize data")?;
+    util::write_atomically(path, &content)
+}
+
+// moves the file aside, e.g. favorites.json to favorites.json.broken-20201018T120000
+fn back_up_file(path: &Path, now: DateTime<Local>) -> Result<PathBuf> {
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(format!(".broken-{}", now.format("%Y%m%dT%H%M%S")));
+    let backup_path = PathBuf::from(backup_path);
+    fs::rename(path, &backup_path).context("Could not move file")?;
+
+    Ok(backup_path)
+}
+
+impl<T: Serialize + DeserializeOwned + Default + Clone> Store<T> {
+    // loads the store from the data directory, a missing or broken file results in an empty store,
+    // a broken file is moved aside, so it does not get overwritten by the next update
+    pub fn load(file_name: &'static str) -> Self {
+        log::debug!("loading store {}", file_name);
+
+        let data = match Self::read(file_name) {
+            Ok(Some(data)) => data,
+            Ok(None) => {
+                log::debug!("store {} does not exist yet", file_name);
+                T::default()
+            },
+            Err(e) => {
+                log::warn!("failed to load store {}: {:#}", file_name, e);
+                if let Err(e) = Self::back_up(file_name) {
+                    log::error!("failed to back up store {}: {:#}", file_name, e);
+                }
+                T::default()
+            },
+        };
+
+        Self {
+            file_name,
+            data: Rc::new(RefCell::new(data)),
+            listeners: Rc::new(RefCell::new(Vec::new())),
+            next_listener_id: Rc::new(Cell::new(0)),
+        }
+    }
+
+    fn read(file_name: &str) -> Result<Option<T>> {
+        let xdg = xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk")
+            .context("Could not find data directory")?;
+        let path = match xdg.find_data_file(file_name) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        read_file(&path).context(format!("Could not read '{}'", file_name)).map(Some)
+    }
+
+    fn back_up(file_name: &str) -> Result<()> {
+        let xdg = xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk")
+            .context("Could not find data directory")?;
+        if let Some(path) = xdg.find_data_file(file_name) {
+            let backup_path = back_up_file(&path, Local::now())?;
+            log::warn!("moved broken store {} to {:?}", file_name, backup_path);
+        }
+
+        Ok(())
+    }
+
+    fn write(&self) -> Result<()> {
+        log::debug!("writing store {}", self.file_name);
+
+        let xdg = xdg::BaseDirectories::with_prefix("ovgu-canteen-gtk")
+            .context("Could not find data directory")?;
+        let path = xdg
+            .place_data_file(self.file_name)
+            .context(format!("Could not place '{}'", self.file_name))?;
+        write_file(&path, &*self.data.borrow()).context(format!("Could not write '{}'", self.file_name))
+    }
+
+    pub fn get(&self) -> Ref<T> {
+        self.data.borrow()
+    }
+
+    // applies the updater to the data, writes the store to disk and notifies all listeners
+    // if the updater reports a change by returning true
+    pub fn update<F: FnOnce(&mut T) -> bool>(&self, updater: F) {
+        if !updater(&mut self.data.borrow_mut()) {
+            log::debug!("store {} did not change", self.file_name);
+            return;
+        }
+
+        if let Err(e) = self.write() {
+            log::error!("error writing store {}: {:#}", self.file_name, e);
+        }
+
+        // listeners may connect or disconnect while being notified, so notify a snapshot
+        let listeners = self
+            .listeners
+            .borrow()
+            .iter()
+            .map(|(_id, listener)| Rc::clone(listener))
+            .collect::<Vec<_>>();
+        // listeners may update the store again, so the data must not be borrowed while notifying
+        let data = self.data.borrow().clone();
+        for listener in listeners {
+            listener(&data);
+        }
+    }
+
+    pub fn connect_changed<F: Fn(&T) + 'static>(&self, listener: F) -> ListenerId {
+        let id = ListenerId(self.next_listener_id.get());
+        self.next_listener_id.set(id.0 + 1);
+        self.listeners.borrow_mut().push((id, Rc::new(listener)));
+
+        id
+    }
+
+    pub fn disconnect(&self, id: ListenerId) {
+        self.listeners.borrow_mut().retain(|(listener_id, _listener)| *listener_id != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    // an empty directory only used by a single test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ovgu-canteen-gtk-store-{}-{}", name, std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut file_names = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        file_names.sort();
+        file_names
+    }
+
+    #[test]
+    fn written_file_replaces_the_old_one() {
+        let dir = test_dir("write");
+        let path = dir.join("favorites.json");
+        fs::write(&path, "[\"soup\"]").unwrap();
+
+        let favorites = vec!["pasta".to_owned()].into_iter().collect::<BTreeSet<_>>();
+        write_file(&path, &favorites).unwrap();
+
+        // no temporary files are left behind
+        assert_eq!(file_names(&dir), ["favorites.json"]);
+        assert_eq!(read_file::<BTreeSet<String>>(&path).unwrap(), favorites);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn broken_file_is_moved_aside() {
+        let dir = test_dir("backup");
+        let path = dir.join("ratings.json");
+        // a write got interrupted in older versions
+        fs::write(&path, "{\"ratings\": [").unwrap();
+
+        assert!(read_file::<BTreeSet<String>>(&path).is_err());
+        let backup_path = back_up_file(&path, Local.ymd(2020, 10, 18).and_hms(12, 0, 0)).unwrap();
+
+        assert_eq!(backup_path, dir.join("ratings.json.broken-20201018T120000"));
+        assert_eq!(file_names(&dir), ["ratings.json.broken-20201018T120000"]);
+        assert_eq!(fs::read_to_string(&backup_path).unwrap(), "{\"ratings\": [");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
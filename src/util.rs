@@ -1,6 +1,15 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::fs::{self, File};
 use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::Path;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use futures::stream::{self, TryStreamExt};
 use itertools::{EitherOrBoth, Itertools};
@@ -75,7 +84,17 @@ where
 
 pub struct AdjustingVec<T, E> {
     data: Vec<T>,
+    // the hashed keys of the items in data, None if the item got adjusted by its index
+    keys: Vec<Option<u64>>,
     handlers: Box<dyn AdjustingVecHandlers<T, E>>,
+    // moves the widget of an item to the given index, only used by adjust_keyed
+    mover: Box<dyn Fn(&T, usize)>,
+}
+
+fn hash_key<K: Hash>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
 }
 
 impl<T: std::fmt::Debug, E> std::fmt::Debug for AdjustingVec<T, E> {
@@ -95,6 +114,7 @@ impl<T, E> AdjustingVec<T, E> {
     {
         Self {
             data: Vec::new(),
+            keys: Vec::new(),
             handlers: Box::new(AdjustingVecHandlersImpl {
                 // create pinned boxes for the futures returned by creator and destroyer
                 creator: move || Box::pin(creator()) as Pin<Box<dyn Future<Output = Result<T, E>>>>,
@@ -102,9 +122,30 @@ impl<T, E> AdjustingVec<T, E> {
                     Box::pin(destroyer(item)) as Pin<Box<dyn Future<Output = Result<(), E>>>>
                 },
             }),
+            mover: Box::new(|_item, _idx| {}),
         }
     }
 
+    // like new, but the items can be adjusted by their key, the mover places
+    // the widget of an item at the given index of its container
+    pub fn new_keyed<C, FC, D, FD, M>(creator: C, destroyer: D, mover: M) -> Self
+    where
+        C: 'static + Fn() -> FC,
+        FC: 'static + Future<Output = Result<T, E>>,
+        D: 'static + Fn(T) -> FD,
+        FD: 'static + Future<Output = Result<(), E>>,
+        M: 'static + Fn(&T, usize),
+    {
+        Self {
+            mover: Box::new(mover),
+            ..Self::new(creator, destroyer)
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
     pub async fn adjust<A, I, F, FT>(&mut self, iterable: I, mapper: F) -> Result<(), E>
     where
         I: IntoIterator<Item = A>,
@@ -128,7 +169,217 @@ impl<T, E> AdjustingVec<T, E> {
             })
             .try_collect()
             .await?;
+        self.keys = self.data.iter().map(|_item| None).collect();
 
         Ok(())
     }
+
+    // adjusts the items like adjust, but matches the current items with the new ones by their key,
+    // so inserting an item at the top does not update all items below. matched items are moved to
+    // their new index, unmatched items are reused for new keys and only the remaining ones get
+    // created or destroyed. the mapper is still called for every item, so it has to skip items
+    // whose content did not change itself.
+    pub async fn adjust_keyed<A, I, K, KF, F, FT>(&mut self, iterable: I, key: KF, mapper: F) -> Result<(), E>
+    where
+        I: IntoIterator<Item = A>,
+        K: Hash,
+        KF: Fn(&A) -> K,
+        F: Fn(T, A) -> FT,
+        FT: Future<Output = Result<T, E>>,
+    {
+        let items = iterable
+            .into_iter()
+            .map(|item| (hash_key(&key(&item)), item))
+            .collect::<Vec<_>>();
+        let mut current = self
+            .keys
+            .drain(..)
+            .zip(self.data.drain(..))
+            .map(Some)
+            .collect::<Vec<_>>();
+
+        // items with the same key are matched in order
+        let mut matched = items
+            .iter()
+            .map(|(item_key, _item)| {
+                current
+                    .iter_mut()
+                    .find(|entry| entry.as_ref().map_or(false, |(current_key, _current)| *current_key == Some(*item_key)))
+                    .and_then(Option::take)
+                    .map(|(_current_key, current)| current)
+            })
+            .collect::<Vec<_>>();
+
+        // reusing an item is cheaper than destroying one and creating another one
+        let mut unmatched = current.into_iter().flatten().map(|(_key, current)| current).collect::<VecDeque<_>>();
+        for slot in matched.iter_mut().filter(|slot| slot.is_none()) {
+            *slot = unmatched.pop_front();
+        }
+
+        // destroy before creating, so creators can append their widgets to the remaining ones
+        for current in unmatched {
+            self.handlers.destroy(current).await?;
+        }
+
+        for (idx, ((item_key, item), current)) in items.into_iter().zip(matched).enumerate() {
+            let current = match current {
+                Some(current) => current,
+                None => self.handlers.create().await?,
+            };
+            let next = mapper(current, item).await?;
+            // all items before idx are already in place
+            (self.mover)(&next, idx);
+            self.data.push(next);
+            self.keys.push(Some(item_key));
+        }
+
+        Ok(())
+    }
+}
+
+// distinguishes the temporary files of writes within this process,
+// the process id distinguishes them from other processes, e.g. the command-line
+static TMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// writes the file next to its destination first and renames it afterwards,
+// so a crash while writing never leaves a truncated file behind
+pub fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let dir = path.parent().context("File has no directory")?;
+    let file_name = path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .context("File has no name")?;
+    let tmp_path = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed),
+    ));
+
+    let mut tmp_file = File::create(&tmp_path).context("Could not create temporary file")?;
+    let written = tmp_file
+        .write_all(content)
+        .and_then(|()| tmp_file.sync_all())
+        .and_then(|()| fs::rename(&tmp_path, path));
+    if let Err(e) = written {
+        // the temporary file is of no use without being renamed
+        if let Err(e) = fs::remove_file(&tmp_path) {
+            log::warn!("could not remove temporary file {:?}: {:#}", tmp_path, e);
+        }
+        return Err(e).context(format!("Could not write {:?}", path));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Calls {
+        created: usize,
+        destroyed: Vec<String>,
+        moved: Vec<(String, usize)>,
+    }
+
+    // the items are the number of their creation and the value they show
+    type Item = (usize, String);
+
+    fn keyed_vec(calls: &Rc<RefCell<Calls>>) -> AdjustingVec<Item, ()> {
+        AdjustingVec::new_keyed(
+            enclose! { (calls) move || {
+                enclose! { (calls) async move {
+                    calls.borrow_mut().created += 1;
+                    Ok((calls.borrow().created, String::new()))
+                }}
+            }},
+            enclose! { (calls) move |item: Item| {
+                enclose! { (calls) async move {
+                    calls.borrow_mut().destroyed.push(item.1);
+                    Ok(())
+                }}
+            }},
+            enclose! { (calls) move |item: &Item, idx| {
+                calls.borrow_mut().moved.push((item.1.clone(), idx));
+            }},
+        )
+    }
+
+    fn adjust(vec: &mut AdjustingVec<Item, ()>, values: &[&str]) -> Vec<Item> {
+        block_on(vec.adjust_keyed(
+            values.iter().map(ToString::to_string),
+            Clone::clone,
+            |(number, _old), value| async move { Ok((number, value)) },
+        ))
+        .unwrap();
+
+        vec.iter().cloned().collect()
+    }
+
+    fn item(number: usize, value: &str) -> Item {
+        (number, value.to_owned())
+    }
+
+    #[test]
+    fn adjust_keyed_keeps_items_of_known_keys() {
+        let calls = Rc::new(RefCell::new(Calls::default()));
+        let mut vec = keyed_vec(&calls);
+
+        assert_eq!(adjust(&mut vec, &["a", "b", "c"]), vec![item(1, "a"), item(2, "b"), item(3, "c")]);
+        assert_eq!(calls.borrow().created, 3);
+
+        // reorder
+        calls.borrow_mut().moved.clear();
+        assert_eq!(adjust(&mut vec, &["c", "a", "b"]), vec![item(3, "c"), item(1, "a"), item(2, "b")]);
+        assert_eq!(calls.borrow().created, 3);
+        assert!(calls.borrow().destroyed.is_empty());
+        assert_eq!(
+            calls.borrow().moved,
+            vec![("c".to_owned(), 0), ("a".to_owned(), 1), ("b".to_owned(), 2)],
+        );
+
+        // insert
+        assert_eq!(
+            adjust(&mut vec, &["x", "c", "a", "b"]),
+            vec![item(4, "x"), item(3, "c"), item(1, "a"), item(2, "b")],
+        );
+        assert_eq!(calls.borrow().created, 4);
+        assert!(calls.borrow().destroyed.is_empty());
+
+        // remove
+        assert_eq!(adjust(&mut vec, &["c", "b"]), vec![item(3, "c"), item(2, "b")]);
+        assert_eq!(calls.borrow().created, 4);
+        assert_eq!(calls.borrow().destroyed, vec!["x".to_owned(), "a".to_owned()]);
+    }
+
+    #[test]
+    fn adjust_keyed_matches_duplicate_keys_in_order() {
+        let calls = Rc::new(RefCell::new(Calls::default()));
+        let mut vec = keyed_vec(&calls);
+
+        adjust(&mut vec, &["b", "c"]);
+        assert_eq!(adjust(&mut vec, &["b", "b", "c"]), vec![item(1, "b"), item(3, "b"), item(2, "c")]);
+        assert_eq!(calls.borrow().created, 3);
+
+        // the first of the duplicates keeps its item
+        assert_eq!(adjust(&mut vec, &["c", "b"]), vec![item(2, "c"), item(1, "b")]);
+        assert_eq!(calls.borrow().destroyed, vec!["b".to_owned()]);
+    }
+
+    #[test]
+    fn adjust_keyed_reuses_unmatched_items() {
+        let calls = Rc::new(RefCell::new(Calls::default()));
+        let mut vec = keyed_vec(&calls);
+
+        adjust(&mut vec, &["a", "b", "c"]);
+        assert_eq!(adjust(&mut vec, &["d", "b"]), vec![item(1, "d"), item(2, "b")]);
+        assert_eq!(calls.borrow().created, 3);
+        assert_eq!(calls.borrow().destroyed, vec!["c".to_owned()]);
+    }
 }